@@ -2,6 +2,250 @@
 pub enum SoilType {
     #[default]
     PottingSoil,
+    /// Mineral mix for cacti and succulents.
+    MineralMix,
+    /// Orchid bark.
+    Bark,
+    /// Peat based mix, e.g. for herbs.
+    Peat,
+    /// Expanded clay (LECA) and semi-hydroponics.
+    Leca,
+}
+
+impl std::fmt::Display for SoilType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SoilType::PottingSoil => write!(f, "Potting soil"),
+            SoilType::MineralMix => write!(f, "Mineral mix"),
+            SoilType::Bark => write!(f, "Bark"),
+            SoilType::Peat => write!(f, "Peat"),
+            SoilType::Leca => write!(f, "LECA"),
+        }
+    }
+}
+
+impl SoilType {
+    pub const ALL: [SoilType; 5] = [
+        SoilType::PottingSoil,
+        SoilType::MineralMix,
+        SoilType::Bark,
+        SoilType::Peat,
+        SoilType::Leca,
+    ];
+
+    /// Response curve of the substrate as sorted `(sensor percent, substrate moisture percent)` pairs.
+    ///
+    /// The sensor percent is the calibrated reading between air (0 %) and water (100 %).
+    /// Coarse substrates never get close to the water reading, even when saturated.
+    #[must_use]
+    pub fn curve(&self) -> &'static [(f32, f32)] {
+        match self {
+            SoilType::PottingSoil => &[(0.0, 0.0), (100.0, 100.0)],
+            SoilType::MineralMix => &[(0.0, 0.0), (40.0, 70.0), (60.0, 100.0)],
+            SoilType::Bark => &[(0.0, 0.0), (30.0, 60.0), (50.0, 100.0)],
+            SoilType::Peat => &[(0.0, 0.0), (50.0, 40.0), (100.0, 100.0)],
+            SoilType::Leca => &[(0.0, 0.0), (20.0, 50.0), (60.0, 100.0)],
+        }
+    }
+
+    /// Millilitres of water a millilitre of the substrate holds when it is at 100 % moisture.
+    #[must_use]
+    pub fn water_capacity(&self) -> f32 {
+        match self {
+            SoilType::PottingSoil => 0.5,
+            SoilType::MineralMix => 0.3,
+            SoilType::Bark => 0.35,
+            SoilType::Peat => 0.6,
+            SoilType::Leca => 0.25,
+        }
+    }
+
+    /// Default target band of the substrate.
+    #[must_use]
+    pub fn band(&self) -> MoistureBand {
+        match self {
+            SoilType::PottingSoil => MoistureBand {
+                very_dry_below: 15.0,
+                dry_below: 40.0,
+                moist_above: 60.0,
+                wet_above: 80.0,
+            },
+            SoilType::MineralMix => MoistureBand {
+                very_dry_below: 5.0,
+                dry_below: 15.0,
+                moist_above: 40.0,
+                wet_above: 60.0,
+            },
+            SoilType::Bark => MoistureBand {
+                very_dry_below: 15.0,
+                dry_below: 30.0,
+                moist_above: 60.0,
+                wet_above: 80.0,
+            },
+            SoilType::Peat => MoistureBand {
+                very_dry_below: 25.0,
+                dry_below: 45.0,
+                moist_above: 70.0,
+                wet_above: 85.0,
+            },
+            SoilType::Leca => MoistureBand {
+                very_dry_below: 10.0,
+                dry_below: 25.0,
+                moist_above: 60.0,
+                wet_above: 90.0,
+            },
+        }
+    }
+}
+
+/// Thresholds in percent used to classify a moisture value.
+///
+/// Values between `dry_below` and `moist_above` are perfect.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct MoistureBand {
+    pub very_dry_below: f32,
+    pub dry_below: f32,
+    pub moist_above: f32,
+    pub wet_above: f32,
+}
+
+impl MoistureBand {
+    #[must_use]
+    pub fn classify(&self, percent: f32) -> CalculatedMoisture {
+        if percent > self.wet_above {
+            CalculatedMoisture::Wet
+        } else if percent > self.moist_above {
+            CalculatedMoisture::Moist
+        } else if percent > self.dry_below {
+            CalculatedMoisture::Perfect
+        } else if percent > self.very_dry_below {
+            CalculatedMoisture::Dry
+        } else {
+            CalculatedMoisture::VeryDry
+        }
+    }
+}
+
+/// Care requirements of a plant, all values in percent of substrate moisture.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct CareProfile {
+    pub name: String,
+    /// Classification of the moisture, values between `dry_below` and `moist_above` are acceptable.
+    pub band: MoistureBand,
+    /// Below this the plant should be watered.
+    pub needs_water_below: f32,
+    /// How many days the substrate should be allowed to dry out before watering.
+    ///
+    /// The watering controller waits at least this long once the plant needs water.
+    pub dry_out_days: f32,
+}
+
+impl Default for CareProfile {
+    fn default() -> Self {
+        CareProfile {
+            name: "Custom".to_string(),
+            band: SoilType::PottingSoil.band(),
+            needs_water_below: 40.0,
+            dry_out_days: 2.0,
+        }
+    }
+}
+
+/// A care profile defined by the user and stored on the board.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, Default)]
+pub struct CustomCareProfile {
+    pub id: u16,
+    pub profile: CareProfile,
+}
+
+/// Selects the care profile of a plant.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize, Default)]
+pub enum CareProfileId {
+    /// No profile, the target band of the soil is used.
+    #[default]
+    Unspecified,
+    Succulent,
+    Fern,
+    TropicalFoliage,
+    Herb,
+    /// Id of a [`CustomCareProfile`] stored on the board.
+    Custom(u16),
+}
+
+impl std::fmt::Display for CareProfileId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CareProfileId::Unspecified => write!(f, "Soil default"),
+            CareProfileId::Succulent => write!(f, "Succulent"),
+            CareProfileId::Fern => write!(f, "Fern"),
+            CareProfileId::TropicalFoliage => write!(f, "Tropical foliage"),
+            CareProfileId::Herb => write!(f, "Herb"),
+            CareProfileId::Custom(id) => write!(f, "Custom {}", id),
+        }
+    }
+}
+
+impl CareProfileId {
+    pub const BUILTIN: [CareProfileId; 5] = [
+        CareProfileId::Unspecified,
+        CareProfileId::Succulent,
+        CareProfileId::Fern,
+        CareProfileId::TropicalFoliage,
+        CareProfileId::Herb,
+    ];
+
+    /// The profile of a built-in id, `None` for [`CareProfileId::Unspecified`] and custom profiles.
+    #[must_use]
+    pub fn builtin(&self) -> Option<CareProfile> {
+        let (name, band, needs_water_below, dry_out_days) = match self {
+            CareProfileId::Unspecified | CareProfileId::Custom(_) => return None,
+            CareProfileId::Succulent => ("Succulent", [5.0, 15.0, 40.0, 60.0], 8.0, 14.0),
+            CareProfileId::Fern => ("Fern", [30.0, 50.0, 75.0, 90.0], 50.0, 0.0),
+            CareProfileId::TropicalFoliage => {
+                ("Tropical foliage", [20.0, 40.0, 65.0, 85.0], 35.0, 3.0)
+            }
+            CareProfileId::Herb => ("Herb", [20.0, 40.0, 65.0, 80.0], 35.0, 1.0),
+        };
+        Some(CareProfile {
+            name: name.to_string(),
+            band: MoistureBand {
+                very_dry_below: band[0],
+                dry_below: band[1],
+                moist_above: band[2],
+                wet_above: band[3],
+            },
+            needs_water_below,
+            dry_out_days,
+        })
+    }
+
+    /// Looks up the profile, searching `custom` for custom profiles.
+    #[must_use]
+    pub fn resolve(&self, custom: &[CustomCareProfile]) -> Option<CareProfile> {
+        match self {
+            CareProfileId::Custom(id) => custom
+                .iter()
+                .find(|p| p.id == *id)
+                .map(|p| p.profile.clone()),
+            _ => self.builtin(),
+        }
+    }
+}
+
+/// Linear interpolation between points sorted by x, clamped to the first and last point.
+fn interpolate(points: &[(f32, f32)], x: f32) -> Option<f32> {
+    let first = points.first()?;
+    let last = points.last()?;
+    if x <= first.0 {
+        return Some(first.1);
+    }
+    if x >= last.0 {
+        return Some(last.1);
+    }
+    let upper = points.iter().position(|p| p.0 >= x)?;
+    let (x0, y0) = points[upper - 1];
+    let (x1, y1) = points[upper];
+    Some(y0 + (y1 - y0) * (x - x0) / (x1 - x0))
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
@@ -27,47 +271,602 @@ impl std::fmt::Display for CalculatedMoisture {
     }
 }
 
+/// A single known voltage/moisture pair of a sensor.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct CalibrationPoint {
+    pub voltage: f32,
+    pub percent: f32,
+}
+
+/// Per sensor calibration, mapping a measured voltage to a moisture percentage.
+///
+/// For digital sensors the raw reading of the sensor is used instead of a voltage.
+/// `dry_voltage` is the reading at 0 %, `wet_voltage` the reading at 100 %.
+/// `points` are optional intermediate points; between all points the mapping is linear.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Calibration {
+    pub dry_voltage: f32,
+    pub wet_voltage: f32,
+    pub points: Vec<CalibrationPoint>,
+}
+
+impl Default for Calibration {
+    /// Reproduces the fixed voltage thresholds used before sensors could be calibrated.
+    fn default() -> Self {
+        Calibration {
+            dry_voltage: 3000.0,
+            wet_voltage: 0.0,
+            points: vec![
+                CalibrationPoint {
+                    voltage: 500.0,
+                    percent: 80.0,
+                },
+                CalibrationPoint {
+                    voltage: 1000.0,
+                    percent: 60.0,
+                },
+                CalibrationPoint {
+                    voltage: 1500.0,
+                    percent: 40.0,
+                },
+                CalibrationPoint {
+                    voltage: 2500.0,
+                    percent: 15.0,
+                },
+            ],
+        }
+    }
+}
+
+/// Result of checking the spread between the dry and the wet reference of a [`Calibration`].
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum CalibrationCheck {
+    Plausible,
+    TooNarrow,
+    Inverted,
+}
+
+impl std::fmt::Display for CalibrationCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalibrationCheck::Plausible => write!(f, "Plausible"),
+            CalibrationCheck::TooNarrow => write!(f, "Dry and wet readings are too close"),
+            CalibrationCheck::Inverted => write!(f, "Dry and wet readings are swapped"),
+        }
+    }
+}
+
+/// Which reference of a [`Calibration`] is captured from the current reading.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum CalibrationReference {
+    /// Probe held in the air.
+    Dry,
+    /// Probe submerged in water.
+    Wet,
+}
+
+impl Calibration {
+    /// Smallest difference between the dry and the wet reference that is considered plausible.
+    pub const MIN_SPREAD: f32 = 300.0;
+
+    /// Creates a calibration from the two reference readings only.
+    #[must_use]
+    pub fn two_point(dry_voltage: f32, wet_voltage: f32) -> Calibration {
+        Calibration {
+            dry_voltage,
+            wet_voltage,
+            points: vec![],
+        }
+    }
+
+    /// Checks whether the dry and wet reference look like readings of a capacitive sensor on `connector`.
+    #[must_use]
+    pub fn check(&self, connector: &Connector) -> CalibrationCheck {
+        let spread = if connector.reading_rises_with_moisture() {
+            self.wet_voltage - self.dry_voltage
+        } else {
+            self.dry_voltage - self.wet_voltage
+        };
+        if spread < 0.0 {
+            CalibrationCheck::Inverted
+        } else if spread < Self::MIN_SPREAD {
+            CalibrationCheck::TooNarrow
+        } else {
+            CalibrationCheck::Plausible
+        }
+    }
+
+    /// Maps a voltage to a moisture percentage, clamped to 0–100 %.
+    ///
+    /// Returns `None` if the calibration can't be used, e.g. when the dry and wet references are equal.
+    #[must_use]
+    pub fn percent(&self, voltage: f32) -> Option<f32> {
+        if !voltage.is_finite() || (self.dry_voltage - self.wet_voltage).abs() < f32::EPSILON {
+            return None;
+        }
+        let mut points: Vec<(f32, f32)> = Vec::with_capacity(self.points.len() + 2);
+        points.push((self.dry_voltage, 0.0));
+        points.push((self.wet_voltage, 100.0));
+        points.extend(self.points.iter().map(|p| (p.voltage, p.percent)));
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        points.dedup_by(|a, b| (a.0 - b.0).abs() < f32::EPSILON);
+        interpolate(&points, voltage).map(|percent| percent.clamp(0.0, 100.0))
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, Default)]
 pub struct Moisture {
     pub measured_voltage: Option<f32>,
+    /// Volume of the pot in millilitres.
     pub pot_volume: Option<f32>,
     pub soil: SoilType,
+    #[serde(default)]
+    pub calibration: Calibration,
+    /// The resolved care profile of the plant, `None` if the soil defaults apply.
+    #[serde(default)]
+    pub profile: Option<CareProfile>,
 }
 
 impl Moisture {
+    /// The calibrated sensor reading in percent between air and water, if a value was measured.
+    #[must_use]
+    pub fn sensor_percent(&self) -> Option<f32> {
+        self.calibration.percent(self.measured_voltage?)
+    }
+
+    /// The moisture of the substrate in percent, based on the response curve of the soil.
+    #[must_use]
+    pub fn percent(&self) -> Option<f32> {
+        self.percent_of(self.measured_voltage?)
+    }
+
+    /// The moisture of the substrate in percent for a sensor reading.
+    #[must_use]
+    pub fn percent_of(&self, voltage: f32) -> Option<f32> {
+        interpolate(self.soil.curve(), self.calibration.percent(voltage)?)
+    }
+
+    /// The target band of the care profile, or of the soil if there is no profile.
+    #[must_use]
+    pub fn band(&self) -> MoistureBand {
+        match &self.profile {
+            Some(profile) => profile.band.clone(),
+            None => self.soil.band(),
+        }
+    }
+
+    /// The moisture percentage below which the plant should be watered.
+    #[must_use]
+    pub fn needs_water_below(&self) -> f32 {
+        match &self.profile {
+            Some(profile) => profile.needs_water_below,
+            None => self.soil.band().dry_below,
+        }
+    }
+
+    /// Seconds the substrate should stay dry before watering, see [`CareProfile::dry_out_days`].
+    #[must_use]
+    pub fn dry_out_seconds(&self) -> u64 {
+        self.profile.as_ref().map_or(0, |profile| {
+            (profile.dry_out_days.max(0.0) * 86_400.0) as u64
+        })
+    }
+
+    /// The moisture percentage aimed for when watering, the middle of the acceptable band.
+    #[must_use]
+    pub fn target_percent(&self) -> f32 {
+        let band = self.band();
+        (band.dry_below + band.moist_above) / 2.0
+    }
+
+    /// Estimated water in the pot in millilitres, requires the pot volume.
+    #[must_use]
+    pub fn water_content_ml(&self) -> Option<f32> {
+        Some(self.pot_volume? * self.soil.water_capacity() * self.percent()? / 100.0)
+    }
+
+    /// Millilitres of water to add to reach the target moisture, 0 if the plant is moist enough.
+    #[must_use]
+    pub fn recommended_water_ml(&self) -> Option<f32> {
+        let missing = (self.target_percent() - self.percent()?).max(0.0);
+        Some(self.pot_volume? * self.soil.water_capacity() * missing / 100.0)
+    }
+
+    #[must_use]
+    pub fn needs_water(&self) -> Option<bool> {
+        Some(self.percent()? < self.needs_water_below())
+    }
+
     #[must_use]
     pub fn calulated_moisture(&self) -> CalculatedMoisture {
-        match self.measured_voltage {
-            None => return CalculatedMoisture::Unknown,
-            Some(volatage) => {
-                if volatage < 500.0 {
-                    return CalculatedMoisture::Wet;
-                } else if volatage < 1000.0 {
-                    return CalculatedMoisture::Moist;
-                } else if volatage < 1500.0 {
-                    return CalculatedMoisture::Perfect;
-                } else if volatage < 2500.0 {
-                    return CalculatedMoisture::Dry;
-                } else {
-                    return CalculatedMoisture::VeryDry;
-                }
-            }
+        match self.percent() {
+            None => CalculatedMoisture::Unknown,
+            Some(percent) => self.band().classify(percent),
+        }
+    }
+
+    /// Fits a line to the moisture since the last watering, `now` is the time of the prediction.
+    ///
+    /// The slope is the median of the slopes between all pairs of points (Theil-Sen estimator),
+    /// so single outliers and sensor glitches don't bend the line. `None` if the history is too
+    /// short to tell.
+    #[must_use]
+    pub fn drying_trend(&self, history: &[HistoryPoint], now: u64) -> Option<DryingTrend> {
+        let points: Vec<(f64, f64)> = history
+            .iter()
+            .filter_map(|point| {
+                let percent = self.percent_of(point.measured_voltage)?;
+                Some((point.time as f64 / 86_400.0, percent as f64))
+            })
+            .collect();
+        // a steep rise is a watering, the soil only dries out after it
+        let watered = points
+            .windows(2)
+            .rposition(|pair| pair[1].1 - pair[0].1 > WATERING_RISE)
+            .map_or(0, |index| index + 1);
+        let points = &points[watered..];
+        let span = points.last()?.0 - points.first()?.0;
+        if points.len() < MIN_TREND_POINTS || span < MIN_TREND_DAYS {
+            return None;
         }
+
+        let mut slopes: Vec<f64> = points
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| points[i + 1..].iter().map(move |b| (a, b)))
+            .filter(|(a, b)| b.0 > a.0)
+            .map(|(a, b)| (b.1 - a.1) / (b.0 - a.0))
+            .collect();
+        let slope = median(&mut slopes)?;
+        let mut intercepts: Vec<f64> = points.iter().map(|p| p.1 - slope * p.0).collect();
+        let intercept = median(&mut intercepts)?;
+
+        let now = now as f64 / 86_400.0;
+        let threshold = self.needs_water_below() as f64;
+        let needs_water_at = if intercept + slope * now < threshold {
+            Some(now)
+        } else if slope < 0.0 {
+            Some((threshold - intercept) / slope)
+        } else {
+            None
+        };
+        Some(DryingTrend {
+            percent_per_day: slope as f32,
+            needs_water_at: needs_water_at.map(|day| (day * 86_400.0) as u64),
+        })
+    }
+}
+
+/// Rise of the substrate moisture in percentage points between two readings that counts as a
+/// watering.
+pub const WATERING_RISE: f64 = 10.0;
+/// Readings of the last days the drying trend is fitted to.
+pub const TREND_WINDOW_DAYS: u64 = 3;
+const MIN_TREND_POINTS: usize = 6;
+/// Shortest time since the last watering a trend is fitted to, a quarter of a day.
+const MIN_TREND_DAYS: f64 = 0.25;
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    match values.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((values[middle - 1] + values[middle]) / 2.0),
+        _ => Some(values[middle]),
+    }
+}
+
+/// How fast the substrate of a plant dries out.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct DryingTrend {
+    /// Change of the substrate moisture in percentage points per day, negative while drying.
+    pub percent_per_day: f32,
+    /// When the moisture falls below [`Moisture::needs_water_below`], in seconds since the Unix
+    /// epoch. `None` if the substrate doesn't dry out.
+    pub needs_water_at: Option<u64>,
+}
+
+impl DryingTrend {
+    /// Seconds until the plant needs water, 0 if it needs water already.
+    #[must_use]
+    pub fn needs_water_in(&self, now: u64) -> Option<u64> {
+        Some(self.needs_water_at?.saturating_sub(now))
     }
 }
 
-#[derive(Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize, Debug)]
 pub enum Connector {
     GPIO(u8),
-    //I2C
+    /// A digital capacitive soil sensor (Adafruit seesaw) on an I2C bus.
+    ///
+    /// `channel` selects the port of a TCA9548A multiplexer, `None` if the sensor is connected directly.
+    I2c {
+        bus: u8,
+        address: u8,
+        channel: Option<u8>,
+    },
 }
 impl Default for Connector {
     fn default() -> Self {
         Connector::GPIO(32)
     }
-    
 }
 
+impl std::fmt::Display for Connector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Connector::GPIO(pin) => write!(f, "GPIO {}", pin),
+            Connector::I2c {
+                bus,
+                address,
+                channel: None,
+            } => write!(f, "I2C {} 0x{:02x}", bus, address),
+            Connector::I2c {
+                bus,
+                address,
+                channel: Some(channel),
+            } => write!(f, "I2C {} 0x{:02x} channel {}", bus, address, channel),
+        }
+    }
+}
+
+impl Connector {
+    /// Default I2C address of the seesaw soil sensor.
+    pub const SEESAW_ADDRESS: u8 = 0x36;
+
+    /// Analog sensors read lower voltages when wet, the digital sensors report higher values.
+    #[must_use]
+    pub fn reading_rises_with_moisture(&self) -> bool {
+        matches!(self, Connector::I2c { .. })
+    }
+
+    /// A calibration matching the typical readings of the sensor type.
+    #[must_use]
+    pub fn default_calibration(&self) -> Calibration {
+        match self {
+            Connector::GPIO(_) => Calibration::default(),
+            Connector::I2c { .. } => Calibration::two_point(300.0, 1000.0),
+        }
+    }
+
+    /// Whether two sensors can't be read independently.
+    ///
+    /// A sensor directly on a bus also answers while a multiplexer channel with a sensor of the
+    /// same address is selected.
+    #[must_use]
+    pub fn conflicts_with(&self, other: &Connector) -> bool {
+        match (self, other) {
+            (
+                Connector::I2c {
+                    bus,
+                    address,
+                    channel,
+                },
+                Connector::I2c {
+                    bus: other_bus,
+                    address: other_address,
+                    channel: other_channel,
+                },
+            ) => {
+                bus == other_bus
+                    && address == other_address
+                    && (channel.is_none() || other_channel.is_none() || channel == other_channel)
+            }
+            _ => self == other,
+        }
+    }
+}
+
+/// What a GPIO of the board can be used for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum PinCapability {
+    /// Sampled by ADC1, the only ADC usable for sensors.
+    Adc1,
+    /// Sampled by ADC2, which is unavailable while Wi-Fi is running.
+    Adc2,
+    /// Digital input and output only.
+    Digital,
+    /// Decides the boot mode, a sensor could keep the board from starting.
+    Strapping,
+    /// Used by one of the I2C buses of the board.
+    I2c,
+    /// Connected to the flash or the serial console, or not available at all.
+    Reserved,
+}
+
+impl std::fmt::Display for PinCapability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PinCapability::Adc1 => write!(f, "ADC1"),
+            PinCapability::Adc2 => write!(f, "ADC2, unavailable with Wi-Fi"),
+            PinCapability::Digital => write!(f, "digital only"),
+            PinCapability::Strapping => write!(f, "strapping pin"),
+            PinCapability::I2c => write!(f, "used by I2C"),
+            PinCapability::Reserved => write!(f, "reserved"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum ConnectorStatus {
+    Free,
+    /// Used by the plant with the id.
+    Used(u16),
+    /// Can't be read by the board.
+    Unsupported,
+}
+
+/// A connector as listed by `GET /connectors`.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ConnectorInfo {
+    pub connector: Connector,
+    /// The capability of a GPIO, `None` for I2C connectors.
+    pub capability: Option<PinCapability>,
+    pub status: ConnectorStatus,
+}
+
+/// What an actuator does when it is switched on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize, Default)]
+pub enum ActuatorKind {
+    #[default]
+    Pump,
+    /// A solenoid valve on a pressurized line.
+    Valve,
+}
+
+impl std::fmt::Display for ActuatorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActuatorKind::Pump => write!(f, "Pump"),
+            ActuatorKind::Valve => write!(f, "Valve"),
+        }
+    }
+}
+
+/// A flow sensor that sends a pulse for every few drops of water.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct FlowSensor {
+    pub pin: u8,
+    /// About 450 for the common YF-S201.
+    pub pulses_per_litre: f32,
+}
+
+/// A pump or valve switched by a relay or MOSFET on a GPIO.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, Default)]
+pub struct Actuator {
+    #[serde(default)]
+    pub kind: ActuatorKind,
+    pub pin: u8,
+    /// Whether it is switched on by a low pin, like most relay modules.
+    #[serde(default)]
+    pub active_low: bool,
+    /// Measured flow in ml/s, used to deliver volumes without a flow sensor.
+    #[serde(default)]
+    pub flow_rate: Option<f32>,
+    #[serde(default)]
+    pub flow_sensor: Option<FlowSensor>,
+}
+
+/// An actuator of a plant as sent to clients.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ActuatorInfo {
+    pub actuator: Actuator,
+    pub running: bool,
+    /// Seconds until the safety limits allow the next run, 0 if it can run now.
+    pub blocked_for: u64,
+}
+
+/// How long to run an actuator, the board rejects runs beyond its safety limits.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum RunRequest {
+    Seconds(f32),
+    /// Needs a flow sensor or a known flow rate.
+    Millilitres(f32),
+}
+
+/// Settings of the controller that waters a plant on its own.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ControllerSettings {
+    /// Index of the actuator of the plant that waters it.
+    #[serde(default)]
+    pub actuator: u8,
+    /// Only records what it would do, the actuator is never started.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Seconds the moisture has to stay below the watering threshold before the plant is watered.
+    ///
+    /// The dry out days of the care profile of the plant are waited if they are longer.
+    pub dry_for: u32,
+    /// Seconds to wait for the water to soak in before measuring again.
+    pub soak_for: u32,
+    /// Most water given at once, less if the pot volume tells that less is missing.
+    pub max_dose_ml: f32,
+    /// Most water given within 24 hours.
+    pub daily_budget_ml: f32,
+}
+
+impl Default for ControllerSettings {
+    fn default() -> Self {
+        ControllerSettings {
+            actuator: 0,
+            dry_run: true,
+            dry_for: 30 * 60,
+            soak_for: 30 * 60,
+            max_dose_ml: 100.0,
+            daily_budget_ml: 500.0,
+        }
+    }
+}
+
+/// Why a controller stopped watering.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum LockoutReason {
+    /// The sensor sent no readings for a while.
+    SensorFault,
+    /// The moisture didn't rise after watering, the reservoir might be empty.
+    NoRise {
+        moisture_before: f32,
+        moisture_after: f32,
+    },
+}
+
+impl std::fmt::Display for LockoutReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockoutReason::SensorFault => write!(f, "the sensor sends no readings"),
+            LockoutReason::NoRise {
+                moisture_before,
+                moisture_after,
+            } => write!(
+                f,
+                "the moisture didn't rise after watering ({:.0} % → {:.0} %), is the reservoir empty?",
+                moisture_before, moisture_after
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum ControllerStatus {
+    Idle,
+    /// Below the watering threshold for `seconds`.
+    Dry {
+        seconds: u64,
+    },
+    /// Waiting for the water to soak in.
+    Soaking {
+        seconds_left: u64,
+    },
+    /// Stopped until it is reset.
+    LockedOut(LockoutReason),
+}
+
+/// The controller of a plant as sent to clients.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ControllerInfo {
+    pub settings: ControllerSettings,
+    pub status: ControllerStatus,
+    /// Water given within the last 24 hours, including the doses of a dry run.
+    pub given_today_ml: f32,
+}
+
+/// Maximum number of actuators of a plant.
+pub const MAX_ACTUATORS: usize = 4;
+/// Longest run of an actuator in seconds, also when a volume was requested.
+pub const MAX_RUN_SECONDS: u32 = 120;
+/// Maximum number of runs of an actuator within 24 hours.
+pub const MAX_RUNS_PER_DAY: usize = 12;
+/// Seconds from the start of a run until the actuator can run again.
+pub const MIN_RUN_INTERVAL: u64 = 10 * 60;
+
+/// Maximum length of plant, board and care profile names and of board locations in bytes.
+pub const MAX_NAME_LENGTH: usize = 32;
+/// Maximum length of the description of a board in bytes.
+pub const MAX_DESCRIPTION_LENGTH: usize = 200;
+/// Maximum length of the icon of a board in bytes, enough for an emoji with modifiers.
+pub const MAX_ICON_LENGTH: usize = 16;
+/// Maximum length of host names, like the SNTP server, in bytes.
+pub const MAX_HOST_LENGTH: usize = 64;
 
 #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, Default)]
 pub struct PlantInfo {
@@ -75,12 +874,395 @@ pub struct PlantInfo {
     pub name: String,
     pub measured_moisture: Moisture,
     pub connection: Connector,
+    #[serde(default)]
+    pub care_profile: CareProfileId,
+    /// Temperature in °C, if reported by the sensor.
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// When the sensor was last read, `None` if it wasn't read yet.
+    #[serde(default)]
+    pub measured_at: Option<Timestamp>,
+    /// `None` until the board has a few hours of history since the last watering.
+    #[serde(default)]
+    pub drying: Option<DryingTrend>,
+    #[serde(default)]
+    pub actuators: Vec<ActuatorInfo>,
+    /// `None` if the plant isn't watered automatically.
+    #[serde(default)]
+    pub controller: Option<ControllerInfo>,
+}
+
+/// A point in time as known by a board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Timestamp {
+    /// Seconds since the Unix epoch if `synced`, otherwise seconds since the board started.
+    pub seconds: u64,
+    /// Whether the board got the time from an SNTP server.
+    pub synced: bool,
+}
+
+impl Timestamp {
+    /// Seconds from `self` to `now`, `None` if only one of them is synced.
+    #[must_use]
+    pub fn age_at(&self, now: &Timestamp) -> Option<u64> {
+        (self.synced == now.synced).then(|| now.seconds.saturating_sub(self.seconds))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct BoardState {
     pub name: String,
     pub plants: Vec<PlantInfo>,
+    #[serde(default)]
+    pub care_profiles: Vec<CustomCareProfile>,
+    /// When the state was taken, `None` for boards without a clock.
+    #[serde(default)]
+    pub time: Option<Timestamp>,
+    /// The SNTP server the board gets the time from.
+    #[serde(default)]
+    pub sntp_server: Option<String>,
+    #[serde(default)]
+    pub metadata: BoardMetadata,
+}
+
+/// Details set by the user to tell boards apart, empty for boards that don't keep them.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize, Default)]
+pub struct BoardMetadata {
+    /// The room or place of the board, like `Kitchen`.
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub description: String,
+    /// RGB colour the board is marked with.
+    #[serde(default)]
+    pub color: Option<[u8; 3]>,
+    /// An emoji or symbol shown before the name.
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+/// UDP port boards answer discovery probes on.
+pub const DISCOVERY_PORT: u16 = 47_815;
+/// Datagram clients send to find boards, usually to the broadcast address.
+pub const DISCOVERY_PROBE: &[u8] = b"plant-board-probe";
+/// Version of the HTTP API of the boards, increased with incompatible changes.
+pub const API_VERSION: u16 = 1;
+
+/// Answer of a board to a discovery probe, sent as JSON.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct BoardAnnouncement {
+    pub name: String,
+    pub firmware_version: String,
+    pub api_version: u16,
+    /// Address the HTTP API is served on, the sender of the announcement if `None`.
+    #[serde(default)]
+    pub address: Option<std::net::Ipv4Addr>,
+    pub port: u16,
+}
+
+/// Number of Wi-Fi networks a board remembers.
+pub const MAX_WIFI_NETWORKS: usize = 5;
+/// SSIDs are limited to 32 bytes by the Wi-Fi standard.
+pub const MAX_SSID_LENGTH: usize = 32;
+/// Address of a board in its own access point, where it can be given a network to join.
+pub const PROVISIONING_ADDRESS: std::net::Ipv4Addr = std::net::Ipv4Addr::new(192, 168, 71, 1);
+
+/// A network the board joins, sent to the board to add or replace the network with the SSID.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize, Default)]
+pub struct WifiNetwork {
+    pub ssid: String,
+    /// WPA passphrase of 8 to 63 characters or 64 hex digits, empty for open networks.
+    #[serde(default)]
+    pub password: String,
+}
+
+/// A network the board remembers, the password is never sent back.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct KnownNetwork {
+    pub ssid: String,
+    pub secured: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize, Default)]
+pub enum WifiMode {
+    /// The board doesn't manage its network, like simulated boards.
+    #[default]
+    Unmanaged,
+    /// Trying to join one of the known networks.
+    Joining,
+    Connected {
+        ssid: String,
+    },
+    /// The access point for provisioning is open, the known networks are retried meanwhile.
+    AccessPoint {
+        ssid: String,
+    },
+}
+
+/// Sent by `GET /wifi`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize, Default)]
+pub struct WifiStatus {
+    pub mode: WifiMode,
+    /// In the order they are tried.
+    pub networks: Vec<KnownNetwork>,
+}
+
+/// Everything about a plant that can be set by the user, used to create or replace a plant.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, Default)]
+pub struct PlantSettings {
+    pub name: String,
+    pub connection: Connector,
+    #[serde(default)]
+    pub soil: SoilType,
+    #[serde(default)]
+    pub pot_volume: Option<f32>,
+    #[serde(default)]
+    pub care_profile: CareProfileId,
+    #[serde(default)]
+    pub actuators: Vec<Actuator>,
+    #[serde(default)]
+    pub controller: Option<ControllerSettings>,
+}
+
+impl From<PlantInfo> for PlantSettings {
+    fn from(plant: PlantInfo) -> Self {
+        PlantSettings {
+            name: plant.name,
+            connection: plant.connection,
+            soil: plant.measured_moisture.soil,
+            pot_volume: plant.measured_moisture.pot_volume,
+            care_profile: plant.care_profile,
+            actuators: plant.actuators.into_iter().map(|a| a.actuator).collect(),
+            controller: plant.controller.map(|c| c.settings),
+        }
+    }
+}
+
+/// Partial update of a plant, fields that are `None` are left unchanged.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, Default)]
+pub struct PlantUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection: Option<Connector>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub soil: Option<SoilType>,
+    /// `Some(None)` (`null`) clears the pot volume.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub pot_volume: Option<Option<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub care_profile: Option<CareProfileId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actuators: Option<Vec<Actuator>>,
+    /// `Some(None)` (`null`) stops watering the plant automatically.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub controller: Option<Option<ControllerSettings>>,
+}
+
+impl From<PlantSettings> for PlantUpdate {
+    fn from(settings: PlantSettings) -> Self {
+        PlantUpdate {
+            name: Some(settings.name),
+            connection: Some(settings.connection),
+            soil: Some(settings.soil),
+            pot_volume: Some(settings.pot_volume),
+            care_profile: Some(settings.care_profile),
+            actuators: Some(settings.actuators),
+            controller: Some(settings.controller),
+        }
+    }
+}
+
+/// Tells a present `null` apart from a missing field.
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Partial update of the board, fields that are `None` are left unchanged.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, Default)]
+pub struct BoardUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sntp_server: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// `Some(None)` (`null`) removes the colour.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub color: Option<Option<[u8; 3]>>,
+    /// `Some(None)` (`null`) removes the icon.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub icon: Option<Option<String>>,
+}
+
+/// Interval of the averaged values of a measurement history.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize, Default,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryResolution {
+    /// Kept for a day.
+    #[default]
+    Minute,
+    /// Kept for a month.
+    Hour,
+    /// Kept for a year.
+    Day,
+}
+
+impl HistoryResolution {
+    pub const ALL: [HistoryResolution; 3] = [
+        HistoryResolution::Minute,
+        HistoryResolution::Hour,
+        HistoryResolution::Day,
+    ];
+
+    #[must_use]
+    pub fn seconds(&self) -> u64 {
+        match self {
+            HistoryResolution::Minute => 60,
+            HistoryResolution::Hour => 60 * 60,
+            HistoryResolution::Day => 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct HistoryPoint {
+    /// Start of the interval in seconds since the Unix epoch.
+    pub time: u64,
+    /// Average sensor reading over the interval, see [`Moisture::measured_voltage`].
+    pub measured_voltage: f32,
+}
+
+/// Query of `GET /plants/{id}/history`, times are in seconds since the Unix epoch.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, Default)]
+pub struct HistoryQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<u64>,
+    /// Picked by the board if `None`, the finest resolution that still covers `from`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<HistoryResolution>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct History {
+    pub plant: u16,
+    pub resolution: HistoryResolution,
+    /// Oldest first, intervals without measurements are left out.
+    pub points: Vec<HistoryPoint>,
+}
+
+/// A change a board pushes to the clients of `GET /live`, sent as server-sent events with the
+/// update as JSON data.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum LiveUpdate {
+    /// The whole state, sent first and whenever plants, care profiles or the board change.
+    State(BoardState),
+    /// A plant after a new reading or after one of its actuators or its controller changed.
+    Plant(PlantInfo),
+    Event(PlantEvent),
+}
+
+/// Something that happened to a plant, as recorded by its board.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct PlantEvent {
+    /// Increases with every event of a board.
+    pub id: u32,
+    pub plant: u16,
+    pub time: Timestamp,
+    pub kind: PlantEventKind,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum PlantEventKind {
+    /// The board saw the moisture rise sharply, moistures are substrate moistures in percent.
+    Watered {
+        moisture_before: Option<f32>,
+        moisture_after: Option<f32>,
+        /// Estimated from the rise of the moisture, requires the pot volume.
+        amount_ml: Option<f32>,
+    },
+    /// The controller of the plant watered it, or would have in a dry run.
+    Pumped {
+        amount_ml: f32,
+        moisture_before: f32,
+        dry_run: bool,
+    },
+    /// The controller stopped watering until it is reset.
+    LockedOut(LockoutReason),
+    /// The controller didn't water, the daily budget is used up.
+    BudgetExhausted { budget_ml: f32 },
+    /// The controller was reset after a lockout.
+    ControllerReset,
+    /// Someone reported watering the plant by hand.
+    WateredByHand { amount_ml: Option<f32> },
+}
+
+/// Body of `POST /plants/{id}/watered`, reports watering a plant by hand.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, Default)]
+pub struct ManualWatering {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount_ml: Option<f32>,
+}
+
+/// Query of `GET /events`.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, Default)]
+pub struct EventQuery {
+    /// Only events of this plant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plant: Option<u16>,
+    /// Only events with a greater id, to fetch the events added since the last query.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct CalibrationRequest {
+    pub id: u16,
+    pub reference: CalibrationReference,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SoilRequest {
+    pub id: u16,
+    pub soil: SoilType,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct PotVolumeRequest {
+    pub id: u16,
+    pub pot_volume: Option<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct CareProfileRequest {
+    pub id: u16,
+    pub profile: CareProfileId,
 }
 
 #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -88,13 +1270,225 @@ pub enum OkStatus {
     Empty,
     Created,
     Deleted,
+    Calibrated(CalibrationCheck),
+    Updated,
+    Started,
+    Stopped,
+    Recorded,
 }
 
 #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum ErrStatus {
     BadRequest,
+    PlantNotFound(u16),
+    CareProfileNotFound(u16),
+    /// The care profile is still used by a plant.
+    CareProfileInUse {
+        profile: u16,
+        plant: u16,
+    },
+    /// Another plant is already connected to the connector.
+    ConnectorInUse {
+        connector: Connector,
+        plant: u16,
+    },
+    /// The pin can't be used for an analog sensor.
+    PinNotAdcCapable {
+        pin: u8,
+        capability: PinCapability,
+    },
+    /// The I2C bus, address or multiplexer channel doesn't exist.
+    InvalidConnector(Connector),
+    /// Names are limited to [`MAX_NAME_LENGTH`] bytes.
+    NameTooLong,
+    /// Descriptions are limited to [`MAX_DESCRIPTION_LENGTH`] bytes.
+    DescriptionTooLong,
+    /// Icons must not be empty and are limited to [`MAX_ICON_LENGTH`] bytes.
+    InvalidIcon,
+    /// Host names must not be empty and are limited to [`MAX_HOST_LENGTH`] bytes.
+    InvalidHost(String),
+    /// The plant has no measurements yet, so nothing can be calibrated.
+    NoMeasurements(u16),
+    ActuatorNotFound {
+        plant: u16,
+        actuator: u8,
+    },
+    /// The pin can't drive an actuator or count the pulses of a flow sensor.
+    PinNotSwitchable {
+        pin: u8,
+        capability: PinCapability,
+    },
+    /// Plants are limited to [`MAX_ACTUATORS`] actuators.
+    TooManyActuators,
+    /// A volume was requested from an actuator without flow sensor or flow rate.
+    FlowUnknown {
+        plant: u16,
+        actuator: u8,
+    },
+    /// The plant isn't watered automatically.
+    NoController(u16),
+    /// Runs are limited to [`MAX_RUN_SECONDS`].
+    RunTooLong {
+        seconds: f32,
+    },
+    ActuatorRunning {
+        plant: u16,
+        actuator: u8,
+    },
+    /// The actuator ran less than [`MIN_RUN_INTERVAL`] ago.
+    ActuatorCoolingDown {
+        seconds: u64,
+    },
+    /// The actuator ran [`MAX_RUNS_PER_DAY`] times within the last 24 hours.
+    DailyRunLimit {
+        seconds: u64,
+    },
+    /// SSIDs must not be empty and are limited to [`MAX_SSID_LENGTH`] bytes.
+    InvalidSsid,
+    /// WPA passphrases have 8 to 63 printable ASCII characters or are 64 hex digits.
+    InvalidPassword,
+    /// Boards remember at most [`MAX_WIFI_NETWORKS`] networks.
+    TooManyNetworks,
+    NetworkNotFound(String),
+    /// Only the Wi-Fi can be set up while the access point is open, it has no password.
+    Provisioning,
+    StorageFull,
+    StorageWriteFailed(String),
+}
+
+impl ErrStatus {
+    /// The HTTP status code the error is sent with.
+    #[must_use]
+    pub fn http_status(&self) -> u16 {
+        match self {
+            ErrStatus::BadRequest => 400,
+            ErrStatus::PlantNotFound(_)
+            | ErrStatus::CareProfileNotFound(_)
+            | ErrStatus::ActuatorNotFound { .. }
+            | ErrStatus::NoController(_)
+            | ErrStatus::NetworkNotFound(_) => 404,
+            ErrStatus::CareProfileInUse { .. }
+            | ErrStatus::ConnectorInUse { .. }
+            | ErrStatus::NoMeasurements(_)
+            | ErrStatus::ActuatorRunning { .. } => 409,
+            ErrStatus::PinNotAdcCapable { .. }
+            | ErrStatus::InvalidConnector(_)
+            | ErrStatus::NameTooLong
+            | ErrStatus::DescriptionTooLong
+            | ErrStatus::InvalidIcon
+            | ErrStatus::InvalidHost(_)
+            | ErrStatus::PinNotSwitchable { .. }
+            | ErrStatus::TooManyActuators
+            | ErrStatus::FlowUnknown { .. }
+            | ErrStatus::RunTooLong { .. }
+            | ErrStatus::InvalidSsid
+            | ErrStatus::InvalidPassword
+            | ErrStatus::TooManyNetworks => 422,
+            ErrStatus::Provisioning => 403,
+            ErrStatus::ActuatorCoolingDown { .. } | ErrStatus::DailyRunLimit { .. } => 429,
+            ErrStatus::StorageWriteFailed(_) => 500,
+            ErrStatus::StorageFull => 507,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrStatus::BadRequest => write!(f, "The request is invalid"),
+            ErrStatus::PlantNotFound(id) => write!(f, "There is no plant with id {}", id),
+            ErrStatus::CareProfileNotFound(id) => {
+                write!(f, "There is no care profile with id {}", id)
+            }
+            ErrStatus::CareProfileInUse { profile, plant } => write!(
+                f,
+                "Care profile {} is still used by plant {}",
+                profile, plant
+            ),
+            ErrStatus::ConnectorInUse { connector, plant } => {
+                write!(f, "{} is already used by plant {}", connector, plant)
+            }
+            ErrStatus::PinNotAdcCapable { pin, capability } => write!(
+                f,
+                "GPIO {} can't measure analog sensors, it is {}",
+                pin, capability
+            ),
+            ErrStatus::InvalidConnector(connector) => {
+                write!(f, "{} doesn't exist on the board", connector)
+            }
+            ErrStatus::NameTooLong => {
+                write!(f, "Names can't be longer than {} bytes", MAX_NAME_LENGTH)
+            }
+            ErrStatus::DescriptionTooLong => write!(
+                f,
+                "Descriptions can't be longer than {} bytes",
+                MAX_DESCRIPTION_LENGTH
+            ),
+            ErrStatus::InvalidIcon => write!(
+                f,
+                "Icons must have 1 to {} bytes, like a single emoji",
+                MAX_ICON_LENGTH
+            ),
+            ErrStatus::InvalidHost(host) => write!(f, "'{}' is not a valid host name", host),
+            ErrStatus::NoMeasurements(id) => write!(f, "Plant {} has no measurements yet", id),
+            ErrStatus::ActuatorNotFound { plant, actuator } => {
+                write!(f, "Plant {} has no actuator {}", plant, actuator)
+            }
+            ErrStatus::PinNotSwitchable { pin, capability } => write!(
+                f,
+                "GPIO {} can't be used for actuators or flow sensors, it is {}",
+                pin, capability
+            ),
+            ErrStatus::TooManyActuators => {
+                write!(f, "Plants can't have more than {} actuators", MAX_ACTUATORS)
+            }
+            ErrStatus::FlowUnknown { plant, actuator } => write!(
+                f,
+                "Actuator {} of plant {} has neither a flow sensor nor a flow rate",
+                actuator, plant
+            ),
+            ErrStatus::NoController(id) => {
+                write!(f, "Plant {} isn't watered automatically", id)
+            }
+            ErrStatus::RunTooLong { seconds } => write!(
+                f,
+                "A run of {:.0} s is longer than the limit of {} s",
+                seconds, MAX_RUN_SECONDS
+            ),
+            ErrStatus::ActuatorRunning { plant, actuator } => {
+                write!(f, "Actuator {} of plant {} is running", actuator, plant)
+            }
+            ErrStatus::ActuatorCoolingDown { seconds } => {
+                write!(f, "The actuator can run again in {} s", seconds)
+            }
+            ErrStatus::DailyRunLimit { seconds } => write!(
+                f,
+                "The actuator ran {} times today, it can run again in {} s",
+                MAX_RUNS_PER_DAY, seconds
+            ),
+            ErrStatus::InvalidSsid => write!(f, "SSIDs must have 1 to {} bytes", MAX_SSID_LENGTH),
+            ErrStatus::InvalidPassword => write!(
+                f,
+                "Wi-Fi passwords have 8 to 63 characters or are 64 hex digits"
+            ),
+            ErrStatus::TooManyNetworks => write!(
+                f,
+                "The board can't remember more than {} networks",
+                MAX_WIFI_NETWORKS
+            ),
+            ErrStatus::NetworkNotFound(ssid) => write!(f, "The board doesn't know '{}'", ssid),
+            ErrStatus::Provisioning => write!(
+                f,
+                "Only the Wi-Fi can be set up while the access point of the board is open"
+            ),
+            ErrStatus::StorageFull => write!(f, "The storage of the board is full"),
+            ErrStatus::StorageWriteFailed(e) => write!(f, "Writing to the storage failed: {}", e),
+        }
+    }
 }
 
+impl std::error::Error for ErrStatus {}
+
 #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum ReplyStatus {
     Ok(OkStatus),
@@ -104,5 +1498,180 @@ pub enum ReplyStatus {
 #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct Reply {
     pub status: ReplyStatus,
+    /// Human-readable description of an error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
     pub state: BoardState,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    /// Potting soil with a linear analog sensor reading 3000 mV in the air and 0 mV in water.
+    fn moisture(percent: f32) -> Moisture {
+        Moisture {
+            measured_voltage: Some(3000.0 - percent * 30.0),
+            pot_volume: Some(1000.0),
+            soil: SoilType::PottingSoil,
+            calibration: Calibration::two_point(3000.0, 0.0),
+            profile: None,
+        }
+    }
+
+    #[test]
+    fn interpolates_and_clamps() {
+        let points = [(0.0, 0.0), (10.0, 50.0), (20.0, 60.0)];
+        assert_eq!(interpolate(&points, 5.0), Some(25.0));
+        assert_eq!(interpolate(&points, 15.0), Some(55.0));
+        assert_eq!(interpolate(&points, -5.0), Some(0.0));
+        assert_eq!(interpolate(&points, 25.0), Some(60.0));
+        assert_eq!(interpolate(&[], 5.0), None);
+    }
+
+    #[test]
+    fn calibrated_percent() {
+        let calibration = Calibration::default();
+        assert_close(calibration.percent(1000.0).unwrap(), 60.0);
+        assert_close(calibration.percent(1250.0).unwrap(), 50.0);
+        // beyond the references the percentage is clamped
+        let calibration = Calibration::two_point(3000.0, 1000.0);
+        assert_eq!(calibration.percent(3500.0), Some(0.0));
+        assert_eq!(calibration.percent(500.0), Some(100.0));
+        assert_close(calibration.percent(2500.0).unwrap(), 25.0);
+        assert_eq!(calibration.percent(f32::NAN), None);
+    }
+
+    #[test]
+    fn equal_references_are_unusable() {
+        let calibration = Calibration::two_point(1500.0, 1500.0);
+        assert_eq!(calibration.percent(1500.0), None);
+        assert_eq!(
+            calibration.check(&Connector::GPIO(32)),
+            CalibrationCheck::TooNarrow
+        );
+    }
+
+    #[test]
+    fn inverted_calibrations() {
+        let analog = Connector::GPIO(32);
+        let digital = Connector::I2c {
+            bus: 0,
+            address: Connector::SEESAW_ADDRESS,
+            channel: None,
+        };
+        assert_eq!(
+            Calibration::default().check(&analog),
+            CalibrationCheck::Plausible
+        );
+        assert_eq!(
+            Calibration::two_point(1000.0, 900.0).check(&analog),
+            CalibrationCheck::TooNarrow
+        );
+        let rising = Calibration::two_point(300.0, 1000.0);
+        assert_eq!(rising.check(&analog), CalibrationCheck::Inverted);
+        assert_eq!(rising.check(&digital), CalibrationCheck::Plausible);
+        assert_eq!(
+            Calibration::two_point(1000.0, 300.0).check(&digital),
+            CalibrationCheck::Inverted
+        );
+        // the mapping itself works in both directions
+        assert_close(rising.percent(475.0).unwrap(), 25.0);
+    }
+
+    #[test]
+    fn soil_curves() {
+        for soil in SoilType::ALL {
+            let curve = soil.curve();
+            assert_eq!(curve.first(), Some(&(0.0, 0.0)), "{}", soil);
+            assert_eq!(curve.last().unwrap().1, 100.0, "{}", soil);
+            assert!(curve
+                .windows(2)
+                .all(|pair| pair[0].0 < pair[1].0 && pair[0].1 < pair[1].1));
+        }
+        let bark = Moisture {
+            soil: SoilType::Bark,
+            ..moisture(40.0)
+        };
+        assert_close(bark.percent().unwrap(), 80.0);
+        assert_close(bark.sensor_percent().unwrap(), 40.0);
+    }
+
+    #[test]
+    fn recommended_water() {
+        // the target of potting soil is 50 %, it holds half its volume of water
+        let dry = moisture(30.0);
+        assert_close(dry.water_content_ml().unwrap(), 150.0);
+        assert_close(dry.recommended_water_ml().unwrap(), 100.0);
+        assert_eq!(dry.needs_water(), Some(true));
+        assert_eq!(moisture(70.0).recommended_water_ml(), Some(0.0));
+        let unknown_pot = Moisture {
+            pot_volume: None,
+            ..dry
+        };
+        assert_eq!(unknown_pot.recommended_water_ml(), None);
+        let unmeasured = Moisture {
+            measured_voltage: None,
+            ..moisture(30.0)
+        };
+        assert_eq!(unmeasured.recommended_water_ml(), None);
+        assert_eq!(unmeasured.calulated_moisture(), CalculatedMoisture::Unknown);
+    }
+
+    #[test]
+    fn median_of_even_and_odd_counts() {
+        assert_eq!(median(&mut [3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(&mut [4.0, 1.0, 3.0, 2.0]), Some(2.5));
+        assert_eq!(median(&mut [7.0]), Some(7.0));
+        assert_eq!(median(&mut []), None);
+    }
+
+    #[test]
+    fn drying_trend_ignores_outliers() {
+        // drying by 10 points a day from 70 %, measured hourly for two days
+        let mut history: Vec<HistoryPoint> = (0..=48)
+            .map(|hour| {
+                let percent = 70.0 - 10.0 * hour as f32 / 24.0;
+                HistoryPoint {
+                    time: hour * 3600,
+                    measured_voltage: moisture(percent).measured_voltage.unwrap(),
+                }
+            })
+            .collect();
+        // a glitch, small enough not to count as a watering when the readings recover
+        history[30].measured_voltage += 8.0 * 30.0;
+        let now = 2 * 86_400;
+        let trend = moisture(50.0).drying_trend(&history, now).unwrap();
+        assert!((trend.percent_per_day + 10.0).abs() < 0.1);
+        // 40 % is reached a day later
+        let needs_water_in = trend.needs_water_in(now).unwrap();
+        assert!(needs_water_in.abs_diff(86_400) < 3600);
+
+        assert_eq!(moisture(50.0).drying_trend(&history[..5], now), None);
+    }
+
+    #[test]
+    fn drying_trend_starts_after_watering() {
+        let mut history: Vec<HistoryPoint> = (0..=24)
+            .map(|hour| HistoryPoint {
+                time: hour * 3600,
+                measured_voltage: moisture(70.0 - hour as f32).measured_voltage.unwrap(),
+            })
+            .collect();
+        // watered back to 70 % after a day, only four hours since
+        history.extend((25..=28).map(|hour| HistoryPoint {
+            time: hour * 3600,
+            measured_voltage: moisture(70.0).measured_voltage.unwrap(),
+        }));
+        assert_eq!(moisture(70.0).drying_trend(&history, 28 * 3600), None);
+    }
+}
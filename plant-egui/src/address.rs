@@ -0,0 +1,307 @@
+//! Addresses of boards, like `192.168.1.20`, `[fe80::1]:8080` or `https://example.org/kitchen`.
+
+use std::net::{IpAddr, Ipv6Addr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Scheme {
+    #[default]
+    Http,
+    Https,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Host {
+    Ip(IpAddr),
+    /// A DNS or mDNS name, resolved when a request is sent.
+    Name(String),
+}
+
+/// Where the API of a board is served, defaults to `http` on port 80 without a path prefix.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BoardAddress {
+    pub scheme: Scheme,
+    pub host: Host,
+    pub port: Option<u16>,
+    /// Prepended to the paths of the API, empty or starting with `/` without a trailing one.
+    pub path: String,
+}
+
+impl BoardAddress {
+    /// The address of a board at the default port and path.
+    pub fn new(ip: impl Into<IpAddr>) -> BoardAddress {
+        BoardAddress {
+            scheme: Scheme::Http,
+            host: Host::Ip(ip.into()),
+            port: None,
+            path: String::new(),
+        }
+    }
+
+    pub fn with_port(self, port: u16) -> BoardAddress {
+        let default = match self.scheme {
+            Scheme::Http => 80,
+            Scheme::Https => 443,
+        };
+        BoardAddress {
+            port: (port != default).then_some(port),
+            ..self
+        }
+    }
+
+    /// The URL of a path of the API, `path` starts with `/`.
+    pub fn url(&self, path: &str) -> String {
+        let scheme = match self.scheme {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+        };
+        format!("{}://{}{}{}", scheme, self.authority(), self.path, path)
+    }
+
+    fn authority(&self) -> String {
+        let host = match &self.host {
+            Host::Ip(IpAddr::V6(ip)) => format!("[{}]", ip),
+            Host::Ip(IpAddr::V4(ip)) => ip.to_string(),
+            Host::Name(name) => name.clone(),
+        };
+        match self.port {
+            Some(port) => format!("{}:{}", host, port),
+            None => host,
+        }
+    }
+}
+
+impl std::fmt::Display for BoardAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.scheme == Scheme::Https {
+            write!(f, "https://")?;
+        }
+        write!(f, "{}{}", self.authority(), self.path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    Empty,
+    UnsupportedScheme(String),
+    InvalidPort(String),
+    InvalidHost(String),
+}
+
+impl std::fmt::Display for AddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressError::Empty => write!(f, "Enter an address"),
+            AddressError::UnsupportedScheme(scheme) => {
+                write!(f, "Use http or https instead of {}", scheme)
+            }
+            AddressError::InvalidPort(port) => write!(f, "{} isn't a port", port),
+            AddressError::InvalidHost(host) => write!(f, "{} isn't a host name or IP", host),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+impl std::str::FromStr for BoardAddress {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (scheme, rest) = match s.split_once("://") {
+            Some((scheme, rest)) => match scheme.to_ascii_lowercase().as_str() {
+                "http" => (Scheme::Http, rest),
+                "https" => (Scheme::Https, rest),
+                _ => return Err(AddressError::UnsupportedScheme(scheme.to_string())),
+            },
+            None => (Scheme::Http, s),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, ""),
+        };
+        if authority.is_empty() {
+            return Err(AddressError::Empty);
+        }
+        let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+            // [IPv6] or [IPv6]:port
+            let (ip, port) = bracketed
+                .split_once(']')
+                .ok_or_else(|| AddressError::InvalidHost(authority.to_string()))?;
+            let ip: Ipv6Addr = ip
+                .parse()
+                .map_err(|_| AddressError::InvalidHost(ip.to_string()))?;
+            let port = match port {
+                "" => None,
+                port => Some(port.strip_prefix(':').unwrap_or(port)),
+            };
+            (Host::Ip(IpAddr::V6(ip)), port)
+        } else if let Ok(ip) = authority.parse::<Ipv6Addr>() {
+            (Host::Ip(IpAddr::V6(ip)), None)
+        } else {
+            let (host, port) = match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            };
+            (parse_host(host)?, port)
+        };
+        let port = port
+            .map(|port| {
+                port.parse::<u16>()
+                    .map_err(|_| AddressError::InvalidPort(port.to_string()))
+            })
+            .transpose()?;
+        let address = BoardAddress {
+            scheme,
+            host,
+            port: None,
+            path: path.trim_end_matches('/').to_string(),
+        };
+        Ok(match port {
+            Some(port) => address.with_port(port),
+            None => address,
+        })
+    }
+}
+
+fn parse_host(host: &str) -> Result<Host, AddressError> {
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(Host::Ip(ip));
+    }
+    let valid = !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if valid {
+        Ok(Host::Name(host.to_ascii_lowercase()))
+    } else {
+        Err(AddressError::InvalidHost(host.to_string()))
+    }
+}
+
+// stored as text, so that the IPv4 addresses saved by older versions still load
+impl serde::Serialize for BoardAddress {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for BoardAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    fn parse(text: &str) -> BoardAddress {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_hosts() {
+        assert_eq!(
+            parse("192.168.1.20"),
+            BoardAddress::new(Ipv4Addr::new(192, 168, 1, 20))
+        );
+        assert_eq!(
+            parse("fe80::1"),
+            BoardAddress::new("fe80::1".parse::<Ipv6Addr>().unwrap())
+        );
+        let address = parse("[fe80::1]:8080");
+        assert_eq!(address.host, Host::Ip("fe80::1".parse().unwrap()));
+        assert_eq!(address.port, Some(8080));
+        let address = parse("Plants.local:81");
+        assert_eq!(address.host, Host::Name("plants.local".to_string()));
+        assert_eq!(address.port, Some(81));
+    }
+
+    #[test]
+    fn parses_scheme_and_path() {
+        let address = parse("HTTPS://example.org/kitchen/");
+        assert_eq!(address.scheme, Scheme::Https);
+        assert_eq!(address.port, None);
+        assert_eq!(address.path, "/kitchen");
+        assert_eq!(address.url("/state"), "https://example.org/kitchen/state");
+        assert_eq!(
+            parse("10.0.0.2:8080").url("/state"),
+            "http://10.0.0.2:8080/state"
+        );
+        assert_eq!(parse("[::1]").url("/state"), "http://[::1]/state");
+    }
+
+    #[test]
+    fn strips_default_ports() {
+        assert_eq!(parse("10.0.0.2:80").port, None);
+        assert_eq!(parse("https://10.0.0.2:443").port, None);
+        assert_eq!(parse("https://10.0.0.2:80").port, Some(80));
+        assert_eq!(parse("http://10.0.0.2:443").port, Some(443));
+        assert_eq!(
+            BoardAddress::new(Ipv4Addr::LOCALHOST).with_port(80).port,
+            None
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for text in [
+            "10.0.0.2",
+            "10.0.0.2:8080",
+            "[fe80::1]:8080",
+            "fe80::1",
+            "plants.local",
+            "https://example.org/kitchen",
+            "https://example.org:8443/a/b",
+        ] {
+            let address = parse(text);
+            assert_eq!(parse(&address.to_string()), address, "{}", text);
+        }
+        assert_eq!(parse("http://10.0.0.2:80/").to_string(), "10.0.0.2");
+        assert_eq!(parse("fe80::1").to_string(), "[fe80::1]");
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        let error = |text: &str| text.parse::<BoardAddress>().unwrap_err();
+        assert_eq!(error(""), AddressError::Empty);
+        assert_eq!(error("http:///state"), AddressError::Empty);
+        assert_eq!(
+            error("ftp://10.0.0.2"),
+            AddressError::UnsupportedScheme("ftp".to_string())
+        );
+        assert_eq!(
+            error("10.0.0.2:99999"),
+            AddressError::InvalidPort("99999".to_string())
+        );
+        assert_eq!(
+            error("[fe80::1]x"),
+            AddressError::InvalidPort("x".to_string())
+        );
+        assert_eq!(
+            error("[fe80::zz]"),
+            AddressError::InvalidHost("fe80::zz".to_string())
+        );
+        assert_eq!(
+            error("plant_board"),
+            AddressError::InvalidHost("plant_board".to_string())
+        );
+        assert_eq!(error("a..b"), AddressError::InvalidHost("a..b".to_string()));
+    }
+
+    #[test]
+    fn serializes_as_text() {
+        let address = parse("[fe80::1]:8080");
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(json, r#""[fe80::1]:8080""#);
+        assert_eq!(
+            serde_json::from_str::<BoardAddress>(&json).unwrap(),
+            address
+        );
+        assert!(serde_json::from_str::<BoardAddress>(r#""ftp://x""#).is_err());
+    }
+}
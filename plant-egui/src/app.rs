@@ -1,14 +1,69 @@
-use crate::board::{self, Board, Boards, OnlineStatus};
-use std::net::Ipv4Addr;
+use crate::address::BoardAddress;
+use crate::board::{Board, Boards, OnlineStatus};
+use crate::discovery::DiscoveredBoard;
+use crate::live::{self, LiveReply, LiveStatus};
+use plant_common::{
+    CalibrationCheck, ConnectorInfo, ErrStatus, History, PinCapability, PlantEvent, WifiStatus,
+};
 use tokio_with_wasm::tokio::sync::mpsc::{Receiver, Sender};
 
-#[derive(Debug, Clone, Default)]
-pub struct Message {}
+/// An error reported by a board, shown until the user dismisses it.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub board: BoardAddress,
+    pub error: ErrStatus,
+    pub detail: String,
+}
+
+impl Message {
+    /// What the user can do about the error.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self.error {
+            ErrStatus::ConnectorInUse { .. } => Some("Choose a free connector."),
+            ErrStatus::PinNotAdcCapable {
+                capability: PinCapability::Adc2,
+                ..
+            } => Some("Use one of the GPIOs 32 to 36 or 39, ADC2 can't be read while Wi-Fi is on."),
+            ErrStatus::PinNotAdcCapable { .. } => Some("Use one of the GPIOs 32 to 36 or 39."),
+            ErrStatus::InvalidConnector(_) => {
+                Some("Use bus 0 or 1, an address from 0x08 to 0x77 and a channel from 0 to 7.")
+            }
+            ErrStatus::PinNotSwitchable { .. } => {
+                Some("Use one of the GPIOs 4, 13, 14, 16 to 19, 23 or 27.")
+            }
+            ErrStatus::FlowUnknown { .. } => Some("Set the flow rate or add a flow sensor."),
+            ErrStatus::RunTooLong { .. } => Some("Water in several shorter runs."),
+            ErrStatus::ActuatorCoolingDown { .. } | ErrStatus::DailyRunLimit { .. } => {
+                Some("The board limits how often pumps run, so they can't flood the plants.")
+            }
+            ErrStatus::NameTooLong => Some("Choose a shorter name."),
+            ErrStatus::NoMeasurements(_) => Some("Wait a few seconds and try again."),
+            ErrStatus::InvalidPassword => {
+                Some("Check the password, leave it empty for open networks.")
+            }
+            ErrStatus::TooManyNetworks => Some("Remove a network the board doesn't need."),
+            ErrStatus::Provisioning => {
+                Some("Add the network the board should join, everything else works there.")
+            }
+            ErrStatus::StorageFull => Some("Delete unused plants or care profiles."),
+            ErrStatus::StorageWriteFailed(_) => Some("Try again or restart the board."),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct BoardReply {
     pub board: Option<Board>,
     pub message: Option<Message>,
+    /// The check of a calibration reference the board captured.
+    pub calibrated: Option<(BoardAddress, CalibrationCheck)>,
+    pub connectors: Option<(BoardAddress, Vec<ConnectorInfo>)>,
+    pub events: Option<(BoardAddress, Vec<PlantEvent>)>,
+    pub history: Option<(BoardAddress, History)>,
+    pub wifi: Option<(BoardAddress, WifiStatus)>,
+    pub discovered: Option<Vec<DiscoveredBoard>>,
+    pub live: Option<(BoardAddress, LiveReply)>,
 }
 
 pub enum Page {
@@ -23,7 +78,10 @@ pub struct App {
     pub board_sender: Sender<BoardReply>,
     pub board_receiver: Receiver<BoardReply>,
     pub http_client: reqwest::Client,
+    pub home_page: crate::pages::home::HomePage,
     pub settings_page: crate::pages::settings::SettingsPage,
+    pub watering_page: crate::pages::watering::WateringPage,
+    pub messages: Vec<Message>,
 }
 
 impl Default for App {
@@ -34,33 +92,38 @@ impl Default for App {
             boards: Boards::default(),
             board_sender,
             board_receiver,
-            http_client: reqwest::Client::new(),
+            http_client: http_client(),
+            home_page: crate::pages::home::HomePage::default(),
             settings_page: crate::pages::settings::SettingsPage::default(),
+            watering_page: crate::pages::watering::WateringPage::default(),
+            messages: Vec::new(),
         }
     }
 }
 
+/// The client for the requests to the boards, it resolves their names with [`crate::resolver`].
+#[cfg(not(target_arch = "wasm32"))]
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .dns_resolver(std::sync::Arc::new(
+            crate::resolver::BoardResolver::default(),
+        ))
+        .build()
+        .unwrap_or_default()
+}
+
+/// The browser resolves the names of the boards.
+#[cfg(target_arch = "wasm32")]
+fn http_client() -> reqwest::Client {
+    reqwest::Client::new()
+}
+
 impl App {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         let mut app: App = Default::default();
         if let Some(storage) = cc.storage {
             app.boards = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
         };
-        if !app
-            .boards
-            .boards
-            .iter()
-            .map(|x| x.ip)
-            .any(|x| x == Ipv4Addr::new(10, 69, 69, 155))
-        {
-            app.boards.boards.push(Board {
-                ip: Ipv4Addr::new(10, 69, 69, 155),
-                status: OnlineStatus::Offline,
-                state: None,
-                settings_new_plant_name: "New Plant".to_string(),
-                settings_new_plant_port: 0,
-            });
-        }
         for board in app.boards.boards.iter_mut() {
             board.reload(app.board_sender.clone(), app.http_client.clone());
         }
@@ -74,24 +137,117 @@ impl eframe::App for App {
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        while let Some(board) = self.board_receiver.try_recv().ok() {
-            if let Some(mut board) = board.board {
-                if let Some(index) = self.boards.boards.iter().position(|b| b.ip == board.ip) {
+        let now = ctx.input(|i| i.time);
+        while let Ok(reply) = self.board_receiver.try_recv() {
+            if let Some(message) = reply.message {
+                self.messages.push(message);
+            }
+            if let Some((address, check)) = reply.calibrated {
+                if let Some(wizard) = self
+                    .settings_page
+                    .calibration_wizard
+                    .as_mut()
+                    .filter(|w| w.board == address)
+                {
+                    wizard.calibrated(check);
+                }
+            }
+            if let Some((address, connectors)) = reply.connectors {
+                if let Some(board) = self.boards.boards.iter_mut().find(|b| b.address == address) {
+                    board.connectors = connectors;
+                }
+            }
+            if let Some((address, events)) = reply.events {
+                if let Some(board) = self.boards.boards.iter_mut().find(|b| b.address == address) {
+                    board.events = events;
+                }
+            }
+            if let Some((address, wifi)) = reply.wifi {
+                if let Some(board) = self.boards.boards.iter_mut().find(|b| b.address == address) {
+                    board.wifi = Some(wifi);
+                }
+            }
+            if let Some((address, reply)) = reply.live {
+                if let Some(board) = self.boards.boards.iter_mut().find(|b| b.address == address) {
+                    board.live_reply(
+                        self.board_sender.clone(),
+                        self.http_client.clone(),
+                        reply,
+                        now,
+                    );
+                }
+            }
+            if let Some(boards) = reply.discovered {
+                self.settings_page.scanning = false;
+                self.settings_page.discovered = boards;
+            }
+            if let Some((address, history)) = reply.history {
+                if let Some(board) = self.boards.boards.iter_mut().find(|b| b.address == address) {
+                    board.history.insert(history);
+                }
+            }
+            if let Some(mut board) = reply.board {
+                if let Some(index) = self
+                    .boards
+                    .boards
+                    .iter()
+                    .position(|b| b.address == board.address)
+                {
                     if board.state.is_some() {
                         let mut state = board.state.unwrap();
                         state.plants.sort_by(|x, y| x.id.cmp(&y.id));
                         board.state = Some(state);
+                        // the plants might have changed, so the used connectors did as well
+                        board.load_connectors(self.board_sender.clone(), self.http_client.clone());
+                        board.load_events(self.board_sender.clone(), self.http_client.clone());
+                        board.load_wifi(self.board_sender.clone(), self.http_client.clone());
+                        board.connectors =
+                            std::mem::take(&mut self.boards.boards[index].connectors);
+                        board.events = std::mem::take(&mut self.boards.boards[index].events);
+                        board.history = std::mem::take(&mut self.boards.boards[index].history);
+                        board.wifi = self.boards.boards[index].wifi.take();
+                        board.settings_new_network =
+                            std::mem::take(&mut self.boards.boards[index].settings_new_network);
+                        board.polling = std::mem::take(&mut self.boards.boards[index].polling);
+                        board.live = std::mem::take(&mut self.boards.boards[index].live);
+                        if matches!(board.live, LiveStatus::Disconnected) {
+                            board.live = live::subscribe(
+                                self.board_sender.clone(),
+                                self.http_client.clone(),
+                                board.address.clone(),
+                            );
+                        }
+                        // the interval might have been changed while the request was running
+                        board.poll_interval = self.boards.boards[index].poll_interval;
+                        board.answered(now);
                         self.boards.boards[index] = board;
                     } else {
                         self.boards.boards[index].status = OnlineStatus::Offline;
+                        self.boards.boards[index].failed(now);
                     }
                 }
                 //if the board is not in boards, dont add it
                 //every created board is added to boards at the time of creation
                 //if it is not in boards, it was deleted
             }
-            // Todo: handle messages
         }
+        for board in self.boards.boards.iter_mut() {
+            board.poll(self.board_sender.clone(), self.http_client.clone(), now);
+        }
+        // the replies arrive in the background, so look for them regularly while waiting
+        let wait = if self.boards.boards.iter().any(|b| b.is_loading()) {
+            0.1
+        } else {
+            let next_poll = self
+                .boards
+                .boards
+                .iter()
+                .filter_map(|b| b.next_poll_in(now));
+            // the ages shown are in minutes
+            next_poll.fold(30.0, f64::min)
+        };
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(wait));
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 if ui.button("Boards").clicked() {
@@ -109,12 +265,46 @@ impl eframe::App for App {
             });
         });
 
+        if !self.messages.is_empty() {
+            egui::TopBottomPanel::bottom("messages").show(ctx, |ui| {
+                self.messages_panel(ui);
+            });
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             (match self.page {
                 Page::Home => crate::pages::home::home_page,
                 Page::Settings => crate::pages::settings::settings_page,
-                Page::Watering => crate::pages::home::home_page,
+                Page::Watering => crate::pages::watering::watering_page,
             } as fn(_, _))(ui, self);
         });
     }
 }
+
+impl App {
+    fn messages_panel(&mut self, ui: &mut egui::Ui) {
+        let mut dismissed = None;
+        for (index, message) in self.messages.iter().enumerate() {
+            let board = self
+                .boards
+                .boards
+                .iter()
+                .find(|b| b.address == message.board)
+                .and_then(|b| b.state.as_ref())
+                .map(|s| s.name.clone())
+                .unwrap_or_else(|| message.board.to_string());
+            ui.horizontal(|ui| {
+                if ui.button('\u{2716}'.to_string()).clicked() {
+                    dismissed = Some(index);
+                }
+                ui.colored_label(egui::Color32::RED, format!("{}: {}", board, message.detail));
+                if let Some(hint) = message.hint() {
+                    ui.label(hint);
+                }
+            });
+        }
+        if let Some(index) = dismissed {
+            self.messages.remove(index);
+        }
+    }
+}
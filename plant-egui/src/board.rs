@@ -1,10 +1,17 @@
-use std::{clone, net::Ipv4Addr};
+use std::collections::HashMap;
 
-use plant_common::{BoardState, Connector, Moisture, Reply};
+use plant_common::{
+    BoardState, BoardUpdate, CalibrationReference, CareProfile, Connector, ConnectorInfo, History,
+    HistoryQuery, HistoryResolution, LiveUpdate, ManualWatering, OkStatus, PlantEvent,
+    PlantSettings, PlantUpdate, Reply, ReplyStatus, SoilType, WifiNetwork, WifiStatus,
+};
 use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
 use tokio_with_wasm::tokio::sync::mpsc::Sender;
 
-use crate::app::BoardReply;
+use crate::address::BoardAddress;
+use crate::app::{BoardReply, Message};
+use crate::live::{LiveReply, LiveStatus};
 
 /*impl std::fmt::Display for IpAddr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -37,14 +44,140 @@ impl std::fmt::Display for OnlineStatus {
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Board {
-    pub ip: Ipv4Addr,
+    /// Saved as `ip` by versions that only knew IPv4 addresses.
+    #[serde(alias = "ip")]
+    pub address: BoardAddress,
     pub status: OnlineStatus,
     pub state: Option<BoardState>,
     pub settings_new_plant_name: String,
-    pub settings_new_plant_port: u8,
+    #[serde(default)]
+    pub settings_new_plant_connector: Connector,
+    #[serde(default)]
+    pub settings_new_plant_soil: SoilType,
+    #[serde(default)]
+    pub settings_new_plant_pot_volume: f32,
+    #[serde(default)]
+    pub settings_new_profile: CareProfile,
+    /// The connectors of the board and which plant uses them, empty until they were loaded.
+    #[serde(skip)]
+    pub connectors: Vec<ConnectorInfo>,
+    /// The events of all plants of the board, oldest first, empty until they were loaded.
+    #[serde(skip)]
+    pub events: Vec<PlantEvent>,
+    #[serde(skip)]
+    pub history: HistoryCache,
+    /// The networks the board knows, `None` until they were loaded.
+    #[serde(skip)]
+    pub wifi: Option<WifiStatus>,
+    /// Not saved, it holds a password.
+    #[serde(skip)]
+    pub settings_new_network: WifiNetwork,
+    /// Seconds between two reloads of the state, 0 to only reload on request.
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: u32,
+    #[serde(skip)]
+    pub polling: Polling,
+    /// While the board pushes its changes, it isn't polled.
+    #[serde(skip)]
+    pub live: LiveStatus,
+}
+
+/// Seconds between two polls of a board, unless configured otherwise.
+pub const DEFAULT_POLL_INTERVAL: u32 = 30;
+/// Longest wait between two polls of an offline board, in seconds.
+const MAX_POLL_BACKOFF: f64 = 10.0 * 60.0;
+/// Seconds after which the state of a board that isn't polled counts as stale.
+const STALE_WITHOUT_POLLING: f64 = 10.0 * 60.0;
+
+fn default_poll_interval() -> u32 {
+    DEFAULT_POLL_INTERVAL
+}
+
+/// When the board last answered and when it is polled next, in seconds of the ui time.
+#[derive(Debug, Clone, Default)]
+pub struct Polling {
+    last_update: Option<f64>,
+    next_poll: f64,
+    /// Polls failed in a row, the wait doubles with each of them.
+    failures: u32,
+}
+
+/// Seconds after which a shown history is loaded again.
+const HISTORY_REFRESH: f64 = 60.0;
+
+/// The measurement histories of the plants of a board, loaded once they are shown.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryCache {
+    histories: HashMap<(u16, HistoryResolution), History>,
+    /// When each history was last requested, in seconds of the ui time.
+    requested: HashMap<(u16, HistoryResolution), f64>,
+}
+
+impl HistoryCache {
+    pub fn get(&self, plant: u16, resolution: HistoryResolution) -> Option<&History> {
+        self.histories.get(&(plant, resolution))
+    }
+
+    pub fn insert(&mut self, history: History) {
+        self.histories
+            .insert((history.plant, history.resolution), history);
+    }
+
+    /// Loads a history unless it was requested recently, it is sent back through `tx`.
+    pub fn load(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        address: &BoardAddress,
+        plant: u16,
+        resolution: HistoryResolution,
+        now: f64,
+    ) {
+        let requested = self
+            .requested
+            .entry((plant, resolution))
+            .or_insert(f64::MIN);
+        if now - *requested < HISTORY_REFRESH {
+            return;
+        }
+        *requested = now;
+        let query = HistoryQuery {
+            resolution: Some(resolution),
+            ..Default::default()
+        };
+        let request = http_client
+            .get(address.url(&format!("/plants/{}/history", plant)))
+            .query(&query);
+        let address = address.clone();
+        Board::spawn_load(tx, request, move |history| BoardReply {
+            history: Some((address, history)),
+            ..Default::default()
+        });
+    }
 }
 
 impl Board {
+    pub fn new(address: BoardAddress) -> Board {
+        Board {
+            address,
+            status: OnlineStatus::Offline,
+            state: None,
+            settings_new_plant_name: "New plant".to_string(),
+            settings_new_plant_connector: Default::default(),
+            settings_new_plant_soil: Default::default(),
+            settings_new_plant_pot_volume: 0.0,
+            settings_new_profile: Default::default(),
+            connectors: Vec::new(),
+            events: Vec::new(),
+            history: Default::default(),
+            wifi: None,
+            settings_new_network: Default::default(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            polling: Default::default(),
+            live: Default::default(),
+        }
+    }
+
     async fn send_request(
         request_builder: RequestBuilder,
         tx: Sender<BoardReply>,
@@ -60,13 +193,29 @@ impl Board {
                 if let Ok(response) = output {
                     if let Ok(data) = response.text().await {
                         if let Ok(reply) = serde_json::from_str::<Reply>(&data) {
+                            let calibrated = match &reply.status {
+                                ReplyStatus::Ok(OkStatus::Calibrated(check)) => {
+                                    Some((board.address.clone(), check.clone()))
+                                }
+                                _ => None,
+                            };
+                            let message = match reply.status {
+                                ReplyStatus::Err(error) => Some(Message {
+                                    board: board.address.clone(),
+                                    detail: reply.detail.unwrap_or_else(|| error.to_string()),
+                                    error,
+                                }),
+                                ReplyStatus::Ok(_) => None,
+                            };
                             tx.send(BoardReply {
-                                message: None,
+                                message,
+                                calibrated,
                                 board: Some(Board {
                                     status: OnlineStatus::Online,
                                     state: Some(reply.state),
                                     ..board
                                 }),
+                                ..Default::default()
                             })
                             .await
                             .unwrap();
@@ -89,85 +238,354 @@ impl Board {
         }
     }
 
-    pub fn reload(&mut self, tx: Sender<BoardReply>, http_client: reqwest::Client) {
+    /// Sends the request in the background, the board is sent back through `tx` once it answered.
+    fn spawn_request(&mut self, tx: Sender<BoardReply>, request_builder: RequestBuilder) {
         self.set_loading();
         let clone = self.clone();
         tokio_with_wasm::tokio::spawn(async move {
-            let request_builder = http_client.get(format!("http://{}/state", clone.ip.clone()));
             Board::send_request(
                 request_builder,
                 tx,
                 clone.clone(),
                 BoardReply {
-                    message: None,
                     board: Some(Board {
                         state: None,
-                        ..clone.clone()
+                        ..clone
                     }),
+                    ..Default::default()
                 },
             )
             .await;
         });
     }
 
-    pub fn create_plant(
+    pub fn is_loading(&self) -> bool {
+        matches!(
+            self.status,
+            OnlineStatus::LoadingWasOnline | OnlineStatus::LoadingWasOffline
+        )
+    }
+
+    /// Reloads the state if the next poll is due.
+    pub fn poll(&mut self, tx: Sender<BoardReply>, http_client: reqwest::Client, now: f64) {
+        if self.poll_interval == 0
+            || self.is_loading()
+            || self.live.is_connected()
+            || now < self.polling.next_poll
+        {
+            return;
+        }
+        self.reload(tx, http_client);
+    }
+
+    /// Seconds until the board is polled next, `None` if it isn't polled.
+    pub fn next_poll_in(&self, now: f64) -> Option<f64> {
+        (self.poll_interval > 0 && !self.live.is_connected())
+            .then(|| (self.polling.next_poll - now).max(0.0))
+    }
+
+    /// Schedules the next poll after the board answered.
+    pub fn answered(&mut self, now: f64) {
+        self.polling.last_update = Some(now);
+        self.polling.failures = 0;
+        self.polling.next_poll = now + self.poll_interval as f64;
+    }
+
+    /// Schedules the next poll after the board didn't answer, backing off while it is offline.
+    pub fn failed(&mut self, now: f64) {
+        self.polling.failures = self.polling.failures.saturating_add(1);
+        let backoff = 2f64.powi(self.polling.failures.min(16) as i32);
+        let wait = (self.poll_interval as f64 * backoff).min(MAX_POLL_BACKOFF);
+        self.polling.next_poll = now + wait;
+    }
+
+    /// Seconds since the state was last loaded, `None` if it wasn't loaded since the start.
+    pub fn age(&self, now: f64) -> Option<f64> {
+        self.polling.last_update.map(|time| now - time)
+    }
+
+    /// Whether the shown state might be outdated, it is if two polls were missed.
+    ///
+    /// A connected stream is always up to date, it is closed once the board stops sending.
+    pub fn is_stale(&self, now: f64) -> bool {
+        if self.live.is_connected() {
+            return false;
+        }
+        let max_age = match self.poll_interval {
+            0 => STALE_WITHOUT_POLLING,
+            interval => 2.0 * interval as f64 + 5.0,
+        };
+        self.status == OnlineStatus::Offline || self.age(now).map_or(true, |age| age > max_age)
+    }
+
+    /// Applies what a board pushed, falling back to polling once its stream ends.
+    pub fn live_reply(
         &mut self,
         tx: Sender<BoardReply>,
         http_client: reqwest::Client,
-        name: String,
-        connection: Connector,
+        reply: LiveReply,
+        now: f64,
     ) {
-        self.set_loading();
-        let clone = self.clone();
-        tokio_with_wasm::tokio::spawn(async move {
-            let request_builder = http_client
-                .post(format!("http://{}/create_plant", clone.ip))
-                .json(&plant_common::PlantInfo {
-                    name,
-                    connection,
-                    ..Default::default()
-                });
-            Board::send_request(
-                request_builder,
-                tx,
-                clone.clone(),
-                BoardReply {
-                    message: None,
-                    board: Some(Board {
-                        state: None,
-                        ..clone.clone()
-                    }),
-                },
-            )
-            .await;
+        match reply {
+            LiveReply::Connected => {
+                if let LiveStatus::Connecting(handle) = std::mem::take(&mut self.live) {
+                    self.live = LiveStatus::Connected(handle);
+                }
+            }
+            LiveReply::Update(update) => self.apply_update(tx, http_client, *update, now),
+            LiveReply::Closed => {
+                self.live = LiveStatus::Disconnected;
+                // poll right away, the stream is opened again once the board answers
+                self.polling.next_poll = now;
+            }
+            LiveReply::Unsupported => self.live = LiveStatus::Unsupported,
+        }
+    }
+
+    fn apply_update(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        update: LiveUpdate,
+        now: f64,
+    ) {
+        match update {
+            LiveUpdate::State(mut state) => {
+                state.plants.sort_by(|x, y| x.id.cmp(&y.id));
+                self.state = Some(state);
+                self.status = OnlineStatus::Online;
+                self.answered(now);
+                // the plants might have changed, so the used connectors did as well
+                self.load_connectors(tx, http_client);
+            }
+            LiveUpdate::Plant(plant) => {
+                if let Some(state) = &mut self.state {
+                    match state.plants.iter_mut().find(|p| p.id == plant.id) {
+                        Some(known) => *known = plant,
+                        None => {
+                            state.plants.push(plant);
+                            state.plants.sort_by(|x, y| x.id.cmp(&y.id));
+                        }
+                    }
+                }
+                self.answered(now);
+            }
+            LiveUpdate::Event(event) => {
+                if self.events.last().map_or(true, |last| last.id < event.id) {
+                    self.events.push(event);
+                }
+            }
+        }
+    }
+
+    pub fn reload(&mut self, tx: Sender<BoardReply>, http_client: reqwest::Client) {
+        let request_builder = http_client.get(self.address.url("/state"));
+        self.spawn_request(tx, request_builder);
+    }
+
+    /// Loads the connectors of the board, they are sent back through `tx` without a board.
+    pub fn load_connectors(&self, tx: Sender<BoardReply>, http_client: reqwest::Client) {
+        let request = http_client.get(self.address.url("/connectors"));
+        let address = self.address.clone();
+        Board::spawn_load(tx, request, move |connectors| BoardReply {
+            connectors: Some((address, connectors)),
+            ..Default::default()
         });
     }
 
-    pub fn delete_plant(&mut self, tx: Sender<BoardReply>, http_client: reqwest::Client, id: u16) {
-        self.set_loading();
-        let clone = self.clone();
+    /// Loads the events of the board, they are sent back through `tx` without a board.
+    pub fn load_events(&self, tx: Sender<BoardReply>, http_client: reqwest::Client) {
+        let request = http_client.get(self.address.url("/events"));
+        let address = self.address.clone();
+        Board::spawn_load(tx, request, move |events| BoardReply {
+            events: Some((address, events)),
+            ..Default::default()
+        });
+    }
+
+    /// Loads the Wi-Fi networks of the board, they are sent back through `tx` without a board.
+    pub fn load_wifi(&self, tx: Sender<BoardReply>, http_client: reqwest::Client) {
+        let request = http_client.get(self.address.url("/wifi"));
+        let address = self.address.clone();
+        Board::spawn_load(tx, request, move |wifi| BoardReply {
+            wifi: Some((address, wifi)),
+            ..Default::default()
+        });
+    }
+
+    /// Sends a GET request in the background, nothing is sent back if it fails.
+    fn spawn_load<T: DeserializeOwned + Send + 'static>(
+        tx: Sender<BoardReply>,
+        request_builder: RequestBuilder,
+        to_reply: impl FnOnce(T) -> BoardReply + Send + 'static,
+    ) {
+        const MAX_WAIT_TIME: std::time::Duration =
+            tokio_with_wasm::tokio::time::Duration::from_secs(5);
+        let request = request_builder.send();
         tokio_with_wasm::tokio::spawn(async move {
-            let request_builder = http_client
-                .delete(format!("http://{}/delete_plant", clone.ip))
-                .json(&plant_common::PlantInfo {
-                    id,
-                    ..Default::default()
-                });
-            Board::send_request(
-                request_builder,
-                tx,
-                clone.clone(),
-                BoardReply {
-                    message: None,
-                    board: Some(Board {
-                        state: None,
-                        ..clone.clone()
-                    }),
+            tokio_with_wasm::tokio::select! {
+                output = request => {
+                    if let Ok(response) = output {
+                        if let Ok(data) = response.json::<T>().await {
+                            tx.send(to_reply(data)).await.unwrap();
+                        }
+                    }
                 },
-            )
-            .await;
+                _ = tokio_with_wasm::tokio::time::sleep(MAX_WAIT_TIME) => { },
+            };
         });
     }
+
+    pub fn update_board(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        update: BoardUpdate,
+    ) {
+        let request_builder = http_client.patch(self.address.url("/board")).json(&update);
+        self.spawn_request(tx, request_builder);
+    }
+
+    pub fn create_plant(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        settings: PlantSettings,
+    ) {
+        let request_builder = http_client
+            .post(self.address.url("/plants"))
+            .json(&settings);
+        self.spawn_request(tx, request_builder);
+    }
+
+    /// Replaces all settings of a plant, keeping its id and measurements.
+    pub fn replace_plant(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        id: u16,
+        settings: PlantSettings,
+    ) {
+        let request_builder = http_client
+            .put(self.address.url(&format!("/plants/{}", id)))
+            .json(&settings);
+        self.spawn_request(tx, request_builder);
+    }
+
+    /// Changes only the fields of a plant that are set in `update`.
+    pub fn update_plant(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        id: u16,
+        update: PlantUpdate,
+    ) {
+        let request_builder = http_client
+            .patch(self.address.url(&format!("/plants/{}", id)))
+            .json(&update);
+        self.spawn_request(tx, request_builder);
+    }
+
+    pub fn calibrate(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        id: u16,
+        reference: CalibrationReference,
+    ) {
+        let request_builder = http_client
+            .post(self.address.url("/calibrate"))
+            .json(&plant_common::CalibrationRequest { id, reference });
+        self.spawn_request(tx, request_builder);
+    }
+
+    pub fn create_care_profile(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        profile: CareProfile,
+    ) {
+        let request_builder = http_client
+            .post(self.address.url("/create_care_profile"))
+            .json(&profile);
+        self.spawn_request(tx, request_builder);
+    }
+
+    pub fn delete_care_profile(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        id: u16,
+    ) {
+        let request_builder = http_client
+            .delete(self.address.url("/delete_care_profile"))
+            .json(&plant_common::CustomCareProfile {
+                id,
+                ..Default::default()
+            });
+        self.spawn_request(tx, request_builder);
+    }
+
+    pub fn delete_plant(&mut self, tx: Sender<BoardReply>, http_client: reqwest::Client, id: u16) {
+        let request_builder = http_client.delete(self.address.url(&format!("/plants/{}", id)));
+        self.spawn_request(tx, request_builder);
+    }
+
+    pub fn record_manual_watering(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        id: u16,
+        watering: ManualWatering,
+    ) {
+        let request_builder = http_client
+            .post(self.address.url(&format!("/plants/{}/watered", id)))
+            .json(&watering);
+        self.spawn_request(tx, request_builder);
+    }
+
+    /// Adds a network for the board to join or changes the password of a known one.
+    pub fn add_wifi_network(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        network: WifiNetwork,
+    ) {
+        let request_builder = http_client
+            .post(self.address.url("/wifi/networks"))
+            .json(&network);
+        self.spawn_request(tx, request_builder);
+    }
+
+    pub fn remove_wifi_network(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        ssid: &str,
+    ) {
+        // SSIDs can contain anything, so the segment is escaped
+        let Ok(mut url) = reqwest::Url::parse(&self.address.url("/wifi/networks")) else {
+            return;
+        };
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.push(ssid);
+        }
+        let request_builder = http_client.delete(url);
+        self.spawn_request(tx, request_builder);
+    }
+
+    pub fn reset_controller(
+        &mut self,
+        tx: Sender<BoardReply>,
+        http_client: reqwest::Client,
+        id: u16,
+    ) {
+        let request_builder = http_client.post(
+            self.address
+                .url(&format!("/plants/{}/controller/reset", id)),
+        );
+        self.spawn_request(tx, request_builder);
+    }
 }
 
 #[derive(Default, Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -234,3 +652,29 @@ pub struct Boards {
        });
    }
 */
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn loads_boards_saved_with_an_ip() {
+        let saved = r#"{
+            "ip": "10.69.69.155",
+            "status": "Offline",
+            "state": null,
+            "settings_new_plant_name": "New plant",
+            "settings_new_plant_port": 32
+        }"#;
+        let board: Board = serde_json::from_str(saved).unwrap();
+        assert_eq!(
+            board.address,
+            BoardAddress::new(Ipv4Addr::new(10, 69, 69, 155))
+        );
+        assert_eq!(board.poll_interval, DEFAULT_POLL_INTERVAL);
+        let saved = serde_json::to_value(&board).unwrap();
+        assert_eq!(saved["address"], "10.69.69.155");
+    }
+}
@@ -0,0 +1,216 @@
+//! Charts of the measurement history of a plant.
+
+use egui::{Color32, Ui};
+use egui_plot::{HLine, Legend, Line, Plot, PlotPoints, Polygon, VLine};
+use plant_common::{History, HistoryResolution, PlantEvent, PlantEventKind, PlantInfo};
+
+/// The time range shown by the history chart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ChartRange {
+    Hour,
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl ChartRange {
+    pub const ALL: [ChartRange; 4] = [
+        ChartRange::Hour,
+        ChartRange::Day,
+        ChartRange::Week,
+        ChartRange::Month,
+    ];
+
+    fn hours(&self) -> f64 {
+        match self {
+            ChartRange::Hour => 1.0,
+            ChartRange::Day => 24.0,
+            ChartRange::Week => 7.0 * 24.0,
+            ChartRange::Month => 30.0 * 24.0,
+        }
+    }
+
+    /// The finest resolution the board keeps long enough for the range.
+    #[must_use]
+    pub fn resolution(&self) -> HistoryResolution {
+        match self {
+            ChartRange::Hour | ChartRange::Day => HistoryResolution::Minute,
+            ChartRange::Week | ChartRange::Month => HistoryResolution::Hour,
+        }
+    }
+}
+
+impl std::fmt::Display for ChartRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChartRange::Hour => write!(f, "Hour"),
+            ChartRange::Day => write!(f, "Day"),
+            ChartRange::Week => write!(f, "Week"),
+            ChartRange::Month => write!(f, "Month"),
+        }
+    }
+}
+
+/// Whether the chart shows the substrate moisture or the raw sensor reading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ChartValue {
+    #[default]
+    Percent,
+    Voltage,
+}
+
+/// The points of the history within the last `hours`, x in hours before `now`.
+fn points(
+    plant: &PlantInfo,
+    history: &History,
+    value: ChartValue,
+    now: u64,
+    hours: f64,
+) -> Vec<[f64; 2]> {
+    history
+        .points
+        .iter()
+        .filter_map(|point| {
+            let x = (point.time as f64 - now as f64) / 3600.0;
+            let y = match value {
+                ChartValue::Percent => {
+                    plant.measured_moisture.percent_of(point.measured_voltage)?
+                }
+                ChartValue::Voltage => point.measured_voltage,
+            };
+            (x >= -hours).then_some([x, y as f64])
+        })
+        .collect()
+}
+
+fn format_hours_ago(hours: f64) -> String {
+    let hours = -hours;
+    if hours.abs() < 0.01 {
+        "now".to_string()
+    } else if hours < 1.0 {
+        format!("{:.0} min ago", hours * 60.0)
+    } else if hours < 48.0 {
+        format!("{:.0} h ago", hours)
+    } else {
+        format!("{:.0} days ago", hours / 24.0)
+    }
+}
+
+/// A zoomable chart of the history of a plant with its target band and waterings.
+///
+/// `now` is the synced time of the board, `reset` shows the whole range again.
+#[allow(clippy::too_many_arguments)]
+pub fn moisture_chart(
+    ui: &mut Ui,
+    id_source: impl std::hash::Hash,
+    plant: &PlantInfo,
+    history: Option<&History>,
+    events: &[PlantEvent],
+    now: u64,
+    range: ChartRange,
+    value: ChartValue,
+    reset: bool,
+) {
+    let hours = range.hours();
+    let unit = match value {
+        ChartValue::Percent => " %",
+        // millivolts for analog sensors, counts for digital ones
+        ChartValue::Voltage => "",
+    };
+    let mut plot = Plot::new(id_source)
+        .height(220.0)
+        .legend(Legend::default())
+        .include_x(-hours)
+        .include_x(0.0)
+        .x_axis_formatter(|mark, _, _| format_hours_ago(mark.value))
+        .label_formatter(move |name, point| {
+            let time = format_hours_ago(point.x);
+            match name {
+                "" => format!("{:.0}{}\n{}", point.y, unit, time),
+                name => format!("{}\n{:.0}{}\n{}", name, point.y, unit, time),
+            }
+        });
+    if value == ChartValue::Percent {
+        plot = plot.include_y(0.0).include_y(100.0);
+    }
+    if reset {
+        plot = plot.reset();
+    }
+    let moisture = &plant.measured_moisture;
+    plot.show(ui, |plot_ui| {
+        if value == ChartValue::Percent {
+            let band = moisture.band();
+            let (low, high) = (band.dry_below as f64, band.moist_above as f64);
+            let area = vec![[-hours, low], [0.0, low], [0.0, high], [-hours, high]];
+            plot_ui.polygon(
+                Polygon::new(PlotPoints::new(area))
+                    .name("Target")
+                    .fill_color(Color32::from_rgba_unmultiplied(60, 160, 60, 40))
+                    .stroke(egui::Stroke::NONE),
+            );
+            plot_ui.hline(
+                HLine::new(moisture.needs_water_below())
+                    .name("Water below")
+                    .color(Color32::from_rgb(220, 140, 40)),
+            );
+        }
+        let watered = events.iter().filter(|e| {
+            e.plant == plant.id
+                && e.time.synced
+                && match e.kind {
+                    PlantEventKind::Watered { .. } | PlantEventKind::WateredByHand { .. } => true,
+                    PlantEventKind::Pumped { dry_run, .. } => !dry_run,
+                    _ => false,
+                }
+        });
+        for event in watered {
+            let x = (event.time.seconds as f64 - now as f64) / 3600.0;
+            if x >= -hours {
+                plot_ui.vline(
+                    VLine::new(x)
+                        .name("Watered")
+                        .color(Color32::from_rgb(70, 130, 220)),
+                );
+            }
+        }
+        if let Some(history) = history {
+            let name = match value {
+                ChartValue::Percent => "Moisture",
+                ChartValue::Voltage => "Sensor",
+            };
+            let points = points(plant, history, value, now, hours);
+            plot_ui.line(Line::new(PlotPoints::new(points)).name(name));
+        }
+    });
+}
+
+/// A small chart of the moisture of the last day without axes.
+pub fn sparkline(
+    ui: &mut Ui,
+    id_source: impl std::hash::Hash,
+    plant: &PlantInfo,
+    history: &History,
+    now: u64,
+) -> egui::Response {
+    let points = points(plant, history, ChartValue::Percent, now, 24.0);
+    Plot::new(id_source)
+        .width(80.0)
+        .height(18.0)
+        .show_axes(false)
+        .show_grid(false)
+        .show_background(false)
+        .show_x(false)
+        .show_y(false)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .allow_double_click_reset(false)
+        .include_y(0.0)
+        .include_y(100.0)
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(PlotPoints::new(points)))
+        })
+        .response
+}
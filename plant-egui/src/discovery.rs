@@ -0,0 +1,81 @@
+//! Finds boards on the local network, they answer a broadcast probe with an announcement.
+
+use plant_common::BoardAnnouncement;
+use tokio_with_wasm::tokio::sync::mpsc::Sender;
+
+use crate::address::BoardAddress;
+use crate::app::BoardReply;
+
+/// A board that answered the scan.
+#[derive(Debug, Clone)]
+pub struct DiscoveredBoard {
+    pub address: BoardAddress,
+    pub announcement: BoardAnnouncement,
+}
+
+/// Whether the network can be scanned, browsers can't send UDP.
+pub const CAN_SCAN: bool = cfg!(not(target_arch = "wasm32"));
+
+/// Broadcasts a probe, the boards that answer are sent back through `tx`.
+pub fn scan(tx: Sender<BoardReply>) {
+    tokio_with_wasm::tokio::spawn(async move {
+        #[cfg(not(target_arch = "wasm32"))]
+        let boards = native::probe().await.unwrap_or_else(|e| {
+            log::warn!("Cannot scan the network: {}", e);
+            Vec::new()
+        });
+        #[cfg(target_arch = "wasm32")]
+        let boards = Vec::new();
+        tx.send(BoardReply {
+            discovered: Some(boards),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+
+    use plant_common::{BoardAnnouncement, DISCOVERY_PORT, DISCOVERY_PROBE};
+    use tokio_with_wasm::tokio::net::UdpSocket;
+    use tokio_with_wasm::tokio::time::{timeout_at, Instant};
+
+    use super::DiscoveredBoard;
+    use crate::address::BoardAddress;
+
+    /// How long to wait for answers.
+    const SCAN_TIME: Duration = Duration::from_secs(2);
+
+    pub async fn probe() -> std::io::Result<Vec<DiscoveredBoard>> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+        socket.set_broadcast(true)?;
+        socket
+            .send_to(DISCOVERY_PROBE, (Ipv4Addr::BROADCAST, DISCOVERY_PORT))
+            .await?;
+        let deadline = Instant::now() + SCAN_TIME;
+        let mut boards: Vec<DiscoveredBoard> = Vec::new();
+        let mut buf = [0; 512];
+        while let Ok(received) = timeout_at(deadline, socket.recv_from(&mut buf)).await {
+            let (len, sender) = received?;
+            let Ok(announcement) = serde_json::from_slice::<BoardAnnouncement>(&buf[..len]) else {
+                continue;
+            };
+            let IpAddr::V4(sender) = sender.ip() else {
+                continue;
+            };
+            let ip = announcement.address.unwrap_or(sender);
+            let address = BoardAddress::new(ip).with_port(announcement.port);
+            if !boards.iter().any(|b| b.address == address) {
+                boards.push(DiscoveredBoard {
+                    address,
+                    announcement,
+                });
+            }
+        }
+        Ok(boards)
+    }
+}
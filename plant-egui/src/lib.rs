@@ -1,7 +1,13 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+mod address;
 mod app;
 mod board;
+mod chart;
+mod discovery;
+mod live;
 mod pages;
+#[cfg(not(target_arch = "wasm32"))]
+mod resolver;
 
 pub use app::App;
@@ -0,0 +1,135 @@
+//! Receives the changes boards push through `GET /live`, boards without it are polled.
+
+use std::sync::{Arc, Weak};
+
+use futures::StreamExt;
+use plant_common::LiveUpdate;
+use tokio_with_wasm::tokio::sync::mpsc::Sender;
+
+use crate::address::BoardAddress;
+use crate::app::BoardReply;
+
+/// Seconds without data after which the connection counts as lost, boards send a comment every
+/// 15 seconds to keep it open.
+const MAX_SILENCE: u64 = 45;
+
+/// The subscription of the GUI to the changes of a board.
+#[derive(Debug, Clone, Default)]
+pub enum LiveStatus {
+    /// The board is polled, it is subscribed to again once it answers.
+    #[default]
+    Disconnected,
+    /// The stream runs until the handle is dropped or the board stops sending.
+    Connecting(Arc<()>),
+    Connected(Arc<()>),
+    /// The board has no stream, it is only polled.
+    Unsupported,
+}
+
+impl LiveStatus {
+    pub fn is_connected(&self) -> bool {
+        matches!(self, LiveStatus::Connected(_))
+    }
+}
+
+/// What happened to the stream of a board.
+#[derive(Debug, Clone)]
+pub enum LiveReply {
+    Connected,
+    Update(Box<LiveUpdate>),
+    Closed,
+    Unsupported,
+}
+
+/// Subscribes to the changes of a board, they are sent back through `tx` until the returned
+/// status is dropped.
+pub fn subscribe(
+    tx: Sender<BoardReply>,
+    http_client: reqwest::Client,
+    address: BoardAddress,
+) -> LiveStatus {
+    let handle = Arc::new(());
+    let running = Arc::downgrade(&handle);
+    tokio_with_wasm::tokio::spawn(async move {
+        let reply = receive(&tx, http_client, &address, running).await;
+        // the board is gone if the app doesn't listen anymore
+        let _ = tx
+            .send(BoardReply {
+                live: Some((address, reply)),
+                ..Default::default()
+            })
+            .await;
+    });
+    LiveStatus::Connecting(handle)
+}
+
+/// Forwards the updates of the stream, returns why it ended.
+async fn receive(
+    tx: &Sender<BoardReply>,
+    http_client: reqwest::Client,
+    address: &BoardAddress,
+    running: Weak<()>,
+) -> LiveReply {
+    const MAX_WAIT_TIME: std::time::Duration = std::time::Duration::from_secs(5);
+    let request = http_client.get(address.url("/live")).send();
+    let response = tokio_with_wasm::tokio::select! {
+        output = request => output,
+        _ = tokio_with_wasm::tokio::time::sleep(MAX_WAIT_TIME) => return LiveReply::Closed,
+    };
+    let response = match response {
+        Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {
+            return LiveReply::Unsupported
+        }
+        Ok(response) if response.status().is_success() => response,
+        _ => return LiveReply::Closed,
+    };
+    let send = |reply| {
+        tx.send(BoardReply {
+            live: Some((address.clone(), reply)),
+            ..Default::default()
+        })
+    };
+    if send(LiveReply::Connected).await.is_err() {
+        return LiveReply::Closed;
+    }
+    let mut stream = response.bytes_stream();
+    let mut buffer = Vec::new();
+    loop {
+        let silence = std::time::Duration::from_secs(MAX_SILENCE);
+        let chunk = tokio_with_wasm::tokio::select! {
+            chunk = stream.next() => chunk,
+            _ = tokio_with_wasm::tokio::time::sleep(silence) => None,
+        };
+        let Some(Ok(chunk)) = chunk else {
+            return LiveReply::Closed;
+        };
+        if running.upgrade().is_none() {
+            return LiveReply::Closed;
+        }
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+            let event: Vec<u8> = buffer.drain(..end + 2).collect();
+            let Some(data) = event_data(&String::from_utf8_lossy(&event)) else {
+                continue;
+            };
+            match serde_json::from_str(&data) {
+                Ok(update) => {
+                    if send(LiveReply::Update(Box::new(update))).await.is_err() {
+                        return LiveReply::Closed;
+                    }
+                }
+                Err(e) => log::warn!("Cannot decode an update of {}: {}", address, e),
+            }
+        }
+    }
+}
+
+/// The data of a server-sent event, `None` for comments.
+fn event_data(event: &str) -> Option<String> {
+    let lines: Vec<&str> = event
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
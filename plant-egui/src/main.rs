@@ -1,10 +1,6 @@
 #![warn(clippy::all, rust_2018_idioms)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-mod app;
-mod board;
-mod pages;
-
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 pub fn main() -> eframe::Result<()> {
@@ -1,15 +1,27 @@
+use crate::address::BoardAddress;
 use crate::app::App;
+use crate::chart::{moisture_chart, sparkline, ChartRange, ChartValue};
 use egui::Ui;
+use plant_common::{HistoryResolution, PlantEvent, PlantEventKind, Timestamp};
+
+#[derive(Default)]
+pub struct HomePage {
+    /// The board and id of the plant shown in the detail window.
+    pub plant_details: Option<(BoardAddress, u16)>,
+    pub chart_range: ChartRange,
+    pub chart_value: ChartValue,
+}
 
 pub fn home_page(ui: &mut Ui, app: &mut App) {
+    let ui_time = ui.input(|i| i.time);
     for i in 0..app.boards.boards.len() {
         let board = &mut app.boards.boards[i];
         ui.horizontal(|ui| {
             if let Some(board_state) = &board.state {
-                ui.heading(&board_state.name);
-                ui.label(board.ip.to_string());
+                super::board_heading(ui, board_state);
+                ui.label(board.address.to_string());
             } else {
-                ui.heading(board.ip.to_string());
+                ui.heading(board.address.to_string());
             }
             match board.status {
                 crate::board::OnlineStatus::Online => {
@@ -25,16 +37,101 @@ pub fn home_page(ui: &mut Ui, app: &mut App) {
                     ui.colored_label(egui::Color32::RED, "Offline");
                 }
             }
+            if board.live.is_connected() {
+                ui.weak("live")
+                    .on_hover_text("The board pushes its changes, it isn't polled");
+            }
             if ui.button("Refresh").clicked() {
                 board.reload(app.board_sender.clone(), app.http_client.clone());
             }
+            match board.age(ui_time) {
+                Some(age) => {
+                    ui.weak(format!("updated {} ago", format_duration(age as u64)));
+                }
+                None if board.state.is_some() => {
+                    ui.weak("not updated yet")
+                        .on_hover_text("The values were saved when the app was last closed.");
+                }
+                None => {}
+            }
+            if let Some(time) = board.state.as_ref().and_then(|s| s.time) {
+                if !time.synced {
+                    ui.weak("Clock not synced")
+                        .on_hover_text("Times are relative to the start of the board.");
+                }
+            }
         });
+        let online = board.status == crate::board::OnlineStatus::Online;
+        let stale = board.is_stale(ui_time);
         if let Some(board_state) = &mut board.state {
+            let now = board_state.time;
+            // the history has wall-clock times only
+            let synced_now = now.filter(|t| t.synced);
             ui.indent("state", |ui| {
+                if stale {
+                    ui.visuals_mut().override_text_color = Some(ui.visuals().weak_text_color());
+                }
                 for plant in &mut board_state.plants {
                     ui.horizontal(|ui| {
-                        ui.strong(format!("{}:", &plant.name));
-                        ui.label(plant.measured_moisture.calulated_moisture().to_string());
+                        let name = egui::RichText::new(format!("{}:", &plant.name)).strong();
+                        if ui
+                            .add(egui::Label::new(name).sense(egui::Sense::click()))
+                            .on_hover_text("Show details")
+                            .clicked()
+                        {
+                            app.home_page.plant_details = Some((board.address.clone(), plant.id));
+                        }
+                        let moisture =
+                            ui.label(plant.measured_moisture.calulated_moisture().to_string());
+                        let age = plant
+                            .measured_at
+                            .zip(now)
+                            .and_then(|(t, now)| t.age_at(&now));
+                        if let Some(age) = age {
+                            moisture
+                                .on_hover_text(format!("Measured {} ago", format_duration(age)));
+                        }
+                        if let Some(now) = synced_now {
+                            if online {
+                                board.history.load(
+                                    app.board_sender.clone(),
+                                    app.http_client.clone(),
+                                    &board.address,
+                                    plant.id,
+                                    HistoryResolution::Minute,
+                                    ui_time,
+                                );
+                            }
+                            let history = board.history.get(plant.id, HistoryResolution::Minute);
+                            if let Some(history) = history {
+                                let id = (&board.address, plant.id, "sparkline");
+                                if sparkline(ui, id, plant, history, now.seconds)
+                                    .on_hover_text("Last day, click for details")
+                                    .clicked()
+                                {
+                                    app.home_page.plant_details =
+                                        Some((board.address.clone(), plant.id));
+                                }
+                            }
+                        }
+                        if let (Some(drying), Some(now)) = (&plant.drying, synced_now) {
+                            let due = drying.needs_water_in(now.seconds).filter(|s| *s > 0);
+                            if let Some(seconds) = due {
+                                ui.label(format!("water in ~{}", format_duration(seconds)))
+                                    .on_hover_text(format!(
+                                        "Drying by {:.1} % a day",
+                                        -drying.percent_per_day
+                                    ));
+                            }
+                        }
+                        if let Some(temperature) = plant.temperature {
+                            ui.label(format!("{:.1} °C", temperature));
+                        }
+                        if let Some(water) = plant.measured_moisture.recommended_water_ml() {
+                            if water >= 10.0 {
+                                ui.label(format!("add ~{:.0} ml", (water / 10.0).round() * 10.0));
+                            }
+                        }
                     });
                 }
             });
@@ -43,4 +140,156 @@ pub fn home_page(ui: &mut Ui, app: &mut App) {
             ui.separator();
         }
     }
+    plant_details_window(ui, app);
+}
+
+fn plant_details_window(ui: &mut Ui, app: &mut App) {
+    let Some((address, id)) = app.home_page.plant_details.clone() else {
+        return;
+    };
+    let Some(board) = app.boards.boards.iter_mut().find(|b| b.address == address) else {
+        return;
+    };
+    let resolution = app.home_page.chart_range.resolution();
+    if board.status == crate::board::OnlineStatus::Online {
+        let ui_time = ui.input(|i| i.time);
+        let (tx, client) = (app.board_sender.clone(), app.http_client.clone());
+        board
+            .history
+            .load(tx, client, &address, id, resolution, ui_time);
+    }
+    let page = &mut app.home_page;
+    let Some(state) = &board.state else {
+        return;
+    };
+    let Some(plant) = state.plants.iter().find(|p| p.id == id) else {
+        return;
+    };
+    let mut open = true;
+    egui::Window::new(&plant.name)
+        .id(egui::Id::new((&address, id, "details")))
+        .open(&mut open)
+        .collapsible(false)
+        .show(ui.ctx(), |ui| {
+            egui::Grid::new("plant_details").show(ui, |ui| {
+                ui.label("Moisture:");
+                ui.label(plant.measured_moisture.calulated_moisture().to_string());
+                ui.end_row();
+                ui.label("Soil:");
+                ui.label(plant.measured_moisture.soil.to_string());
+                ui.end_row();
+                if let Some(volume) = plant.measured_moisture.pot_volume {
+                    ui.label("Pot:");
+                    ui.label(format!("{:.0} ml", volume));
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            let mut reset = false;
+            ui.horizontal(|ui| {
+                for range in ChartRange::ALL {
+                    let selected = page.chart_range == range;
+                    if ui.selectable_label(selected, range.to_string()).clicked() {
+                        page.chart_range = range;
+                        reset = true;
+                    }
+                }
+                ui.separator();
+                reset |= ui
+                    .radio_value(&mut page.chart_value, ChartValue::Percent, "Moisture")
+                    .clicked();
+                reset |= ui
+                    .radio_value(&mut page.chart_value, ChartValue::Voltage, "Sensor reading")
+                    .clicked();
+            });
+            match state.time.filter(|t| t.synced) {
+                Some(now) => moisture_chart(
+                    ui,
+                    (&address, id, "chart"),
+                    plant,
+                    board.history.get(id, resolution),
+                    &board.events,
+                    now.seconds,
+                    page.chart_range,
+                    page.chart_value,
+                    reset,
+                ),
+                None => {
+                    ui.weak("The history is kept once the clock of the board is synced.");
+                }
+            }
+            ui.separator();
+            let events: Vec<_> = board.events.iter().filter(|e| e.plant == id).collect();
+            if events.is_empty() {
+                ui.weak("Nothing recorded yet.");
+            }
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    for event in events.into_iter().rev() {
+                        ui.label(describe_event(event, state.time));
+                    }
+                });
+        });
+    if !open {
+        page.plant_details = None;
+    }
+}
+
+/// Describes an event of a plant, with how long ago it happened if the board knows.
+pub fn describe_event(event: &PlantEvent, now: Option<Timestamp>) -> String {
+    let when = now
+        .and_then(|now| event.time.age_at(&now))
+        .map(|age| format!(" {} ago", format_duration(age)))
+        .unwrap_or_default();
+    match &event.kind {
+        PlantEventKind::Watered {
+            moisture_before,
+            moisture_after,
+            amount_ml,
+        } => {
+            let mut text = format!("Watered{}", when);
+            if let Some(amount) = amount_ml {
+                text += &format!(", ~{:.0} ml", amount);
+            }
+            if let Some((before, after)) = moisture_before.zip(*moisture_after) {
+                text += &format!(" ({:.0} % → {:.0} %)", before, after);
+            }
+            text
+        }
+        PlantEventKind::Pumped {
+            amount_ml,
+            moisture_before,
+            dry_run,
+        } => {
+            let verb = if *dry_run {
+                "Would have pumped"
+            } else {
+                "Pumped"
+            };
+            format!(
+                "{} {:.0} ml{} at {:.0} %",
+                verb, amount_ml, when, moisture_before
+            )
+        }
+        PlantEventKind::LockedOut(reason) => format!("Stopped watering{}: {}", when, reason),
+        PlantEventKind::BudgetExhausted { budget_ml } => {
+            format!("Daily budget of {:.0} ml used up{}", budget_ml, when)
+        }
+        PlantEventKind::ControllerReset => format!("Watering resumed{}", when),
+        PlantEventKind::WateredByHand { amount_ml } => match amount_ml {
+            Some(amount) => format!("Watered by hand{}, {:.0} ml", when, amount),
+            None => format!("Watered by hand{}", when),
+        },
+    }
+}
+
+pub fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{} s", seconds),
+        60..=3599 => format!("{} min", seconds / 60),
+        3600..=86_399 => format!("{} h", seconds / 3600),
+        86_400..=129_599 => "1 day".to_string(),
+        _ => format!("{} days", (seconds + 43_200) / 86_400),
+    }
 }
@@ -1,2 +1,27 @@
 pub mod home;
-pub mod settings;
\ No newline at end of file
+pub mod settings;
+pub mod watering;
+
+use egui::Ui;
+use plant_common::BoardState;
+
+/// The name of a board with its icon and colour, followed by its location.
+pub fn board_heading(ui: &mut Ui, state: &BoardState) -> egui::Response {
+    let metadata = &state.metadata;
+    let text = match &metadata.icon {
+        Some(icon) => format!("{} {}", icon, state.name),
+        None => state.name.clone(),
+    };
+    let mut text = egui::RichText::new(text).heading();
+    if let Some([r, g, b]) = metadata.color {
+        text = text.color(egui::Color32::from_rgb(r, g, b));
+    }
+    let mut response = ui.label(text);
+    if !metadata.description.is_empty() {
+        response = response.on_hover_text(&metadata.description);
+    }
+    if !metadata.location.is_empty() {
+        ui.weak(&metadata.location);
+    }
+    response
+}
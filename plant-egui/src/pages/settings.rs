@@ -1,20 +1,67 @@
-use std::net::Ipv4Addr;
-
 use crate::{
+    address::BoardAddress,
     app::App,
-    board::{self, OnlineStatus},
+    board::{Board, OnlineStatus},
+    discovery::{self, DiscoveredBoard},
 };
 use egui::Ui;
+use plant_common::{
+    Actuator, ActuatorKind, BoardMetadata, BoardUpdate, CalibrationCheck, CalibrationReference,
+    CareProfileId, Connector, ConnectorInfo, ConnectorStatus, ControllerSettings,
+    CustomCareProfile, FlowSensor, PlantSettings, PlantUpdate, SoilType, WifiMode, API_VERSION,
+    MAX_ACTUATORS, MAX_DESCRIPTION_LENGTH, MAX_HOST_LENGTH, MAX_ICON_LENGTH, MAX_NAME_LENGTH,
+    MAX_SSID_LENGTH, PROVISIONING_ADDRESS,
+};
 
+#[derive(Default)]
 pub struct SettingsPage {
-    pub new_board: Ipv4Addr,
+    /// The address of the board to add as typed, like `192.168.178.198` or `plants.local:8080`.
+    pub new_board: String,
+    /// The boards found by the last scan of the network.
+    pub discovered: Vec<DiscoveredBoard>,
+    pub scanning: bool,
+    pub calibration_wizard: Option<CalibrationWizard>,
+    pub plant_editor: Option<PlantEditor>,
+    pub board_editor: Option<BoardEditor>,
 }
 
-impl Default for SettingsPage {
-    fn default() -> Self {
-        Self {
-            new_board: Ipv4Addr::new(192, 168, 178, 198),
-        }
+/// The settings of a plant while they are edited, sent to the board when saved.
+pub struct PlantEditor {
+    pub board: BoardAddress,
+    pub plant: u16,
+    pub settings: PlantSettings,
+}
+
+pub struct BoardEditor {
+    pub board: BoardAddress,
+    pub name: String,
+    /// `None` for boards without a clock.
+    pub sntp_server: Option<String>,
+    pub metadata: BoardMetadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CalibrationStep {
+    Dry,
+    Wet,
+    /// Both references are captured, with the check of the board.
+    Done(CalibrationCheck),
+}
+
+/// State of the step-by-step two-point calibration of a single plant sensor.
+pub struct CalibrationWizard {
+    pub board: BoardAddress,
+    pub plant: u16,
+    pub step: CalibrationStep,
+}
+
+impl CalibrationWizard {
+    /// Moves on once the board captured the reference of the current step.
+    pub fn calibrated(&mut self, check: CalibrationCheck) {
+        self.step = match self.step {
+            CalibrationStep::Dry => CalibrationStep::Wet,
+            CalibrationStep::Wet | CalibrationStep::Done(_) => CalibrationStep::Done(check),
+        };
     }
 }
 
@@ -25,10 +72,25 @@ pub fn settings_page(ui: &mut Ui, app: &mut App) {
         let mut delete = false;
         ui.horizontal(|ui| {
             if let Some(board_state) = &board.state {
-                ui.heading(&board_state.name);
-                ui.label(board.ip.to_string());
+                super::board_heading(ui, board_state);
+                if ui
+                    .add_enabled(
+                        board.status == OnlineStatus::Online,
+                        egui::Button::new('\u{270F}'.to_string()),
+                    )
+                    .on_hover_text("Edit")
+                    .clicked()
+                {
+                    app.settings_page.board_editor = Some(BoardEditor {
+                        board: board.address.clone(),
+                        name: board_state.name.clone(),
+                        sntp_server: board_state.sntp_server.clone(),
+                        metadata: board_state.metadata.clone(),
+                    });
+                }
+                ui.label(board.address.to_string());
             } else {
-                ui.heading(board.ip.to_string());
+                ui.heading(board.address.to_string());
             }
             match board.status {
                 crate::board::OnlineStatus::Online => {
@@ -50,6 +112,15 @@ pub fn settings_page(ui: &mut Ui, app: &mut App) {
             if ui.button("Refresh").clicked() {
                 board.reload(app.board_sender.clone(), app.http_client.clone());
             }
+            ui.add(
+                egui::DragValue::new(&mut board.poll_interval)
+                    .clamp_range(0..=3600)
+                    .custom_formatter(|n, _| match n as u32 {
+                        0 => "manually".to_string(),
+                        n => format!("every {} s", n),
+                    }),
+            )
+            .on_hover_text("How often the board is reloaded");
         });
         let board = app.boards.boards.get_mut(i as usize).unwrap();
         if let Some(board_state) = board.state.clone() {
@@ -57,7 +128,91 @@ pub fn settings_page(ui: &mut Ui, app: &mut App) {
                 for plant in &board_state.plants {
                     ui.horizontal(|ui| {
                         ui.strong(format!("{}:", &plant.name));
-                        ui.label(format!("{:?}", &plant.connection));
+                        ui.label(plant.connection.to_string());
+                        let mut soil = plant.measured_moisture.soil.clone();
+                        if soil_combo_box(ui, (&board.address, plant.id), &mut soil)
+                            && board.status == OnlineStatus::Online
+                        {
+                            board.update_plant(
+                                app.board_sender.clone(),
+                                app.http_client.clone(),
+                                plant.id,
+                                PlantUpdate {
+                                    soil: Some(soil),
+                                    ..Default::default()
+                                },
+                            );
+                        }
+                        let mut volume = plant.measured_moisture.pot_volume.unwrap_or(0.0);
+                        let response = ui.add(pot_volume_drag_value(&mut volume));
+                        if response.changed() {
+                            // keep the edited value until the board answers
+                            if let Some(edited) = board
+                                .state
+                                .as_mut()
+                                .and_then(|s| s.plants.iter_mut().find(|p| p.id == plant.id))
+                            {
+                                edited.measured_moisture.pot_volume = pot_volume(volume);
+                            }
+                        }
+                        if (response.drag_stopped() || response.lost_focus())
+                            && board.status == OnlineStatus::Online
+                        {
+                            board.update_plant(
+                                app.board_sender.clone(),
+                                app.http_client.clone(),
+                                plant.id,
+                                PlantUpdate {
+                                    pot_volume: Some(pot_volume(volume)),
+                                    ..Default::default()
+                                },
+                            );
+                        }
+                        let mut profile = plant.care_profile.clone();
+                        if care_profile_combo_box(
+                            ui,
+                            (&board.address, plant.id, "care"),
+                            &board_state.care_profiles,
+                            &mut profile,
+                        ) && board.status == OnlineStatus::Online
+                        {
+                            board.update_plant(
+                                app.board_sender.clone(),
+                                app.http_client.clone(),
+                                plant.id,
+                                PlantUpdate {
+                                    care_profile: Some(profile),
+                                    ..Default::default()
+                                },
+                            );
+                        }
+                        if ui
+                            .add_enabled(
+                                board.status == OnlineStatus::Online,
+                                egui::Button::new('\u{270F}'.to_string()),
+                            )
+                            .on_hover_text("Edit")
+                            .clicked()
+                        {
+                            app.settings_page.plant_editor = Some(PlantEditor {
+                                board: board.address.clone(),
+                                plant: plant.id,
+                                settings: plant.clone().into(),
+                            });
+                        }
+                        if ui
+                            .add_enabled(
+                                board.status == OnlineStatus::Online,
+                                egui::Button::new("Calibrate"),
+                            )
+                            .clicked()
+                        {
+                            app.settings_page.calibration_wizard = Some(CalibrationWizard {
+                                board: board.address.clone(),
+                                plant: plant.id,
+                                step: CalibrationStep::Dry,
+                            });
+                        }
                         if ui
                             .add_enabled(
                                 board.status == OnlineStatus::Online,
@@ -78,23 +233,42 @@ pub fn settings_page(ui: &mut Ui, app: &mut App) {
         if board.status == OnlineStatus::Online || board.status == OnlineStatus::LoadingWasOnline {
             ui.horizontal(|ui| {
                 ui.label("New plant name:");
-                ui.text_edit_singleline(&mut board.settings_new_plant_name);
+                ui.add(name_edit(&mut board.settings_new_plant_name));
                 ui.label("Port:");
-                ui.add(
-                    egui::DragValue::new(&mut board.settings_new_plant_port).clamp_range(0..=40),
+                connector_editor(
+                    ui,
+                    &board.address,
+                    &mut board.settings_new_plant_connector,
+                    &board.connectors,
+                    None,
                 );
+                ui.label("Soil:");
+                soil_combo_box(ui, &board.address, &mut board.settings_new_plant_soil);
+                ui.label("Pot:");
+                ui.add(pot_volume_drag_value(
+                    &mut board.settings_new_plant_pot_volume,
+                ));
                 if ui
-                    .add_enabled(board.status == OnlineStatus::Online, egui::Button::new('\u{2795}'.to_string()))
+                    .add_enabled(
+                        board.status == OnlineStatus::Online,
+                        egui::Button::new('\u{2795}'.to_string()),
+                    )
                     .clicked()
                 {
-                    board.create_plant(
-                        app.board_sender.clone(),
-                        app.http_client.clone(),
-                        board.settings_new_plant_name.clone(),
-                        plant_common::Connector::GPIO(board.settings_new_plant_port),
-                    );
+                    let settings = PlantSettings {
+                        name: board.settings_new_plant_name.clone(),
+                        connection: board.settings_new_plant_connector.clone(),
+                        soil: board.settings_new_plant_soil.clone(),
+                        pot_volume: pot_volume(board.settings_new_plant_pot_volume),
+                        care_profile: CareProfileId::default(),
+                        actuators: Vec::new(),
+                        controller: None,
+                    };
+                    board.create_plant(app.board_sender.clone(), app.http_client.clone(), settings);
                 }
             });
+            care_profiles(ui, app, i as usize);
+            wifi_networks(ui, app, i as usize);
         }
         ui.separator();
         if delete {
@@ -105,29 +279,855 @@ pub fn settings_page(ui: &mut Ui, app: &mut App) {
     }
     ui.heading("Add new board");
     ui.horizontal(|ui| {
-        ui.label("IP:");
-        let mut octets = app.settings_page.new_board.octets();
-        ui.add(egui::DragValue::new(&mut octets[0]).clamp_range(0..=255));
-        ui.label(".");
-        ui.add(egui::DragValue::new(&mut octets[1]).clamp_range(0..=255));
-        ui.label(".");
-        ui.add(egui::DragValue::new(&mut octets[2]).clamp_range(0..=255));
-        ui.label(".");
-        ui.add(egui::DragValue::new(&mut octets[3]).clamp_range(0..=255));
-        app.settings_page.new_board = Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]);
-        if ui.button('\u{2795}'.to_string()).clicked() {
-            app.boards.boards.push(crate::board::Board {
-                ip: app.settings_page.new_board,
-                status: crate::board::OnlineStatus::Offline,
-                state: None,
-                settings_new_plant_name: "New plant".to_string(),
-                settings_new_plant_port: 0,
+        ui.label("Address:");
+        ui.add(
+            egui::TextEdit::singleline(&mut app.settings_page.new_board)
+                .hint_text("192.168.178.198, plants.local:8080 or https://example.org/plants"),
+        );
+        let address = app.settings_page.new_board.parse::<BoardAddress>();
+        let add = ui.add_enabled(address.is_ok(), egui::Button::new('\u{2795}'.to_string()));
+        match address {
+            Ok(address) => {
+                if add.clicked() {
+                    add_board(app, address);
+                    app.settings_page.new_board.clear();
+                }
+            }
+            Err(e) if !app.settings_page.new_board.trim().is_empty() => {
+                ui.colored_label(egui::Color32::RED, e.to_string());
+            }
+            Err(_) => {}
+        }
+    });
+    ui.horizontal(|ui| {
+        let address = BoardAddress::new(PROVISIONING_ADDRESS);
+        let known = app.boards.boards.iter().any(|b| b.address == address);
+        if ui
+            .add_enabled(!known, egui::Button::new("Add a board in setup mode"))
+            .on_hover_text(
+                "Boards without a network open their own, named Plant and the name of the board. \
+                 Connect this computer to it, add the board and give it a network in its Wi-Fi \
+                 settings.",
+            )
+            .clicked()
+        {
+            add_board(app, address);
+        }
+    });
+    discovered_boards(ui, app);
+    calibration_window(ui, app);
+    plant_editor_window(ui, app);
+    board_editor_window(ui, app);
+}
+
+/// Adds a board unless it is already there and loads its state.
+fn add_board(app: &mut App, address: BoardAddress) {
+    if app.boards.boards.iter().any(|b| b.address == address) {
+        return;
+    }
+    let mut board = Board::new(address);
+    board.reload(app.board_sender.clone(), app.http_client.clone());
+    app.boards.boards.push(board);
+}
+
+/// Scans the network for boards and lists the ones that aren't added yet.
+fn discovered_boards(ui: &mut Ui, app: &mut App) {
+    ui.horizontal(|ui| {
+        let scan = egui::Button::new("Scan network");
+        if ui
+            .add_enabled(discovery::CAN_SCAN && !app.settings_page.scanning, scan)
+            .on_disabled_hover_text("Browsers can't scan, enter the address of the board.")
+            .clicked()
+        {
+            app.settings_page.scanning = true;
+            discovery::scan(app.board_sender.clone());
+        }
+        if app.settings_page.scanning {
+            ui.spinner();
+        }
+    });
+    let mut added = None;
+    for found in &app.settings_page.discovered {
+        let known = app.boards.boards.iter().any(|b| b.address == found.address);
+        let announcement = &found.announcement;
+        ui.horizontal(|ui| {
+            ui.strong(&announcement.name);
+            ui.label(found.address.to_string());
+            ui.weak(format!("firmware {}", announcement.firmware_version));
+            if announcement.api_version != API_VERSION {
+                ui.colored_label(egui::Color32::YELLOW, "Incompatible version")
+                    .on_hover_text("Update the app and the board to the same version.");
+            }
+            let add = ui
+                .add_enabled(!known, egui::Button::new('\u{2795}'.to_string()))
+                .on_disabled_hover_text("The board is already added.");
+            if add.clicked() {
+                added = Some(found.address.clone());
+            }
+        });
+    }
+    if let Some(address) = added {
+        add_board(app, address);
+    }
+}
+
+/// Lists the custom care profiles of a board and allows creating new ones.
+fn care_profiles(ui: &mut Ui, app: &mut App, index: usize) {
+    let board = &mut app.boards.boards[index];
+    let online = board.status == OnlineStatus::Online;
+    egui::CollapsingHeader::new("Care profiles")
+        .id_source((&board.address, "care_profiles"))
+        .show(ui, |ui| {
+            if let Some(board_state) = board.state.clone() {
+                for custom in &board_state.care_profiles {
+                    ui.horizontal(|ui| {
+                        ui.strong(format!("{}:", &custom.profile.name));
+                        let band = &custom.profile.band;
+                        ui.label(format!(
+                            "perfect {:.0}–{:.0} %, water below {:.0} %, dry out {:.1} days",
+                            band.dry_below,
+                            band.moist_above,
+                            custom.profile.needs_water_below,
+                            custom.profile.dry_out_days
+                        ));
+                        if ui
+                            .add_enabled(online, egui::Button::new('\u{1F5D1}'.to_string()))
+                            .clicked()
+                        {
+                            board.delete_care_profile(
+                                app.board_sender.clone(),
+                                app.http_client.clone(),
+                                custom.id,
+                            );
+                        }
+                    });
+                }
+            }
+            let new_profile = &mut board.settings_new_profile;
+            let mut create = false;
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.add(name_edit(&mut new_profile.name));
+            });
+            ui.horizontal(|ui| {
+                let band = &mut new_profile.band;
+                ui.label("Very dry below:");
+                ui.add(percent_drag_value(&mut band.very_dry_below));
+                ui.label("Dry below:");
+                ui.add(percent_drag_value(&mut band.dry_below));
+                ui.label("Moist above:");
+                ui.add(percent_drag_value(&mut band.moist_above));
+                ui.label("Wet above:");
+                ui.add(percent_drag_value(&mut band.wet_above));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Needs water below:");
+                ui.add(percent_drag_value(&mut new_profile.needs_water_below));
+                ui.label("Dry out days:");
+                ui.add(
+                    egui::DragValue::new(&mut new_profile.dry_out_days)
+                        .clamp_range(0.0..=60.0)
+                        .speed(0.1),
+                )
+                .on_hover_text("How long automatic watering waits once the plant needs water");
+                create = ui
+                    .add_enabled(online, egui::Button::new('\u{2795}'.to_string()))
+                    .clicked();
+            });
+            if create {
+                let profile = board.settings_new_profile.clone();
+                board.create_care_profile(
+                    app.board_sender.clone(),
+                    app.http_client.clone(),
+                    profile,
+                );
+            }
+        });
+}
+
+/// Lists the networks the board joins and allows adding new ones.
+fn wifi_networks(ui: &mut Ui, app: &mut App, index: usize) {
+    let board = &mut app.boards.boards[index];
+    let online = board.status == OnlineStatus::Online;
+    egui::CollapsingHeader::new("Wi-Fi")
+        .id_source((&board.address, "wifi"))
+        .show(ui, |ui| {
+            let Some(wifi) = board.wifi.clone() else {
+                ui.weak("Loading…");
+                return;
+            };
+            match &wifi.mode {
+                WifiMode::Unmanaged => {
+                    ui.weak("The board doesn't manage its network, the networks are only stored.");
+                }
+                WifiMode::Joining => {
+                    ui.label("Joining a network…");
+                }
+                WifiMode::Connected { ssid } => {
+                    ui.label(format!("Connected to {}", ssid));
+                }
+                WifiMode::AccessPoint { ssid } => {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("Setup mode, the board opened the network {}", ssid),
+                    )
+                    .on_hover_text("It joins one of its networks once it is in reach again.");
+                }
+            }
+            let mut removed = None;
+            for network in &wifi.networks {
+                ui.horizontal(|ui| {
+                    ui.label(&network.ssid);
+                    if !network.secured {
+                        ui.weak("open");
+                    }
+                    if ui
+                        .add_enabled(online, egui::Button::new('\u{1F5D1}'.to_string()))
+                        .on_hover_text("Forget the network")
+                        .clicked()
+                    {
+                        removed = Some(network.ssid.clone());
+                    }
+                });
+            }
+            if wifi.networks.is_empty() {
+                ui.weak("The board knows no network yet.");
+            }
+            let new_network = &mut board.settings_new_network;
+            let mut add = false;
+            ui.horizontal(|ui| {
+                ui.label("SSID:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut new_network.ssid)
+                        .char_limit(MAX_SSID_LENGTH)
+                        .desired_width(120.0),
+                );
+                ui.label("Password:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut new_network.password)
+                        .password(true)
+                        .desired_width(120.0),
+                );
+                add = ui
+                    .add_enabled(
+                        online && !new_network.ssid.is_empty(),
+                        egui::Button::new('\u{2795}'.to_string()),
+                    )
+                    .on_hover_text("Adds the network or changes its password.")
+                    .clicked();
+            });
+            if add {
+                let network = std::mem::take(&mut board.settings_new_network);
+                board.add_wifi_network(app.board_sender.clone(), app.http_client.clone(), network);
+            }
+            if let Some(ssid) = removed {
+                board.remove_wifi_network(app.board_sender.clone(), app.http_client.clone(), &ssid);
+            }
+            if matches!(wifi.mode, WifiMode::AccessPoint { .. }) && !wifi.networks.is_empty() {
+                ui.weak(
+                    "Once the board joined the network, connect this computer to it again \
+                     and scan for the board.",
+                );
+            }
+        });
+}
+
+/// Edits the connector of a plant, switching between a GPIO pin and an I2C sensor.
+///
+/// Once the connectors of the board are loaded, only free GPIOs that can be read can be chosen.
+/// `plant` is the plant being edited, its own connector counts as free.
+fn connector_editor(
+    ui: &mut Ui,
+    id_source: impl std::hash::Hash + Clone,
+    connector: &mut Connector,
+    connectors: &[ConnectorInfo],
+    plant: Option<u16>,
+) {
+    let i2c = Connector::I2c {
+        bus: 0,
+        address: Connector::SEESAW_ADDRESS,
+        channel: None,
+    };
+    egui::ComboBox::from_id_source(id_source.clone())
+        .selected_text(match connector {
+            Connector::GPIO(_) => "GPIO",
+            Connector::I2c { .. } => "I2C",
+        })
+        .show_ui(ui, |ui| {
+            let is_gpio = matches!(connector, Connector::GPIO(_));
+            if ui.selectable_label(is_gpio, "GPIO").clicked() && !is_gpio {
+                *connector = connectors
+                    .iter()
+                    .find(|c| {
+                        matches!(c.connector, Connector::GPIO(_))
+                            && c.status == ConnectorStatus::Free
+                    })
+                    .map(|c| c.connector.clone())
+                    .unwrap_or_default();
+            }
+            if ui.selectable_label(!is_gpio, "I2C").clicked() && is_gpio {
+                *connector = i2c;
+            }
+        });
+    // the status of a connector, as far as the plant being edited is concerned
+    let status = |info: &ConnectorInfo| match info.status {
+        ConnectorStatus::Used(id) if Some(id) == plant => ConnectorStatus::Free,
+        ref status => status.clone(),
+    };
+    match connector {
+        Connector::GPIO(pin) => {
+            let gpios: Vec<_> = connectors
+                .iter()
+                .filter_map(|info| match info.connector {
+                    Connector::GPIO(pin) => Some((pin, info)),
+                    Connector::I2c { .. } => None,
+                })
+                .collect();
+            if gpios.is_empty() {
+                ui.add(egui::DragValue::new(pin).clamp_range(0..=40));
+                return;
+            }
+            egui::ComboBox::from_id_source((id_source, "pin"))
+                .selected_text(pin.to_string())
+                .show_ui(ui, |ui| {
+                    for (gpio, info) in gpios {
+                        let reason = match status(info) {
+                            ConnectorStatus::Free => None,
+                            ConnectorStatus::Used(id) => Some(format!("Used by plant {}", id)),
+                            ConnectorStatus::Unsupported => {
+                                Some(info.capability.map(|c| c.to_string()).unwrap_or_default())
+                            }
+                        };
+                        let response = ui.add_enabled(
+                            reason.is_none(),
+                            egui::SelectableLabel::new(*pin == gpio, gpio.to_string()),
+                        );
+                        if let Some(reason) = reason {
+                            response.on_disabled_hover_text(reason);
+                        } else if response.clicked() {
+                            *pin = gpio;
+                        }
+                    }
+                });
+        }
+        Connector::I2c {
+            bus,
+            address,
+            channel,
+        } => {
+            ui.label("Bus:");
+            ui.add(egui::DragValue::new(bus).clamp_range(0..=1));
+            ui.label("Address:");
+            ui.add(
+                egui::DragValue::new(address)
+                    .clamp_range(0x08..=0x77)
+                    .hexadecimal(2, false, false)
+                    .prefix("0x"),
+            );
+            let mut multiplexed = channel.is_some();
+            if ui.checkbox(&mut multiplexed, "Multiplexer").changed() {
+                *channel = multiplexed.then_some(0);
+            }
+            if let Some(channel) = channel {
+                ui.add(egui::DragValue::new(channel).clamp_range(0..=7));
+            }
+            let used_by = connectors.iter().find_map(|info| match status(info) {
+                ConnectorStatus::Used(id) if info.connector.conflicts_with(connector) => Some(id),
+                _ => None,
             });
-            app.boards
-                .boards
-                .last_mut()
-                .unwrap()
-                .reload(app.board_sender.clone(), app.http_client.clone());
+            if let Some(id) = used_by {
+                ui.colored_label(ui.visuals().warn_fg_color, '\u{26A0}'.to_string())
+                    .on_hover_text(format!("Conflicts with the sensor of plant {}", id));
+            }
         }
+    }
+}
+
+fn name_edit(name: &mut String) -> egui::TextEdit<'_> {
+    egui::TextEdit::singleline(name).char_limit(MAX_NAME_LENGTH)
+}
+
+/// Pot volume in millilitres, 0 means unknown.
+fn pot_volume_drag_value(value: &mut f32) -> egui::DragValue<'_> {
+    egui::DragValue::new(value)
+        .clamp_range(0.0..=100_000.0)
+        .speed(10.0)
+        .suffix(" ml")
+}
+
+fn pot_volume(value: f32) -> Option<f32> {
+    (value > 0.0).then_some(value)
+}
+
+/// Edits the pumps and valves of a plant, the board checks their pins when they are saved.
+fn actuators_editor(ui: &mut Ui, actuators: &mut Vec<Actuator>) {
+    let mut removed = None;
+    for (index, actuator) in actuators.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source(("actuator_kind", index))
+                .selected_text(actuator.kind.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in [ActuatorKind::Pump, ActuatorKind::Valve] {
+                        ui.selectable_value(&mut actuator.kind, kind, kind.to_string());
+                    }
+                });
+            ui.add(
+                egui::DragValue::new(&mut actuator.pin)
+                    .clamp_range(0..=39)
+                    .prefix("GPIO "),
+            );
+            ui.checkbox(&mut actuator.active_low, "Active low")
+                .on_hover_text("Most relay modules switch on with a low pin.");
+            if ui.button('\u{2716}'.to_string()).clicked() {
+                removed = Some(index);
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut rate = actuator.flow_rate.unwrap_or(0.0);
+            let flow_rate = egui::DragValue::new(&mut rate)
+                .clamp_range(0.0..=1000.0)
+                .speed(0.1)
+                .suffix(" ml/s");
+            if ui
+                .add(flow_rate)
+                .on_hover_text("Measured flow, 0 if unknown")
+                .changed()
+            {
+                actuator.flow_rate = (rate > 0.0).then_some(rate);
+            }
+            let mut has_sensor = actuator.flow_sensor.is_some();
+            if ui.checkbox(&mut has_sensor, "Flow sensor").changed() {
+                actuator.flow_sensor = has_sensor.then_some(FlowSensor {
+                    pin: 17,
+                    pulses_per_litre: 450.0,
+                });
+            }
+            if let Some(sensor) = &mut actuator.flow_sensor {
+                ui.add(
+                    egui::DragValue::new(&mut sensor.pin)
+                        .clamp_range(0..=39)
+                        .prefix("GPIO "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut sensor.pulses_per_litre)
+                        .clamp_range(1.0..=10_000.0)
+                        .suffix(" pulses/l"),
+                );
+            }
+        });
+    }
+    if let Some(index) = removed {
+        actuators.remove(index);
+    }
+    if actuators.len() < MAX_ACTUATORS && ui.button("Add pump").clicked() {
+        actuators.push(Actuator {
+            pin: 16,
+            active_low: true,
+            ..Default::default()
+        });
+    }
+}
+
+/// Edits the automatic watering of a plant, it needs an actuator to water with.
+fn controller_editor(ui: &mut Ui, controller: &mut Option<ControllerSettings>, actuators: usize) {
+    let mut automatic = controller.is_some();
+    let enabled = actuators > 0 || automatic;
+    let checkbox = egui::Checkbox::new(&mut automatic, "Water automatically");
+    if ui
+        .add_enabled(enabled, checkbox)
+        .on_disabled_hover_text("Add a pump first.")
+        .changed()
+    {
+        *controller = automatic.then(ControllerSettings::default);
+    }
+    let Some(controller) = controller else {
+        return;
+    };
+    egui::Grid::new("controller_editor").show(ui, |ui| {
+        ui.label("Actuator:");
+        ui.add(
+            egui::DragValue::new(&mut controller.actuator)
+                .clamp_range(0..=actuators.saturating_sub(1))
+                .custom_formatter(|n, _| format!("{}", n as usize + 1)),
+        );
+        ui.end_row();
+        ui.label("Dry run:");
+        ui.checkbox(&mut controller.dry_run, "")
+            .on_hover_text("Only record what would be watered, the pump is never started.");
+        ui.end_row();
+        ui.label("Water after:");
+        minutes_drag_value(ui, &mut controller.dry_for).on_hover_text(
+            "How long the plant has to be dry before it is watered, \
+            the dry out days of its care profile if they are longer",
+        );
+        ui.end_row();
+        ui.label("Soak for:");
+        minutes_drag_value(ui, &mut controller.soak_for)
+            .on_hover_text("How long to wait before measuring again after watering");
+        ui.end_row();
+        ui.label("Dose:");
+        ui.add(
+            egui::DragValue::new(&mut controller.max_dose_ml)
+                .clamp_range(1.0..=1000.0)
+                .suffix(" ml"),
+        )
+        .on_hover_text("Most water given at once");
+        ui.end_row();
+        ui.label("Daily budget:");
+        ui.add(
+            egui::DragValue::new(&mut controller.daily_budget_ml)
+                .clamp_range(1.0..=10_000.0)
+                .suffix(" ml"),
+        );
+        ui.end_row();
     });
 }
+
+/// Edits a duration in seconds as whole minutes.
+fn minutes_drag_value(ui: &mut Ui, seconds: &mut u32) -> egui::Response {
+    let mut minutes = *seconds / 60;
+    let response = ui.add(
+        egui::DragValue::new(&mut minutes)
+            .clamp_range(1..=24 * 60)
+            .suffix(" min"),
+    );
+    *seconds = minutes * 60;
+    response
+}
+
+fn percent_drag_value(value: &mut f32) -> egui::DragValue<'_> {
+    egui::DragValue::new(value)
+        .clamp_range(0.0..=100.0)
+        .suffix(" %")
+}
+
+/// Shows a combo box with the built-in and the custom care profiles, returns whether the selection changed.
+fn care_profile_combo_box(
+    ui: &mut Ui,
+    id_source: impl std::hash::Hash,
+    custom: &[CustomCareProfile],
+    profile: &mut CareProfileId,
+) -> bool {
+    let name = |id: &CareProfileId| match id {
+        CareProfileId::Custom(custom_id) => custom
+            .iter()
+            .find(|p| p.id == *custom_id)
+            .map(|p| p.profile.name.clone())
+            .unwrap_or_else(|| id.to_string()),
+        _ => id.to_string(),
+    };
+    let mut changed = false;
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(name(profile))
+        .show_ui(ui, |ui| {
+            let options = CareProfileId::BUILTIN
+                .into_iter()
+                .chain(custom.iter().map(|p| CareProfileId::Custom(p.id)));
+            for option in options {
+                let text = name(&option);
+                changed |= ui.selectable_value(profile, option, text).changed();
+            }
+        });
+    changed
+}
+
+/// Shows a combo box with all soil types, returns whether the selection changed.
+fn soil_combo_box(ui: &mut Ui, id_source: impl std::hash::Hash, soil: &mut SoilType) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(soil.to_string())
+        .show_ui(ui, |ui| {
+            for option in SoilType::ALL {
+                let text = option.to_string();
+                changed |= ui.selectable_value(soil, option, text).changed();
+            }
+        });
+    changed
+}
+
+fn calibration_window(ui: &mut Ui, app: &mut App) {
+    let Some(mut wizard) = app.settings_page.calibration_wizard.take() else {
+        return;
+    };
+    let Some(board) = app
+        .boards
+        .boards
+        .iter_mut()
+        .find(|b| b.address == wizard.board)
+    else {
+        return;
+    };
+    let Some(plant) = board
+        .state
+        .as_ref()
+        .and_then(|s| s.plants.iter().find(|p| p.id == wizard.plant))
+        .cloned()
+    else {
+        return;
+    };
+    let online = board.status == OnlineStatus::Online;
+    let offline = board.status == OnlineStatus::Offline;
+    let mut open = true;
+    let mut close = false;
+    egui::Window::new(format!("Calibrate {}", plant.name))
+        .open(&mut open)
+        .collapsible(false)
+        .show(ui.ctx(), |ui| {
+            let calibration = &plant.measured_moisture.calibration;
+            let reading = |value: f32| match plant.connection {
+                Connector::GPIO(_) => format!("{:.0} mV", value),
+                // digital sensors report raw counts
+                Connector::I2c { .. } => format!("{:.0}", value),
+            };
+            match &wizard.step {
+                CalibrationStep::Dry => {
+                    ui.label("1. Pull the probe out of the soil and wipe it clean and dry.");
+                    ui.label("2. Hold it in the air and wait about 10 seconds.");
+                    ui.label("3. Press \"Capture Dry\".");
+                    if ui
+                        .add_enabled(online, egui::Button::new("Capture Dry"))
+                        .clicked()
+                    {
+                        board.calibrate(
+                            app.board_sender.clone(),
+                            app.http_client.clone(),
+                            plant.id,
+                            CalibrationReference::Dry,
+                        );
+                    }
+                }
+                CalibrationStep::Wet => {
+                    ui.label(format!("Dry reading: {}", reading(calibration.dry_voltage)));
+                    ui.label(
+                        "1. Put the probe into a glass of water, up to the line on the probe.",
+                    );
+                    ui.label("2. Wait about 10 seconds.");
+                    ui.label("3. Press \"Capture Wet\".");
+                    ui.horizontal(|ui| {
+                        if ui.button("Back").clicked() {
+                            wizard.step = CalibrationStep::Dry;
+                        }
+                        if ui
+                            .add_enabled(online, egui::Button::new("Capture Wet"))
+                            .clicked()
+                        {
+                            board.calibrate(
+                                app.board_sender.clone(),
+                                app.http_client.clone(),
+                                plant.id,
+                                CalibrationReference::Wet,
+                            );
+                        }
+                    });
+                }
+                CalibrationStep::Done(check) => {
+                    ui.label(format!("Dry reading: {}", reading(calibration.dry_voltage)));
+                    ui.label(format!("Wet reading: {}", reading(calibration.wet_voltage)));
+                    match check {
+                        CalibrationCheck::Plausible => {
+                            ui.colored_label(egui::Color32::GREEN, "Calibration looks good.");
+                        }
+                        check => {
+                            ui.colored_label(egui::Color32::RED, check.to_string());
+                            ui.label("Please check the probe and try again.");
+                        }
+                    }
+                    ui.label("Put the probe back into the pot.");
+                    ui.horizontal(|ui| {
+                        if ui.button("Restart").clicked() {
+                            wizard.step = CalibrationStep::Dry;
+                        }
+                        if ui.button("Close").clicked() {
+                            close = true;
+                        }
+                    });
+                }
+            }
+            // the wizard only moves on once the board captured the reading
+            if offline {
+                ui.colored_label(egui::Color32::RED, "The board did not answer.");
+            }
+        });
+    if open && !close {
+        app.settings_page.calibration_wizard = Some(wizard);
+    }
+}
+
+fn plant_editor_window(ui: &mut Ui, app: &mut App) {
+    let Some(mut editor) = app.settings_page.plant_editor.take() else {
+        return;
+    };
+    let Some(board) = app
+        .boards
+        .boards
+        .iter_mut()
+        .find(|b| b.address == editor.board)
+    else {
+        return;
+    };
+    let care_profiles = board
+        .state
+        .as_ref()
+        .map(|s| s.care_profiles.clone())
+        .unwrap_or_default();
+    let connectors = board.connectors.clone();
+    let online = board.status == OnlineStatus::Online;
+    let mut open = true;
+    let mut close = false;
+    egui::Window::new("Edit plant")
+        .id(egui::Id::new((&editor.board, editor.plant, "edit")))
+        .open(&mut open)
+        .collapsible(false)
+        .show(ui.ctx(), |ui| {
+            let settings = &mut editor.settings;
+            egui::Grid::new("plant_editor").show(ui, |ui| {
+                ui.label("Name:");
+                ui.add(name_edit(&mut settings.name));
+                ui.end_row();
+                ui.label("Port:");
+                ui.horizontal(|ui| {
+                    connector_editor(
+                        ui,
+                        "plant_editor_connector",
+                        &mut settings.connection,
+                        &connectors,
+                        Some(editor.plant),
+                    );
+                });
+                ui.end_row();
+                ui.label("Soil:");
+                soil_combo_box(ui, "plant_editor_soil", &mut settings.soil);
+                ui.end_row();
+                ui.label("Pot:");
+                let mut volume = settings.pot_volume.unwrap_or(0.0);
+                if ui.add(pot_volume_drag_value(&mut volume)).changed() {
+                    settings.pot_volume = pot_volume(volume);
+                }
+                ui.end_row();
+                ui.label("Care profile:");
+                care_profile_combo_box(
+                    ui,
+                    "plant_editor_care",
+                    &care_profiles,
+                    &mut settings.care_profile,
+                );
+                ui.end_row();
+            });
+            ui.separator();
+            actuators_editor(ui, &mut settings.actuators);
+            controller_editor(ui, &mut settings.controller, settings.actuators.len());
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(online, egui::Button::new("Save")).clicked() {
+                    board.replace_plant(
+                        app.board_sender.clone(),
+                        app.http_client.clone(),
+                        editor.plant,
+                        settings.clone(),
+                    );
+                    close = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+            });
+        });
+    if open && !close {
+        app.settings_page.plant_editor = Some(editor);
+    }
+}
+
+fn board_editor_window(ui: &mut Ui, app: &mut App) {
+    let Some(mut editor) = app.settings_page.board_editor.take() else {
+        return;
+    };
+    let Some(board) = app
+        .boards
+        .boards
+        .iter_mut()
+        .find(|b| b.address == editor.board)
+    else {
+        return;
+    };
+    let online = board.status == OnlineStatus::Online;
+    let mut open = true;
+    let mut close = false;
+    egui::Window::new("Edit board")
+        .id(egui::Id::new((&editor.board, "edit")))
+        .open(&mut open)
+        .collapsible(false)
+        .show(ui.ctx(), |ui| {
+            egui::Grid::new("board_editor").show(ui, |ui| {
+                ui.label("Name:");
+                ui.add(name_edit(&mut editor.name));
+                ui.end_row();
+                if let Some(server) = &mut editor.sntp_server {
+                    ui.label("Time server:");
+                    ui.add(egui::TextEdit::singleline(server).char_limit(MAX_HOST_LENGTH));
+                    ui.end_row();
+                }
+                let metadata = &mut editor.metadata;
+                ui.label("Location:");
+                ui.add(name_edit(&mut metadata.location).hint_text("Kitchen"));
+                ui.end_row();
+                ui.label("Description:");
+                ui.add(
+                    egui::TextEdit::multiline(&mut metadata.description)
+                        .char_limit(MAX_DESCRIPTION_LENGTH)
+                        .desired_rows(2),
+                );
+                ui.end_row();
+                ui.label("Colour:");
+                ui.horizontal(|ui| {
+                    let mut colored = metadata.color.is_some();
+                    ui.checkbox(&mut colored, "");
+                    match (colored, &mut metadata.color) {
+                        (true, Some(color)) => {
+                            ui.color_edit_button_srgb(color);
+                        }
+                        (true, color) => *color = Some([0x4c, 0xaf, 0x50]),
+                        (false, color) => *color = None,
+                    }
+                });
+                ui.end_row();
+                ui.label("Icon:");
+                let mut icon = metadata.icon.clone().unwrap_or_default();
+                if ui
+                    .add(
+                        egui::TextEdit::singleline(&mut icon)
+                            .char_limit(MAX_ICON_LENGTH)
+                            .desired_width(40.0),
+                    )
+                    .on_hover_text("An emoji or symbol, leave it empty for none")
+                    .changed()
+                {
+                    metadata.icon = (!icon.trim().is_empty()).then(|| icon.trim().to_string());
+                }
+                ui.end_row();
+            });
+            ui.horizontal(|ui| {
+                if ui.add_enabled(online, egui::Button::new("Save")).clicked() {
+                    let metadata = editor.metadata.clone();
+                    board.update_board(
+                        app.board_sender.clone(),
+                        app.http_client.clone(),
+                        BoardUpdate {
+                            name: Some(editor.name.clone()),
+                            sntp_server: editor.sntp_server.clone(),
+                            location: Some(metadata.location),
+                            description: Some(metadata.description),
+                            color: Some(metadata.color),
+                            icon: Some(metadata.icon),
+                        },
+                    );
+                    close = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+            });
+        });
+    if open && !close {
+        app.settings_page.board_editor = Some(editor);
+    }
+}
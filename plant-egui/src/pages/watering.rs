@@ -0,0 +1,426 @@
+use std::collections::HashSet;
+
+use crate::{
+    address::BoardAddress,
+    app::App,
+    board::{Board, OnlineStatus},
+    pages::home::{describe_event, format_duration},
+};
+use egui::Ui;
+use plant_common::{
+    ControllerInfo, ControllerStatus, ManualWatering, PlantEvent, PlantEventKind, PlantInfo,
+    Timestamp,
+};
+
+/// A plant watered this long ago counts as done.
+const DONE_FOR: u64 = 12 * 60 * 60;
+/// Plants predicted to need water within this time are on the list.
+const SOON: u64 = 2 * 24 * 60 * 60;
+
+#[derive(Default)]
+pub struct WateringPage {
+    /// Boards whose plants are left out of the to-do list.
+    pub hidden_boards: HashSet<BoardAddress>,
+    /// Only the boards at this location are listed, all if `None`.
+    pub location: Option<String>,
+    /// Also list the plants that don't need water.
+    pub show_all: bool,
+}
+
+impl WateringPage {
+    fn is_shown(&self, board: &Board) -> bool {
+        let location = board.state.as_ref().map(|s| &s.metadata.location);
+        !self.hidden_boards.contains(&board.address)
+            && self.location.as_ref().map_or(true, |l| Some(l) == location)
+    }
+}
+
+/// How urgently a plant needs water, in the order of the to-do list.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Urgency {
+    /// Below the watering threshold by `below` percentage points.
+    Now {
+        below: f32,
+    },
+    Soon {
+        seconds: u64,
+    },
+    /// Nothing measured, someone has to look.
+    Unknown,
+    Later,
+    Automatic,
+    Done {
+        seconds_ago: u64,
+    },
+}
+
+impl Urgency {
+    fn of(plant: &PlantInfo, events: &[PlantEvent], now: Option<Timestamp>) -> Urgency {
+        if let Some(seconds_ago) = last_watering(plant.id, events, now) {
+            if seconds_ago < DONE_FOR {
+                return Urgency::Done { seconds_ago };
+            }
+        }
+        let automatic = plant.controller.as_ref().is_some_and(|c| {
+            !c.settings.dry_run && !matches!(c.status, ControllerStatus::LockedOut(_))
+        });
+        if automatic {
+            return Urgency::Automatic;
+        }
+        let moisture = &plant.measured_moisture;
+        let Some(percent) = moisture.percent() else {
+            return Urgency::Unknown;
+        };
+        let below = moisture.needs_water_below() - percent;
+        if below > 0.0 {
+            return Urgency::Now { below };
+        }
+        let due = plant
+            .drying
+            .as_ref()
+            .zip(now.filter(|t| t.synced))
+            .and_then(|(drying, now)| drying.needs_water_in(now.seconds));
+        match due {
+            Some(seconds) if seconds <= SOON => Urgency::Soon { seconds },
+            _ => Urgency::Later,
+        }
+    }
+
+    /// Whether the plant belongs on the checklist.
+    fn is_due(&self) -> bool {
+        matches!(
+            self,
+            Urgency::Now { .. } | Urgency::Soon { .. } | Urgency::Unknown
+        )
+    }
+
+    /// Sorts the most urgent first, the driest and the soonest first within a group.
+    fn sort_key(&self) -> (u8, f32) {
+        match *self {
+            Urgency::Now { below } => (0, -below),
+            Urgency::Soon { seconds } => (1, seconds as f32),
+            Urgency::Unknown => (2, 0.0),
+            Urgency::Later => (3, 0.0),
+            Urgency::Automatic => (4, 0.0),
+            Urgency::Done { seconds_ago } => (5, seconds_ago as f32),
+        }
+    }
+}
+
+impl std::fmt::Display for Urgency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Urgency::Now { .. } => write!(f, "needs water now"),
+            Urgency::Soon { seconds } => write!(f, "water in ~{}", format_duration(*seconds)),
+            Urgency::Unknown => write!(f, "no reading, check by hand"),
+            Urgency::Later => write!(f, "fine"),
+            Urgency::Automatic => write!(f, "watered automatically"),
+            Urgency::Done { seconds_ago } => {
+                write!(f, "watered {} ago", format_duration(*seconds_ago))
+            }
+        }
+    }
+}
+
+/// Seconds since the plant was last watered, by hand, by its pump or as seen by the sensor.
+fn last_watering(plant: u16, events: &[PlantEvent], now: Option<Timestamp>) -> Option<u64> {
+    let now = now?;
+    events
+        .iter()
+        .filter(|e| e.plant == plant)
+        .filter(|e| match e.kind {
+            PlantEventKind::Watered { .. } | PlantEventKind::WateredByHand { .. } => true,
+            PlantEventKind::Pumped { dry_run, .. } => !dry_run,
+            _ => false,
+        })
+        .filter_map(|e| e.time.age_at(&now))
+        .min()
+}
+
+/// A plant on the to-do list.
+struct Task {
+    board: BoardAddress,
+    board_name: String,
+    plant: u16,
+    name: String,
+    urgency: Urgency,
+    moisture: Option<f32>,
+    water_ml: Option<f32>,
+}
+
+impl Task {
+    fn details(&self) -> String {
+        let mut text = self.urgency.to_string();
+        if let Some(percent) = self.moisture {
+            text += &format!(", {:.0} %", percent);
+        }
+        if let Some(water) = self.water_ml.filter(|_| self.urgency.is_due()) {
+            if water >= 10.0 {
+                text += &format!(", add ~{:.0} ml", (water / 10.0).round() * 10.0);
+            }
+        }
+        text
+    }
+}
+
+fn board_name(board: &Board) -> String {
+    match &board.state {
+        // boards often share a name, the location tells them apart
+        Some(state) if !state.metadata.location.is_empty() => {
+            format!("{} in {}", state.name, state.metadata.location)
+        }
+        Some(state) => state.name.clone(),
+        None => board.address.to_string(),
+    }
+}
+
+fn tasks(boards: &[Board], page: &WateringPage) -> Vec<Task> {
+    let mut tasks = vec![];
+    for board in boards.iter().filter(|b| page.is_shown(b)) {
+        let Some(state) = &board.state else {
+            continue;
+        };
+        for plant in &state.plants {
+            tasks.push(Task {
+                board: board.address.clone(),
+                board_name: board_name(board),
+                plant: plant.id,
+                name: plant.name.clone(),
+                urgency: Urgency::of(plant, &board.events, state.time),
+                moisture: plant.measured_moisture.percent(),
+                water_ml: plant.measured_moisture.recommended_water_ml(),
+            });
+        }
+    }
+    tasks.sort_by(|a, b| {
+        let (a_rank, a_order) = a.urgency.sort_key();
+        let (b_rank, b_order) = b.urgency.sort_key();
+        a_rank.cmp(&b_rank).then(a_order.total_cmp(&b_order))
+    });
+    tasks
+}
+
+/// A plain text checklist of the plants that need water, to print or send to someone.
+fn checklist(tasks: &[Task]) -> String {
+    let mut text = "Plants to water\n\n".to_string();
+    let due: Vec<_> = tasks.iter().filter(|t| t.urgency.is_due()).collect();
+    if due.is_empty() {
+        text += "Nothing to do.\n";
+    }
+    for task in due {
+        text += &format!(
+            "[ ] {} ({}): {}\n",
+            task.name,
+            task.board_name,
+            task.details()
+        );
+    }
+    text
+}
+
+pub fn watering_page(ui: &mut Ui, app: &mut App) {
+    let mut locations: Vec<&String> = app
+        .boards
+        .boards
+        .iter()
+        .filter_map(|b| b.state.as_ref())
+        .map(|s| &s.metadata.location)
+        .filter(|l| !l.is_empty())
+        .collect();
+    locations.sort();
+    locations.dedup();
+    if !locations.is_empty() {
+        let page = &mut app.watering_page;
+        ui.horizontal(|ui| {
+            ui.label("Location:");
+            egui::ComboBox::from_id_source("watering_location")
+                .selected_text(page.location.as_deref().unwrap_or("All"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut page.location, None, "All");
+                    for location in locations {
+                        ui.selectable_value(
+                            &mut page.location,
+                            Some(location.clone()),
+                            location.as_str(),
+                        );
+                    }
+                });
+        });
+    }
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Boards:");
+        for board in &app.boards.boards {
+            let mut shown = !app.watering_page.hidden_boards.contains(&board.address);
+            if ui.checkbox(&mut shown, board_name(board)).changed() {
+                if shown {
+                    app.watering_page.hidden_boards.remove(&board.address);
+                } else {
+                    app.watering_page
+                        .hidden_boards
+                        .insert(board.address.clone());
+                }
+            }
+        }
+    });
+    let tasks = tasks(&app.boards.boards, &app.watering_page);
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut app.watering_page.show_all, "Show all plants");
+        if ui
+            .button("Copy checklist")
+            .on_hover_text("Copies the plants that need water as text to print or send.")
+            .clicked()
+        {
+            let text = checklist(&tasks);
+            ui.output_mut(|o| o.copied_text = text);
+        }
+    });
+
+    ui.heading("To do");
+    let mut watered = None;
+    let shown: Vec<_> = tasks
+        .iter()
+        .filter(|t| app.watering_page.show_all || t.urgency.is_due())
+        .collect();
+    if shown.is_empty() {
+        ui.weak("No plant needs water.");
+    }
+    egui::Grid::new("watering_tasks")
+        .striped(true)
+        .show(ui, |ui| {
+            for task in shown {
+                let text = egui::RichText::new(&task.name).strong();
+                match task.urgency {
+                    Urgency::Now { .. } => ui.label(text.color(egui::Color32::RED)),
+                    _ => ui.label(text),
+                };
+                ui.label(&task.board_name);
+                ui.label(task.details());
+                let online = app
+                    .boards
+                    .boards
+                    .iter()
+                    .any(|b| b.address == task.board && b.status == OnlineStatus::Online);
+                if ui
+                    .add_enabled(online, egui::Button::new("I watered this"))
+                    .on_hover_text("Records the watering on the board.")
+                    .clicked()
+                {
+                    watered = Some((&task.board, task.plant));
+                }
+                ui.end_row();
+            }
+        });
+    if let Some((address, id)) = watered {
+        if let Some(board) = app.boards.boards.iter_mut().find(|b| &b.address == address) {
+            board.record_manual_watering(
+                app.board_sender.clone(),
+                app.http_client.clone(),
+                id,
+                ManualWatering::default(),
+            );
+        }
+    }
+
+    ui.separator();
+    controllers(ui, app);
+
+    ui.separator();
+    ui.heading("Events");
+    let mut events = vec![];
+    for board in &app.boards.boards {
+        if !app.watering_page.is_shown(board) {
+            continue;
+        }
+        let Some(state) = &board.state else {
+            continue;
+        };
+        for event in &board.events {
+            let plant = state
+                .plants
+                .iter()
+                .find(|p| p.id == event.plant)
+                .map_or_else(|| format!("Plant {}", event.plant), |p| p.name.clone());
+            let age = state.time.and_then(|now| event.time.age_at(&now));
+            events.push((age, plant, describe_event(event, state.time)));
+        }
+    }
+    // events without a comparable time go last
+    events.sort_by_key(|(age, ..)| age.unwrap_or(u64::MAX));
+    if events.is_empty() {
+        ui.weak("Nothing recorded yet.");
+    }
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for (_, plant, text) in events {
+            ui.label(format!("{}: {}", plant, text));
+        }
+    });
+}
+
+/// The state of the automatic watering of every plant that has it.
+fn controllers(ui: &mut Ui, app: &mut App) {
+    ui.heading("Automatic watering");
+    let mut any_controller = false;
+    for board in &mut app.boards.boards {
+        if !app.watering_page.is_shown(board) {
+            continue;
+        }
+        let Some(state) = &board.state else {
+            continue;
+        };
+        let online = board.status == OnlineStatus::Online;
+        let mut reset = None;
+        for plant in &state.plants {
+            let Some(controller) = &plant.controller else {
+                continue;
+            };
+            any_controller = true;
+            ui.horizontal(|ui| {
+                ui.strong(format!("{} ({}):", plant.name, state.name));
+                controller_status(ui, controller);
+                if matches!(controller.status, ControllerStatus::LockedOut(_))
+                    && ui
+                        .add_enabled(online, egui::Button::new("Reset"))
+                        .on_hover_text("Fix the cause first, then let it water again.")
+                        .clicked()
+                {
+                    reset = Some(plant.id);
+                }
+            });
+        }
+        if let Some(id) = reset {
+            board.reset_controller(app.board_sender.clone(), app.http_client.clone(), id);
+        }
+    }
+    if !any_controller {
+        ui.weak("No plant is watered automatically, enable it in the plant settings.");
+    }
+}
+
+fn controller_status(ui: &mut Ui, controller: &ControllerInfo) {
+    match &controller.status {
+        ControllerStatus::Idle => {
+            ui.label("Moist enough");
+        }
+        ControllerStatus::Dry { seconds } => {
+            ui.label(format!("Dry for {}", format_duration(*seconds)));
+        }
+        ControllerStatus::Soaking { seconds_left } => {
+            ui.label(format!(
+                "Soaking in, measuring again in {}",
+                format_duration(*seconds_left)
+            ));
+        }
+        ControllerStatus::LockedOut(reason) => {
+            ui.colored_label(egui::Color32::RED, format!("Stopped: {}", reason));
+        }
+    }
+    let settings = &controller.settings;
+    ui.label(format!(
+        "{:.0} of {:.0} ml today",
+        controller.given_today_ml, settings.daily_budget_ml
+    ));
+    if settings.dry_run {
+        ui.weak("Dry run")
+            .on_hover_text("Only records what it would do, the pump is never started.");
+    }
+}
@@ -0,0 +1,53 @@
+//! Resolves the host names of boards for the requests sent to them.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Resolves DNS names with the servers of the system configuration.
+///
+/// mDNS names ending in `.local` are left to the system, which answers them if an mDNS
+/// responder like Avahi or Bonjour runs, and so are names the DNS servers don't know,
+/// like the ones in the hosts file.
+#[derive(Debug, Default)]
+pub struct BoardResolver {
+    /// Created on the first lookup, it has to be created inside the tokio runtime.
+    dns: Arc<OnceLock<Option<TokioAsyncResolver>>>,
+}
+
+impl Resolve for BoardResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let dns = Arc::clone(&self.dns);
+        let name = name.as_str().to_string();
+        Box::pin(async move {
+            let is_mdns = name.trim_end_matches('.').ends_with(".local");
+            if !is_mdns {
+                let dns = dns.get_or_init(|| match TokioAsyncResolver::tokio_from_system_conf() {
+                    Ok(resolver) => Some(resolver),
+                    Err(e) => {
+                        log::warn!("Cannot read the DNS configuration: {}", e);
+                        None
+                    }
+                });
+                if let Some(dns) = dns {
+                    match dns.lookup_ip(name.as_str()).await {
+                        Ok(lookup) => {
+                            let ips: Vec<IpAddr> = lookup.iter().collect();
+                            // the port is replaced with the one of the URL
+                            let addrs = ips.into_iter().map(|ip| SocketAddr::new(ip, 0));
+                            return Ok(Box::new(addrs) as Addrs);
+                        }
+                        Err(e) => log::debug!("Cannot resolve {} by DNS: {}", name, e),
+                    }
+                }
+            }
+            let addrs: Vec<SocketAddr> =
+                tokio_with_wasm::tokio::net::lookup_host((name.as_str(), 0))
+                    .await?
+                    .collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use esp_idf_svc::sntp::{EspSntp, SntpConf, SyncStatus};
+use log::info;
+use plant_firmware::hal::Clock;
+
+/// Wall-clock time from an SNTP server, the uptime counts from the creation of the clock.
+pub struct SntpClock {
+    started: Instant,
+    sntp: Mutex<Option<EspSntp<'static>>>,
+    /// The time stays valid once it was synchronized, even while the next synchronization runs.
+    synced: AtomicBool,
+}
+
+impl SntpClock {
+    pub fn new() -> SntpClock {
+        SntpClock {
+            started: Instant::now(),
+            sntp: Mutex::new(None),
+            synced: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Default for SntpClock {
+    fn default() -> Self {
+        SntpClock::new()
+    }
+}
+
+impl Clock for SntpClock {
+    fn unix_time(&self) -> Option<u64> {
+        if !self.synced.load(Ordering::Relaxed) {
+            let sntp = self.sntp.lock().unwrap();
+            let status = sntp.as_ref().map(|sntp| sntp.get_sync_status());
+            if !matches!(status, Some(SyncStatus::Completed)) {
+                return None;
+            }
+            info!("Synchronized the time");
+            self.synced.store(true, Ordering::Relaxed);
+        }
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+        Some(since_epoch.as_secs())
+    }
+
+    fn uptime(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    fn set_server(&self, server: &str) -> anyhow::Result<()> {
+        let mut sntp = self.sntp.lock().unwrap();
+        // only one SNTP client can run at a time
+        *sntp = None;
+        let mut conf = SntpConf::default();
+        conf.servers[0] = server;
+        *sntp = Some(EspSntp::new(&conf)?);
+        info!("Synchronizing the time with '{}'", server);
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use esp_idf_svc::hal::delay::TickType;
+use esp_idf_svc::hal::gpio::{Gpio21, Gpio22, Gpio25, Gpio26};
+use esp_idf_svc::hal::i2c::{I2cConfig, I2cDriver, I2C0, I2C1};
+use esp_idf_svc::hal::units::Hertz;
+use esp_idf_svc::sys::{EspError, TickType_t};
+use log::error;
+
+/// I2C address of the TCA9548A multiplexer.
+const MULTIPLEXER_ADDRESS: u8 = 0x70;
+const BAUDRATE: Hertz = Hertz(100_000);
+const TIMEOUT_MS: u64 = 50;
+
+// seesaw registers
+const STATUS_BASE: u8 = 0x00;
+const STATUS_TEMP: u8 = 0x04;
+const TOUCH_BASE: u8 = 0x0F;
+const TOUCH_CHANNEL_OFFSET: u8 = 0x10;
+
+pub struct I2cReading {
+    /// Raw capacitive reading, roughly 200 (air) to 2000 (water).
+    pub moisture: u16,
+    /// Temperature in °C.
+    pub temperature: f32,
+}
+
+/// The I2C buses of the board, bus 0 uses SDA 21/SCL 22, bus 1 uses SDA 25/SCL 26.
+pub struct I2cBuses {
+    buses: [Option<I2cDriver<'static>>; 2],
+}
+
+impl I2cBuses {
+    pub fn new(i2c0: I2C0, i2c1: I2C1) -> I2cBuses {
+        let config = I2cConfig::new().baudrate(BAUDRATE);
+        // SAFETY: `GpioWrapper` takes all of the pins and owns these too, but only drives the
+        // pins the board logic hands it. The firmware never accepts 21, 22, 25 or 26 as the pin
+        // of a sensor, an actuator or a flow sensor, `i2c_pins_are_reserved_for_the_buses` in
+        // `plant_firmware::pins` makes sure of that.
+        let bus0 = unsafe { I2cDriver::new(i2c0, Gpio21::new(), Gpio22::new(), &config) };
+        let bus1 = unsafe { I2cDriver::new(i2c1, Gpio25::new(), Gpio26::new(), &config) };
+        I2cBuses {
+            buses: [bus0, bus1].map(|bus| {
+                bus.map_err(|e| error!("Cannot initialize I2C bus: {:?}", e))
+                    .ok()
+            }),
+        }
+    }
+
+    fn bus(&mut self, bus: u8) -> Result<&mut I2cDriver<'static>, EspError> {
+        self.buses
+            .get_mut(bus as usize)
+            .and_then(|x| x.as_mut())
+            .ok_or(invalid_arg())
+    }
+
+    /// Reads moisture and temperature of a seesaw soil sensor.
+    pub async fn read_seesaw(
+        &mut self,
+        bus: u8,
+        address: u8,
+        channel: Option<u8>,
+    ) -> Result<I2cReading, EspError> {
+        let timeout = TickType::new_millis(TIMEOUT_MS).ticks();
+        if let Some(channel) = channel {
+            let mask = 1u8.checked_shl(channel as u32).ok_or(invalid_arg())?;
+            self.bus(bus)?
+                .write(MULTIPLEXER_ADDRESS, &[mask], timeout)?;
+        }
+        let reading = self.read_selected_seesaw(bus, address, timeout).await;
+        if channel.is_some() {
+            // disconnect the channel again, so sensors directly on the bus don't collide with it
+            self.bus(bus)?.write(MULTIPLEXER_ADDRESS, &[0], timeout)?;
+        }
+        reading
+    }
+
+    async fn read_selected_seesaw(
+        &mut self,
+        bus: u8,
+        address: u8,
+        timeout: TickType_t,
+    ) -> Result<I2cReading, EspError> {
+        let mut moisture = [0; 2];
+        self.bus(bus)?
+            .write(address, &[TOUCH_BASE, TOUCH_CHANNEL_OFFSET], timeout)?;
+        // the sensor needs some time to measure before the value can be read
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        self.bus(bus)?.read(address, &mut moisture, timeout)?;
+
+        let mut temperature = [0; 4];
+        self.bus(bus)?
+            .write(address, &[STATUS_BASE, STATUS_TEMP], timeout)?;
+        tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        self.bus(bus)?.read(address, &mut temperature, timeout)?;
+
+        Ok(I2cReading {
+            moisture: u16::from_be_bytes(moisture),
+            temperature: i32::from_be_bytes(temperature) as f32 / 65536.0,
+        })
+    }
+}
+
+fn invalid_arg() -> EspError {
+    EspError::from_infallible::<{ esp_idf_svc::sys::ESP_ERR_INVALID_ARG }>()
+}
@@ -1,21 +1,31 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
+use clock::SntpClock;
 use esp32_gpio_wrapper::GpioWrapper;
 use esp_idf_svc::eventloop::EspSystemEventLoop;
 use esp_idf_svc::hal::prelude::Peripherals;
 use esp_idf_svc::nvs::EspDefaultNvsPartition;
-use esp_idf_svc::sys::{esp, EspError};
+use esp_idf_svc::sys::esp;
 use esp_idf_svc::timer::EspTaskTimerService;
-use esp_idf_svc::wifi::{AsyncWifi, ClientConfiguration, Configuration, EspWifi};
-use log::info;
-use plant_db::PlantDB;
+use esp_idf_svc::wifi::{AsyncWifi, EspWifi};
+use i2c_sensor::I2cBuses;
+use log::{info, warn};
+use outputs::BoardOutputs;
+use plant_common::{WifiNetwork, DISCOVERY_PORT};
+use plant_firmware::plant_db::PlantDB;
+use plant_firmware::{actuator, discovery, plant, server};
+use sensors::BoardSensors;
+use storage::BoardStore;
+use tokio::net::UdpSocket;
 use tokio::sync::Mutex;
 
-mod plant;
-mod server;
-mod plant_db;
-
-include!(concat!(env!("CARGO_MANIFEST_DIR"), "/wifi.rs"));
+mod clock;
+mod i2c_sensor;
+mod outputs;
+mod sensors;
+mod storage;
+mod wifi;
 
 fn main() -> anyhow::Result<()> {
     esp_idf_svc::sys::link_patches();
@@ -40,69 +50,56 @@ fn main() -> anyhow::Result<()> {
         sysloop,
         timer.clone(),
     )?;
+    let mac = wifi.wifi().sta_netif().get_mac()?;
 
     info!("Starting async run loop");
     tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()?
         .block_on(async move {
-            let mut wifi_loop = WifiLoop { wifi };
-            wifi_loop.configure().await?;
-            wifi_loop.initial_connect().await?;
-
             let gpio = GpioWrapper::new(Some(peripherals.adc1), None, peripherals.pins);
-            let plants = Arc::new(Mutex::new(PlantDB::new(nvs)));
-            tokio::spawn(plant::measure_plants( gpio.clone(), plants.clone()));
-            tokio::spawn(server::auxum_serve(plants.clone()));
+            let i2c = I2cBuses::new(peripherals.i2c0, peripherals.i2c1);
+            let store = BoardStore::new(nvs).expect("Couldn't open the storage");
+            let clock = Arc::new(SntpClock::new());
+            let mut db = PlantDB::with_clock(store, clock);
+            // boards are told apart by their MAC address until they are renamed
+            db.set_default_name(&format!("Board {:02X}{:02X}", mac[4], mac[5]));
+            add_build_network(&mut db);
+            let plants = Arc::new(Mutex::new(db));
+            tokio::spawn(plant::measure_plants(
+                BoardSensors { gpio, i2c },
+                plants.clone(),
+            ));
+            let outputs = BoardOutputs::new().expect("Couldn't set up the actuators");
+            tokio::spawn(actuator::drive_actuators(outputs, plants.clone()));
+            let api = SocketAddr::from(([0, 0, 0, 0], 80));
+            tokio::spawn(server::auxum_serve(plants.clone(), api));
+            let discovery = UdpSocket::bind(("0.0.0.0", DISCOVERY_PORT))
+                .await
+                .expect("Couldn't open the discovery socket");
+            tokio::spawn(discovery::answer_probes(discovery, plants.clone(), api));
 
             info!("Entering main Wi-Fi run loop...");
-            wifi_loop.stay_connected().await
+            wifi::run(wifi, plants).await
         })?;
 
     Ok(())
 }
 
-pub struct WifiLoop<'a> {
-    wifi: AsyncWifi<EspWifi<'a>>,
-}
-
-impl<'a> WifiLoop<'a> {
-    pub async fn configure(&mut self) -> Result<(), EspError> {
-        info!("Setting Wi-Fi credentials...");
-        info!("Wifi SSID: {}", WIFI_SSID);
-        info!("Wifi PSK: {}", WIFI_PSK);
-
-        self.wifi
-            .set_configuration(&Configuration::Client(ClientConfiguration {
-                ssid: WIFI_SSID.parse().unwrap(),
-                password: WIFI_PSK.parse().unwrap(),
-                ..Default::default()
-            }))?;
-
-        info!("Starting Wi-Fi driver...");
-        self.wifi.start().await
-    }
-
-    pub async fn initial_connect(&mut self) -> Result<(), EspError> {
-        self.do_connect_loop(true).await
-    }
-
-    pub async fn stay_connected(mut self) -> Result<(), EspError> {
-        self.do_connect_loop(false).await
+/// Stores the network given by `WIFI_SSID` and `WIFI_PASSWORD` at build time if there is none,
+/// so boards can be set up without the access point.
+fn add_build_network(db: &mut PlantDB<BoardStore>) {
+    let Some(ssid) = option_env!("WIFI_SSID") else {
+        return;
+    };
+    if !db.get_wifi_status().networks.is_empty() {
+        return;
     }
-
-    async fn do_connect_loop(&mut self, exit_after_first_connect: bool) -> Result<(), EspError> {
-        let wifi = &mut self.wifi;
-        loop {
-            wifi.wifi_wait(|wifi| wifi.is_up(), None).await?;
-            info!("Connecting to Wi-Fi...");
-            wifi.connect().await?;
-            info!("Waiting for association...");
-            wifi.ip_wait_while(|wifi| wifi.is_up().map(|s| !s), None)
-                .await?;
-            if exit_after_first_connect {
-                return Ok(());
-            }
-        }
+    let network = WifiNetwork {
+        ssid: ssid.to_string(),
+        password: option_env!("WIFI_PASSWORD").unwrap_or_default().to_string(),
+    };
+    if let Err(e) = db.add_wifi_network(network) {
+        warn!("Cannot store the Wi-Fi network '{}': {}", ssid, e);
     }
 }
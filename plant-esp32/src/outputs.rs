@@ -0,0 +1,70 @@
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use esp_idf_svc::sys::{self, esp, EspError};
+use plant_firmware::hal::OutputPins;
+use plant_firmware::pins::GPIO_COUNT;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_PULSES: AtomicU32 = AtomicU32::new(0);
+/// Pulses counted by the interrupt handler of every pin.
+static PULSES: [AtomicU32; GPIO_COUNT as usize] = [NO_PULSES; GPIO_COUNT as usize];
+
+/// The relays of the actuators and the flow sensors, driven through ESP-IDF directly.
+///
+/// The pins are owned by the `GpioWrapper` of the sensors, the board logic makes sure that the
+/// pins of actuators and flow sensors are never used by a sensor.
+pub struct BoardOutputs {
+    /// Bit masks of the pins configured as outputs and as pulse counters.
+    outputs: u64,
+    counters: u64,
+}
+
+impl BoardOutputs {
+    pub fn new() -> Result<BoardOutputs, EspError> {
+        esp!(unsafe { sys::gpio_install_isr_service(0) })?;
+        Ok(BoardOutputs {
+            outputs: 0,
+            counters: 0,
+        })
+    }
+}
+
+unsafe extern "C" fn count_pulse(pin: *mut c_void) {
+    PULSES[pin as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+impl OutputPins for BoardOutputs {
+    fn set_level(&mut self, pin: u8, high: bool) -> anyhow::Result<()> {
+        anyhow::ensure!(pin < GPIO_COUNT, "GPIO {} doesn't exist", pin);
+        let gpio = pin as i32;
+        if self.outputs & (1 << pin) == 0 {
+            esp!(unsafe { sys::gpio_reset_pin(gpio) })?;
+            esp!(unsafe { sys::gpio_set_direction(gpio, sys::gpio_mode_t_GPIO_MODE_OUTPUT) })?;
+            self.outputs |= 1 << pin;
+        }
+        esp!(unsafe { sys::gpio_set_level(gpio, high as u32) })?;
+        Ok(())
+    }
+
+    fn pulses(&mut self, pin: u8) -> anyhow::Result<u32> {
+        anyhow::ensure!(pin < GPIO_COUNT, "GPIO {} doesn't exist", pin);
+        let gpio = pin as i32;
+        if self.counters & (1 << pin) == 0 {
+            esp!(unsafe { sys::gpio_reset_pin(gpio) })?;
+            esp!(unsafe { sys::gpio_set_direction(gpio, sys::gpio_mode_t_GPIO_MODE_INPUT) })?;
+            // GPIO 34 to 39 have no pull-ups, sensors there need an external one
+            if pin < 34 {
+                esp!(unsafe {
+                    sys::gpio_set_pull_mode(gpio, sys::gpio_pull_mode_t_GPIO_PULLUP_ONLY)
+                })?;
+            }
+            esp!(unsafe { sys::gpio_set_intr_type(gpio, sys::gpio_int_type_t_GPIO_INTR_POSEDGE) })?;
+            esp!(unsafe {
+                sys::gpio_isr_handler_add(gpio, Some(count_pulse), pin as *mut c_void)
+            })?;
+            self.counters |= 1 << pin;
+        }
+        Ok(PULSES[pin as usize].load(Ordering::Relaxed))
+    }
+}
@@ -0,0 +1,42 @@
+use esp32_gpio_wrapper::{Attenuation, GpioWrapper, MeasurementConfig};
+use plant_common::Connector;
+use plant_firmware::hal::{SensorReading, SensorSource};
+
+use crate::i2c_sensor::I2cBuses;
+
+const MEASUREMENT_CONFIG: MeasurementConfig = MeasurementConfig {
+    to_measure: 32,
+    attenuation: Attenuation::DB11,
+};
+
+/// The ADC pins and I2C buses the soil sensors are connected to.
+pub struct BoardSensors {
+    pub gpio: GpioWrapper,
+    pub i2c: I2cBuses,
+}
+
+impl SensorSource for BoardSensors {
+    async fn read(&mut self, connector: &Connector) -> anyhow::Result<SensorReading> {
+        match *connector {
+            Connector::GPIO(pin) => Ok(SensorReading {
+                value: self
+                    .gpio
+                    .get_pin(pin as usize)?
+                    .get_adc_averaged(MEASUREMENT_CONFIG)
+                    .await?,
+                temperature: None,
+            }),
+            Connector::I2c {
+                bus,
+                address,
+                channel,
+            } => {
+                let reading = self.i2c.read_seesaw(bus, address, channel).await?;
+                Ok(SensorReading {
+                    value: reading.moisture as f32,
+                    temperature: Some(reading.temperature),
+                })
+            }
+        }
+    }
+}
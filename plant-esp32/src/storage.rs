@@ -0,0 +1,104 @@
+use esp_idf_svc::nvs::{EspNvs, EspNvsPartition, NvsCustom, NvsDefault, NvsPartitionId};
+use esp_idf_svc::sys::{EspError, ESP_ERR_NVS_NOT_ENOUGH_SPACE};
+use plant_firmware::hal::{KeyValueStore, StorageError};
+use plant_firmware::history;
+
+const NAMESPACE: &str = "plant_ns";
+/// NVS partition of the measurement histories, see `partitions.csv`.
+const HISTORY_PARTITION: &str = "history";
+
+/// The namespace of the plant database in an NVS partition.
+pub struct NvsStore<T: NvsPartitionId>(EspNvs<T>);
+
+impl<T: NvsPartitionId> NvsStore<T> {
+    pub fn new(partition: EspNvsPartition<T>) -> Result<NvsStore<T>, EspError> {
+        Ok(NvsStore(EspNvs::new(partition, NAMESPACE, true)?))
+    }
+}
+
+/// Keeps the settings in the default NVS partition and the histories in their own partition,
+/// so they can't fill up the space needed for plants and Wi-Fi.
+pub struct BoardStore {
+    settings: NvsStore<NvsDefault>,
+    history: NvsStore<NvsCustom>,
+}
+
+impl BoardStore {
+    pub fn new(settings: EspNvsPartition<NvsDefault>) -> Result<BoardStore, EspError> {
+        Ok(BoardStore {
+            settings: NvsStore::new(settings)?,
+            history: NvsStore::new(EspNvsPartition::<NvsCustom>::take(HISTORY_PARTITION)?)?,
+        })
+    }
+
+    fn partition(&self, key: &str) -> &dyn KeyValueStore {
+        if key.starts_with(history::KEY_PREFIX) {
+            &self.history
+        } else {
+            &self.settings
+        }
+    }
+
+    fn partition_mut(&mut self, key: &str) -> &mut dyn KeyValueStore {
+        if key.starts_with(history::KEY_PREFIX) {
+            &mut self.history
+        } else {
+            &mut self.settings
+        }
+    }
+}
+
+impl KeyValueStore for BoardStore {
+    fn get_u16(&self, key: &str) -> Result<Option<u16>, StorageError> {
+        self.partition(key).get_u16(key)
+    }
+
+    fn set_u16(&mut self, key: &str, value: u16) -> Result<(), StorageError> {
+        self.partition_mut(key).set_u16(key, value)
+    }
+
+    fn get_raw<'a>(&self, key: &str, buf: &'a mut [u8]) -> Result<Option<&'a [u8]>, StorageError> {
+        self.partition(key).get_raw(key, buf)
+    }
+
+    fn set_raw(&mut self, key: &str, value: &[u8]) -> Result<(), StorageError> {
+        self.partition_mut(key).set_raw(key, value)
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), StorageError> {
+        self.partition_mut(key).remove(key)
+    }
+}
+
+fn storage_error(e: EspError) -> StorageError {
+    if e.code() == ESP_ERR_NVS_NOT_ENOUGH_SPACE {
+        StorageError::Full
+    } else {
+        StorageError::Failed(e.to_string())
+    }
+}
+
+impl<T: NvsPartitionId> KeyValueStore for NvsStore<T> {
+    fn get_u16(&self, key: &str) -> Result<Option<u16>, StorageError> {
+        self.0.get_u16(key).map_err(storage_error)
+    }
+
+    fn set_u16(&mut self, key: &str, value: u16) -> Result<(), StorageError> {
+        self.0.set_u16(key, value).map_err(storage_error)
+    }
+
+    fn get_raw<'a>(&self, key: &str, buf: &'a mut [u8]) -> Result<Option<&'a [u8]>, StorageError> {
+        self.0.get_raw(key, buf).map_err(storage_error)
+    }
+
+    fn set_raw(&mut self, key: &str, value: &[u8]) -> Result<(), StorageError> {
+        self.0
+            .set_raw(key, value)
+            .map(|_| ())
+            .map_err(storage_error)
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), StorageError> {
+        self.0.remove(key).map(|_| ()).map_err(storage_error)
+    }
+}
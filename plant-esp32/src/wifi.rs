@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use esp_idf_svc::sys::EspError;
+use esp_idf_svc::wifi::{
+    AccessPointConfiguration, AsyncWifi, AuthMethod, ClientConfiguration, Configuration, EspWifi,
+};
+use log::{info, warn};
+use plant_common::WifiNetwork;
+use plant_firmware::hal::KeyValueStore;
+use plant_firmware::plant_db::PlantDB;
+use plant_firmware::wifi::{WifiAction, WifiEvent};
+use tokio::sync::Mutex;
+use tokio::time::{sleep, timeout};
+
+/// How long joining a network may take.
+const JOIN_ATTEMPT: Duration = Duration::from_secs(15);
+/// How often the connection is checked while there is nothing to do.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Joins the networks stored in the database and opens the access point when it can't.
+pub async fn run<K: KeyValueStore>(
+    mut wifi: AsyncWifi<EspWifi<'_>>,
+    plants: Arc<Mutex<PlantDB<K>>>,
+) -> Result<(), EspError> {
+    let mut client = ClientConfiguration::default();
+    let mut access_point: Option<AccessPointConfiguration> = None;
+    let mut connected = false;
+    wifi.set_configuration(&Configuration::Client(client.clone()))?;
+    info!("Starting Wi-Fi driver...");
+    wifi.start().await?;
+    plants.lock().await.start_wifi();
+    loop {
+        let action = plants.lock().await.next_wifi_action();
+        let event = match action {
+            WifiAction::Join(network) => {
+                info!("Joining '{}'...", network.ssid);
+                client = client_configuration(&network);
+                wifi.set_configuration(&configuration(&client, &access_point))?;
+                let joined = timeout(JOIN_ATTEMPT, async {
+                    wifi.connect().await?;
+                    wifi.wait_netif_up().await
+                })
+                .await;
+                match joined {
+                    Ok(Ok(())) => {
+                        info!("Joined '{}'.", network.ssid);
+                        connected = true;
+                        WifiEvent::Joined(network.ssid)
+                    }
+                    Ok(Err(e)) => {
+                        warn!("Cannot join '{}': {}", network.ssid, e);
+                        let _ = wifi.disconnect().await;
+                        WifiEvent::JoinFailed
+                    }
+                    Err(_) => {
+                        warn!("Joining '{}' timed out.", network.ssid);
+                        let _ = wifi.disconnect().await;
+                        WifiEvent::JoinFailed
+                    }
+                }
+            }
+            WifiAction::OpenAccessPoint(ssid) => {
+                info!("Opening the access point '{}' for provisioning...", ssid);
+                // open, so the board only serves the Wi-Fi routes meanwhile, see `server::router`
+                let ap = AccessPointConfiguration {
+                    ssid: ssid.parse().unwrap_or_default(),
+                    auth_method: AuthMethod::None,
+                    ..Default::default()
+                };
+                access_point = Some(ap);
+                wifi.set_configuration(&configuration(&client, &access_point))?;
+                WifiEvent::AccessPointOpened(ssid)
+            }
+            WifiAction::CloseAccessPoint => {
+                info!("Closing the access point...");
+                access_point = None;
+                wifi.set_configuration(&configuration(&client, &access_point))?;
+                WifiEvent::AccessPointClosed
+            }
+            WifiAction::Wait => {
+                sleep(CHECK_INTERVAL).await;
+                if connected && !wifi.is_connected()? {
+                    warn!("Lost the Wi-Fi connection.");
+                    connected = false;
+                    WifiEvent::Disconnected
+                } else {
+                    continue;
+                }
+            }
+        };
+        plants.lock().await.handle_wifi_event(event);
+    }
+}
+
+fn client_configuration(network: &WifiNetwork) -> ClientConfiguration {
+    // the lengths were checked when the network was stored
+    ClientConfiguration {
+        ssid: network.ssid.parse().unwrap_or_default(),
+        password: network.password.parse().unwrap_or_default(),
+        auth_method: if network.password.is_empty() {
+            AuthMethod::None
+        } else {
+            AuthMethod::WPA2Personal
+        },
+        ..Default::default()
+    }
+}
+
+/// Keeps the access point running next to the station while it is open.
+fn configuration(
+    client: &ClientConfiguration,
+    access_point: &Option<AccessPointConfiguration>,
+) -> Configuration {
+    match access_point {
+        Some(ap) => Configuration::Mixed(client.clone(), ap.clone()),
+        None => Configuration::Client(client.clone()),
+    }
+}
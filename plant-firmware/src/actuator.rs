@@ -0,0 +1,414 @@
+//! Runs the pumps and valves of the plants within the safety limits of the board.
+//!
+//! The limits are kept per pin, so changing the settings of a plant doesn't reset them.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{error, info};
+use plant_common::{Actuator, ErrStatus, MAX_RUNS_PER_DAY, MIN_RUN_INTERVAL};
+use tokio::sync::Mutex;
+
+use crate::hal::{KeyValueStore, OutputPins};
+use crate::plant_db::PlantDB;
+
+/// Time between two checks of the running actuators.
+const TICK: Duration = Duration::from_millis(100);
+const DAY: u64 = 24 * 60 * 60;
+
+/// Starts of the recent runs of one pin, in seconds since the start of the board.
+#[derive(Clone, Debug, Default)]
+struct RunLimiter {
+    starts: VecDeque<u64>,
+}
+
+impl RunLimiter {
+    /// Seconds until the pin may run again, with the error to report until then.
+    fn blocked_for(&self, now: u64) -> Option<ErrStatus> {
+        // the board doesn't know when the pin last ran before a restart, which a pump drawing
+        // too much current might even have caused, so the start counts as a run
+        let last = self.starts.back().copied().unwrap_or(0);
+        let seconds = (last + MIN_RUN_INTERVAL).saturating_sub(now);
+        if seconds > 0 {
+            return Some(ErrStatus::ActuatorCoolingDown { seconds });
+        }
+        let day_ago = now.saturating_sub(DAY);
+        let recent: Vec<_> = self.starts.iter().filter(|s| **s > day_ago).collect();
+        if recent.len() >= MAX_RUNS_PER_DAY {
+            let seconds = (recent[0] + DAY).saturating_sub(now);
+            return Some(ErrStatus::DailyRunLimit { seconds });
+        }
+        None
+    }
+
+    fn record(&mut self, now: u64) {
+        self.starts.push_back(now);
+        if self.starts.len() > MAX_RUNS_PER_DAY {
+            self.starts.pop_front();
+        }
+    }
+}
+
+/// Pulses of a flow sensor after which a run is done.
+#[derive(Clone, Debug)]
+pub struct FlowTarget {
+    pub pin: u8,
+    pub pulses: u32,
+    /// Count of the sensor when the run started.
+    start: Option<u32>,
+}
+
+impl FlowTarget {
+    pub fn new(pin: u8, pulses: u32) -> FlowTarget {
+        FlowTarget {
+            pin,
+            pulses,
+            start: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Run {
+    pin: u8,
+    active_low: bool,
+    remaining: Duration,
+    flow: Option<FlowTarget>,
+    on: bool,
+}
+
+/// The running actuators and the recent runs of every pin.
+#[derive(Clone, Debug, Default)]
+pub struct ActuatorRuns {
+    limiters: HashMap<u8, RunLimiter>,
+    runs: Vec<Run>,
+    /// Pins known to be switched off, with whether they are active low.
+    idle: HashSet<(u8, bool)>,
+}
+
+impl ActuatorRuns {
+    /// Whether the actuator on the pin is running or about to.
+    #[must_use]
+    pub fn is_running(&self, pin: u8) -> bool {
+        self.runs
+            .iter()
+            .any(|run| run.pin == pin && !run.remaining.is_zero())
+    }
+
+    /// Seconds until the actuator on the pin can run again, 0 if it can run now.
+    #[must_use]
+    pub fn blocked_for(&self, pin: u8, now: u64) -> u64 {
+        match self
+            .limiters
+            .get(&pin)
+            .cloned()
+            .unwrap_or_default()
+            .blocked_for(now)
+        {
+            Some(ErrStatus::ActuatorCoolingDown { seconds })
+            | Some(ErrStatus::DailyRunLimit { seconds }) => seconds,
+            _ => 0,
+        }
+    }
+
+    /// Starts the actuator if the limits allow it, `now` is the time since the start of the board.
+    ///
+    /// The actuator is switched on with the next tick of [`drive_actuators`].
+    pub fn start(
+        &mut self,
+        actuator: &Actuator,
+        duration: Duration,
+        flow: Option<FlowTarget>,
+        now: u64,
+        plant: u16,
+        index: u8,
+    ) -> Result<(), ErrStatus> {
+        if self.runs.iter().any(|run| run.pin == actuator.pin) {
+            return Err(ErrStatus::ActuatorRunning {
+                plant,
+                actuator: index,
+            });
+        }
+        let limiter = self.limiters.entry(actuator.pin).or_default();
+        if let Some(e) = limiter.blocked_for(now) {
+            return Err(e);
+        }
+        limiter.record(now);
+        self.runs.push(Run {
+            pin: actuator.pin,
+            active_low: actuator.active_low,
+            remaining: duration,
+            flow,
+            on: false,
+        });
+        Ok(())
+    }
+
+    /// Switches the actuator on the pin off with the next tick, it is fine if it isn't running.
+    pub fn stop(&mut self, pin: u8) {
+        for run in self.runs.iter_mut().filter(|run| run.pin == pin) {
+            run.remaining = Duration::ZERO;
+        }
+    }
+
+    /// Switches the runs on and off, and all other `actuators` off.
+    pub fn drive<O: OutputPins>(
+        &mut self,
+        outputs: &mut O,
+        actuators: &[Actuator],
+        elapsed: Duration,
+    ) {
+        for run in self.runs.iter_mut() {
+            if run.on {
+                run.remaining = run.remaining.saturating_sub(elapsed);
+            } else if !run.remaining.is_zero() {
+                self.idle.retain(|(pin, _)| *pin != run.pin);
+                if let Err(e) = switch_on(outputs, run) {
+                    error!("Cannot start the actuator on GPIO {}: {:?}", run.pin, e);
+                    run.remaining = Duration::ZERO;
+                }
+                continue;
+            }
+            if let Some(flow) = &run.flow {
+                match outputs.pulses(flow.pin) {
+                    Ok(pulses)
+                        if pulses.wrapping_sub(flow.start.unwrap_or(pulses)) >= flow.pulses =>
+                    {
+                        run.remaining = Duration::ZERO;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        error!("Cannot read the flow sensor on GPIO {}: {:?}", flow.pin, e);
+                        run.remaining = Duration::ZERO;
+                    }
+                }
+            }
+        }
+        // runs that can't be switched off are kept, so switching them off is retried
+        let idle = &mut self.idle;
+        self.runs.retain(|run| {
+            if !run.remaining.is_zero() {
+                return true;
+            }
+            match outputs.set_level(run.pin, run.active_low) {
+                Ok(()) => {
+                    info!("Stopped the actuator on GPIO {}", run.pin);
+                    idle.insert((run.pin, run.active_low));
+                    false
+                }
+                Err(e) => {
+                    error!("Cannot stop the actuator on GPIO {}: {:?}", run.pin, e);
+                    true
+                }
+            }
+        });
+        for actuator in actuators {
+            let pin = (actuator.pin, actuator.active_low);
+            if self.idle.contains(&pin) || self.runs.iter().any(|run| run.pin == actuator.pin) {
+                continue;
+            }
+            match outputs.set_level(actuator.pin, actuator.active_low) {
+                Ok(()) => {
+                    self.idle.insert(pin);
+                }
+                Err(e) => error!("Cannot switch off GPIO {}: {:?}", actuator.pin, e),
+            }
+        }
+    }
+}
+
+fn switch_on<O: OutputPins>(outputs: &mut O, run: &mut Run) -> anyhow::Result<()> {
+    if let Some(flow) = &mut run.flow {
+        flow.start = Some(outputs.pulses(flow.pin)?);
+    }
+    // the level is set even if it fails, so the pin is switched off again
+    run.on = true;
+    outputs.set_level(run.pin, !run.active_low)?;
+    info!("Started the actuator on GPIO {}", run.pin);
+    Ok(())
+}
+
+/// Switches the actuators of all plants, which are off unless started through the [`PlantDB`].
+pub async fn drive_actuators<O: OutputPins, K: KeyValueStore>(
+    mut outputs: O,
+    plants: Arc<Mutex<PlantDB<K>>>,
+) {
+    let mut last = tokio::time::Instant::now();
+    loop {
+        tokio::time::sleep(TICK).await;
+        let now = tokio::time::Instant::now();
+        plants
+            .lock()
+            .await
+            .drive_actuators(&mut outputs, now - last);
+        last = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plant_common::{FlowSensor, PlantSettings, RunRequest, MAX_RUN_SECONDS};
+
+    use super::*;
+    use crate::host::{basil, clocked_db, ManualClock, MemoryOutputs, MemoryStore};
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn pump() -> Actuator {
+        Actuator {
+            pin: 16,
+            ..Default::default()
+        }
+    }
+
+    fn settings(actuator: Actuator) -> PlantSettings {
+        PlantSettings {
+            actuators: vec![actuator],
+            ..basil()
+        }
+    }
+
+    /// A plant with the actuator, on a board that runs long enough to start it.
+    fn db_with_pump(actuator: Actuator) -> (PlantDB<MemoryStore>, ManualClock) {
+        let (db, clock) = clocked_db([settings(actuator)]);
+        clock.advance(MIN_RUN_INTERVAL);
+        (db, clock)
+    }
+
+    #[test]
+    fn runs_for_the_requested_time() {
+        let relay = Actuator {
+            pin: 16,
+            active_low: true,
+            ..Default::default()
+        };
+        let (mut db, _) = db_with_pump(relay);
+        let mut outputs = MemoryOutputs::new();
+        db.drive_actuators(&mut outputs, SECOND);
+        assert_eq!(outputs.level(16), Some(true));
+
+        db.start_run(0, 0, RunRequest::Seconds(2.0)).unwrap();
+        db.drive_actuators(&mut outputs, SECOND);
+        assert_eq!(outputs.level(16), Some(false));
+        assert!(db.get_plant(0).unwrap().actuators[0].running);
+        db.drive_actuators(&mut outputs, SECOND);
+        assert_eq!(outputs.level(16), Some(false));
+        db.drive_actuators(&mut outputs, SECOND);
+        assert_eq!(outputs.level(16), Some(true));
+        assert!(!db.get_plant(0).unwrap().actuators[0].running);
+    }
+
+    #[test]
+    fn flow_sensor_ends_run() {
+        let pump = Actuator {
+            pin: 16,
+            flow_sensor: Some(FlowSensor {
+                pin: 17,
+                pulses_per_litre: 450.0,
+            }),
+            ..Default::default()
+        };
+        let (mut db, _) = db_with_pump(pump);
+        let mut outputs = MemoryOutputs::new();
+        outputs.add_pulses(17, 1000);
+        db.start_run(0, 0, RunRequest::Millilitres(200.0)).unwrap();
+        db.drive_actuators(&mut outputs, SECOND);
+        assert_eq!(outputs.level(16), Some(true));
+        outputs.add_pulses(17, 80);
+        db.drive_actuators(&mut outputs, SECOND);
+        assert_eq!(outputs.level(16), Some(true));
+        outputs.add_pulses(17, 10);
+        db.drive_actuators(&mut outputs, SECOND);
+        assert_eq!(outputs.level(16), Some(false));
+    }
+
+    #[test]
+    fn runs_are_limited() {
+        let pump = Actuator {
+            pin: 16,
+            flow_rate: Some(5.0),
+            ..Default::default()
+        };
+        let (mut db, clock) = db_with_pump(pump);
+        let too_long = MAX_RUN_SECONDS as f32 + 1.0;
+        assert_eq!(
+            db.start_run(0, 0, RunRequest::Seconds(too_long)),
+            Err(ErrStatus::RunTooLong { seconds: too_long })
+        );
+        assert!(db
+            .start_run(0, 0, RunRequest::Millilitres(10_000.0))
+            .is_err());
+        assert_eq!(
+            db.start_run(0, 0, RunRequest::Seconds(-1.0)),
+            Err(ErrStatus::BadRequest)
+        );
+
+        db.start_run(0, 0, RunRequest::Seconds(5.0)).unwrap();
+        assert_eq!(
+            db.start_run(0, 0, RunRequest::Seconds(5.0)),
+            Err(ErrStatus::ActuatorRunning {
+                plant: 0,
+                actuator: 0
+            })
+        );
+        db.stop_run(0, 0).unwrap();
+        db.drive_actuators(&mut MemoryOutputs::new(), SECOND);
+        assert_eq!(
+            db.start_run(0, 0, RunRequest::Seconds(5.0)),
+            Err(ErrStatus::ActuatorCoolingDown {
+                seconds: MIN_RUN_INTERVAL
+            })
+        );
+        for _ in 1..MAX_RUNS_PER_DAY {
+            clock.advance(MIN_RUN_INTERVAL);
+            db.start_run(0, 0, RunRequest::Seconds(1.0)).unwrap();
+            db.stop_run(0, 0).unwrap();
+            db.drive_actuators(&mut MemoryOutputs::new(), SECOND);
+        }
+        clock.advance(MIN_RUN_INTERVAL);
+        let first_run_ends = DAY - (MAX_RUNS_PER_DAY as u64 * MIN_RUN_INTERVAL);
+        assert_eq!(
+            db.start_run(0, 0, RunRequest::Seconds(1.0)),
+            Err(ErrStatus::DailyRunLimit {
+                seconds: first_run_ends
+            })
+        );
+        assert_eq!(
+            db.get_plant(0).unwrap().actuators[0].blocked_for,
+            first_run_ends
+        );
+    }
+
+    #[test]
+    fn no_runs_right_after_start() {
+        let (mut db, clock) = clocked_db([settings(pump())]);
+        assert_eq!(
+            db.start_run(0, 0, RunRequest::Seconds(1.0)),
+            Err(ErrStatus::ActuatorCoolingDown {
+                seconds: MIN_RUN_INTERVAL
+            })
+        );
+        clock.advance(MIN_RUN_INTERVAL);
+        assert_eq!(db.start_run(0, 0, RunRequest::Seconds(1.0)), Ok(()));
+    }
+
+    #[test]
+    fn unknown_runs_are_rejected() {
+        let (mut db, _) = db_with_pump(pump());
+        assert_eq!(
+            db.start_run(0, 0, RunRequest::Millilitres(100.0)),
+            Err(ErrStatus::FlowUnknown {
+                plant: 0,
+                actuator: 0
+            })
+        );
+        assert_eq!(
+            db.start_run(0, 1, RunRequest::Seconds(1.0)),
+            Err(ErrStatus::ActuatorNotFound {
+                plant: 0,
+                actuator: 1
+            })
+        );
+    }
+}
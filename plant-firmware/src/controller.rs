@@ -0,0 +1,550 @@
+//! Waters a plant on its own once its moisture stayed below the watering threshold for a while.
+//!
+//! After watering it waits for the water to soak in, measures again and waters until the
+//! moisture is back in the target band. It stops for good if the readings look wrong.
+
+use std::collections::VecDeque;
+
+use plant_common::{ControllerSettings, ControllerStatus, LockoutReason, Moisture, PlantEventKind};
+
+/// Seconds without readings after which the sensor is considered broken.
+const SENSOR_TIMEOUT: u64 = 5 * 60;
+/// Rise of the moisture in percentage points expected after watering, less means no water arrived.
+const MIN_RISE: f32 = 2.0;
+const DAY: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug, Default)]
+enum State {
+    #[default]
+    Idle,
+    /// Below the threshold since `since`, the budget event is only recorded once.
+    Dry {
+        since: u64,
+        budget_exhausted: bool,
+    },
+    /// Watered at `since` with the moisture at `before`.
+    Soaking {
+        since: u64,
+        before: f32,
+    },
+    LockedOut(LockoutReason),
+}
+
+/// Seconds the plant has to be dry before it is watered, the longer of the setting and the
+/// dry out days of its care profile.
+fn dry_for(settings: &ControllerSettings, moisture: &Moisture) -> u64 {
+    (settings.dry_for as u64).max(moisture.dry_out_seconds())
+}
+
+/// What the controller wants the board to do.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// Water the plant, then report it with [`Controller::watered`].
+    Water {
+        amount_ml: f32,
+        moisture: f32,
+    },
+    Record(PlantEventKind),
+}
+
+/// The state of the controller of one plant, times are seconds since the start of the board.
+#[derive(Clone, Debug, Default)]
+pub struct Controller {
+    state: State,
+    /// Times and amounts of the waterings of the last 24 hours.
+    given: VecDeque<(u64, f32)>,
+    /// Since when there is no reading.
+    stale_since: Option<u64>,
+}
+
+impl Controller {
+    #[must_use]
+    pub fn status(&self, settings: &ControllerSettings, now: u64) -> ControllerStatus {
+        match &self.state {
+            State::Idle => ControllerStatus::Idle,
+            State::Dry { since, .. } => ControllerStatus::Dry {
+                seconds: now.saturating_sub(*since),
+            },
+            State::Soaking { since, .. } => ControllerStatus::Soaking {
+                seconds_left: (since + settings.soak_for as u64).saturating_sub(now),
+            },
+            State::LockedOut(reason) => ControllerStatus::LockedOut(reason.clone()),
+        }
+    }
+
+    #[must_use]
+    pub fn given_today(&self, now: u64) -> f32 {
+        self.given
+            .iter()
+            .filter(|(time, _)| time + DAY > now)
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+
+    /// Decides what to do about the current moisture, `fresh` tells whether it was just measured.
+    pub fn update(
+        &mut self,
+        settings: &ControllerSettings,
+        moisture: &Moisture,
+        fresh: bool,
+        now: u64,
+    ) -> Option<Step> {
+        if let State::LockedOut(_) = self.state {
+            return None;
+        }
+        let Some(percent) = moisture.percent().filter(|_| fresh) else {
+            let since = *self.stale_since.get_or_insert(now);
+            if now - since >= SENSOR_TIMEOUT {
+                return Some(self.lock_out(LockoutReason::SensorFault));
+            }
+            return None;
+        };
+        self.stale_since = None;
+        self.given.retain(|(time, _)| time + DAY > now);
+        match self.state {
+            State::Idle => {
+                if percent < moisture.needs_water_below() {
+                    self.state = State::Dry {
+                        since: now,
+                        budget_exhausted: false,
+                    };
+                }
+                None
+            }
+            State::Dry { since, .. } => {
+                if percent >= moisture.needs_water_below() {
+                    self.state = State::Idle;
+                    None
+                } else if now - since >= dry_for(settings, moisture) {
+                    self.dose(settings, moisture, percent, now)
+                } else {
+                    None
+                }
+            }
+            State::Soaking { since, before } => {
+                if now - since < settings.soak_for as u64 {
+                    None
+                } else if settings.dry_run {
+                    // no water arrived, waiting for the dry period again keeps it from dosing
+                    // over and over
+                    self.state = State::Idle;
+                    None
+                } else if percent - before < MIN_RISE {
+                    Some(self.lock_out(LockoutReason::NoRise {
+                        moisture_before: before,
+                        moisture_after: percent,
+                    }))
+                } else if percent <= moisture.band().dry_below {
+                    // still below the target band, water again right away
+                    self.state = State::Dry {
+                        since: now.saturating_sub(dry_for(settings, moisture)),
+                        budget_exhausted: false,
+                    };
+                    self.dose(settings, moisture, percent, now)
+                } else {
+                    self.state = State::Idle;
+                    None
+                }
+            }
+            State::LockedOut(_) => None,
+        }
+    }
+
+    /// The water to give, limited by the daily budget.
+    fn dose(
+        &mut self,
+        settings: &ControllerSettings,
+        moisture: &Moisture,
+        percent: f32,
+        now: u64,
+    ) -> Option<Step> {
+        let wanted = moisture
+            .recommended_water_ml()
+            .map_or(settings.max_dose_ml, |ml| ml.min(settings.max_dose_ml));
+        let left = settings.daily_budget_ml - self.given_today(now);
+        if left < 1.0 {
+            if let State::Dry {
+                budget_exhausted, ..
+            } = &mut self.state
+            {
+                if !*budget_exhausted {
+                    *budget_exhausted = true;
+                    return Some(Step::Record(PlantEventKind::BudgetExhausted {
+                        budget_ml: settings.daily_budget_ml,
+                    }));
+                }
+            }
+            return None;
+        }
+        Some(Step::Water {
+            amount_ml: wanted.min(left).max(1.0),
+            moisture: percent,
+        })
+    }
+
+    /// Whether a dose is soaking in.
+    #[must_use]
+    pub fn is_soaking(&self) -> bool {
+        matches!(self.state, State::Soaking { .. })
+    }
+
+    /// Starts waiting for the water to soak in, the doses of a dry run don't use up the budget.
+    pub fn watered(&mut self, amount_ml: f32, moisture: f32, dry_run: bool, now: u64) {
+        if !dry_run {
+            self.given.push_back((now, amount_ml));
+        }
+        self.state = State::Soaking {
+            since: now,
+            before: moisture,
+        };
+    }
+
+    fn lock_out(&mut self, reason: LockoutReason) -> Step {
+        self.state = State::LockedOut(reason.clone());
+        Step::Record(PlantEventKind::LockedOut(reason))
+    }
+
+    /// Starts over with changed settings, the waterings of the day still count against the budget.
+    ///
+    /// A lockout is kept unless the plant is watered by a different actuator now, only
+    /// [`Controller::reset`] clears it otherwise.
+    pub fn settings_changed(&mut self, actuator_changed: bool) {
+        if actuator_changed || !matches!(self.state, State::LockedOut(_)) {
+            self.state = State::Idle;
+        }
+        self.stale_since = None;
+    }
+
+    /// Starts over after a lockout, returns whether it was locked out.
+    pub fn reset(&mut self) -> bool {
+        let locked_out = matches!(self.state, State::LockedOut(_));
+        self.state = State::Idle;
+        self.stale_since = None;
+        locked_out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plant_common::{
+        Actuator, CareProfile, Connector, ErrStatus, EventQuery, PlantEvent, PlantSettings,
+        PlantUpdate, MIN_RUN_INTERVAL,
+    };
+
+    use super::*;
+    use crate::host::{basil, clocked_db, voltage_for, FixedSensors};
+    use crate::plant::measure_once;
+
+    fn moisture(percent: f32) -> Moisture {
+        Moisture {
+            measured_voltage: Some(voltage_for(percent)),
+            ..Default::default()
+        }
+    }
+
+    fn settings() -> ControllerSettings {
+        ControllerSettings {
+            dry_run: false,
+            dry_for: 60,
+            soak_for: 600,
+            ..Default::default()
+        }
+    }
+
+    /// Lets the plant dry for long enough and returns the watering the controller asks for.
+    fn water(controller: &mut Controller, settings: &ControllerSettings, now: u64) -> Option<Step> {
+        controller.update(settings, &moisture(30.0), true, now);
+        controller.update(
+            settings,
+            &moisture(30.0),
+            true,
+            now + settings.dry_for as u64,
+        )
+    }
+
+    #[test]
+    fn waters_after_dry_period() {
+        let settings = settings();
+        let mut controller = Controller::default();
+        assert_eq!(controller.update(&settings, &moisture(30.0), true, 0), None);
+        assert_eq!(
+            controller.status(&settings, 30),
+            ControllerStatus::Dry { seconds: 30 }
+        );
+        assert_eq!(
+            controller.update(&settings, &moisture(30.0), true, 59),
+            None
+        );
+        let Some(Step::Water {
+            amount_ml,
+            moisture: before,
+        }) = controller.update(&settings, &moisture(30.0), true, 60)
+        else {
+            panic!("the plant isn't watered");
+        };
+        assert_eq!(amount_ml, settings.max_dose_ml);
+        assert!((before - 30.0).abs() < 0.1);
+
+        controller.watered(amount_ml, before, false, 60);
+        assert_eq!(
+            controller.status(&settings, 160),
+            ControllerStatus::Soaking { seconds_left: 500 }
+        );
+        assert_eq!(
+            controller.update(&settings, &moisture(30.0), true, 300),
+            None
+        );
+        assert_eq!(
+            controller.update(&settings, &moisture(50.0), true, 660),
+            None
+        );
+        assert_eq!(controller.status(&settings, 660), ControllerStatus::Idle);
+        assert_eq!(controller.given_today(660), amount_ml);
+        assert_eq!(controller.given_today(60 + DAY), 0.0);
+    }
+
+    #[test]
+    fn no_rise_locks_out() {
+        let settings = settings();
+        let mut controller = Controller::default();
+        assert!(matches!(
+            water(&mut controller, &settings, 0),
+            Some(Step::Water { .. })
+        ));
+        controller.watered(100.0, 30.0, false, 60);
+        let Some(Step::Record(PlantEventKind::LockedOut(LockoutReason::NoRise { .. }))) =
+            controller.update(&settings, &moisture(31.0), true, 660)
+        else {
+            panic!("the controller isn't locked out");
+        };
+        assert_eq!(
+            controller.update(&settings, &moisture(10.0), true, 2000),
+            None
+        );
+        assert!(controller.reset());
+        assert!(!controller.reset());
+        assert_eq!(controller.status(&settings, 2000), ControllerStatus::Idle);
+    }
+
+    #[test]
+    fn waters_until_in_band() {
+        let settings = settings();
+        let mut controller = Controller::default();
+        water(&mut controller, &settings, 0);
+        controller.watered(100.0, 20.0, false, 60);
+        // risen, but still too dry
+        assert!(matches!(
+            controller.update(&settings, &moisture(30.0), true, 660),
+            Some(Step::Water { .. })
+        ));
+    }
+
+    #[test]
+    fn budget_limits_watering() {
+        let settings = ControllerSettings {
+            daily_budget_ml: 150.0,
+            ..settings()
+        };
+        let mut controller = Controller::default();
+        water(&mut controller, &settings, 0);
+        controller.watered(100.0, 20.0, false, 60);
+        let Some(Step::Water { amount_ml, .. }) =
+            controller.update(&settings, &moisture(30.0), true, 660)
+        else {
+            panic!("the plant isn't watered");
+        };
+        assert_eq!(amount_ml, 50.0);
+        controller.watered(amount_ml, 30.0, false, 660);
+        assert_eq!(
+            controller.update(&settings, &moisture(35.0), true, 1260),
+            Some(Step::Record(PlantEventKind::BudgetExhausted {
+                budget_ml: 150.0
+            }))
+        );
+        assert_eq!(
+            controller.update(&settings, &moisture(35.0), true, 1300),
+            None
+        );
+        assert!(matches!(
+            controller.update(&settings, &moisture(35.0), true, 60 + DAY),
+            Some(Step::Water { .. })
+        ));
+    }
+
+    #[test]
+    fn dry_run_keeps_budget() {
+        let settings = ControllerSettings {
+            dry_run: true,
+            ..settings()
+        };
+        let mut controller = Controller::default();
+        let Some(Step::Water { amount_ml, .. }) = water(&mut controller, &settings, 0) else {
+            panic!("the dry run doesn't water");
+        };
+        controller.watered(amount_ml, 30.0, true, 60);
+        assert_eq!(controller.given_today(60), 0.0);
+        // the reading doesn't rise without water, the plant is only dosed after the dry period
+        assert_eq!(
+            controller.update(&settings, &moisture(30.0), true, 660),
+            None
+        );
+        assert_eq!(controller.status(&settings, 660), ControllerStatus::Idle);
+        assert!(matches!(
+            water(&mut controller, &settings, 700),
+            Some(Step::Water { .. })
+        ));
+    }
+
+    #[test]
+    fn settings_changes_keep_budget_and_lockout() {
+        let settings = ControllerSettings {
+            daily_budget_ml: 150.0,
+            ..settings()
+        };
+        let mut controller = Controller::default();
+        water(&mut controller, &settings, 0);
+        controller.watered(100.0, 30.0, false, 60);
+        controller.settings_changed(false);
+        assert_eq!(controller.status(&settings, 100), ControllerStatus::Idle);
+        assert_eq!(controller.given_today(100), 100.0);
+        let Some(Step::Water { amount_ml, .. }) = water(&mut controller, &settings, 100) else {
+            panic!("the plant isn't watered");
+        };
+        assert_eq!(amount_ml, 50.0);
+
+        controller.watered(amount_ml, 30.0, false, 160);
+        controller.update(&settings, &moisture(30.5), true, 760);
+        controller.settings_changed(false);
+        assert!(matches!(
+            controller.status(&settings, 800),
+            ControllerStatus::LockedOut(LockoutReason::NoRise { .. })
+        ));
+        controller.settings_changed(true);
+        assert_eq!(controller.status(&settings, 800), ControllerStatus::Idle);
+        assert_eq!(controller.given_today(800), 150.0);
+    }
+
+    #[test]
+    fn waits_for_dry_out_days() {
+        let settings = settings();
+        let mut controller = Controller::default();
+        let succulent = |percent| Moisture {
+            profile: Some(CareProfile {
+                needs_water_below: 40.0,
+                dry_out_days: 1.0,
+                ..Default::default()
+            }),
+            ..moisture(percent)
+        };
+        assert_eq!(
+            controller.update(&settings, &succulent(30.0), true, 0),
+            None
+        );
+        assert_eq!(
+            controller.update(&settings, &succulent(30.0), true, 3600),
+            None
+        );
+        assert!(matches!(
+            controller.update(&settings, &succulent(30.0), true, DAY),
+            Some(Step::Water { .. })
+        ));
+    }
+
+    #[test]
+    fn missing_readings_lock_out() {
+        let settings = settings();
+        let mut controller = Controller::default();
+        assert_eq!(
+            controller.update(&settings, &moisture(30.0), false, 0),
+            None
+        );
+        assert_eq!(
+            controller.update(&settings, &moisture(30.0), true, 100),
+            None
+        );
+        assert_eq!(
+            controller.update(&settings, &Moisture::default(), true, 200),
+            None
+        );
+        assert_eq!(
+            controller.update(&settings, &Moisture::default(), true, 200 + SENSOR_TIMEOUT),
+            Some(Step::Record(PlantEventKind::LockedOut(
+                LockoutReason::SensorFault
+            )))
+        );
+    }
+
+    #[tokio::test]
+    async fn board_runs_the_controller() {
+        let (mut db, clock) = clocked_db([]);
+        let pump = Actuator {
+            pin: 16,
+            flow_rate: Some(5.0),
+            ..Default::default()
+        };
+        let mut plant = PlantSettings {
+            actuators: vec![Actuator {
+                flow_rate: None,
+                ..pump.clone()
+            }],
+            controller: Some(ControllerSettings {
+                dry_run: true,
+                ..settings()
+            }),
+            ..basil()
+        };
+        assert_eq!(
+            db.create_plant(plant.clone()),
+            Err(ErrStatus::FlowUnknown {
+                plant: 0,
+                actuator: 0
+            })
+        );
+        plant.actuators = vec![pump];
+        db.create_plant(plant).unwrap();
+        clock.advance(MIN_RUN_INTERVAL);
+        let mut sensors = FixedSensors::new();
+        sensors.set(Connector::GPIO(32), voltage_for(30.0));
+
+        measure_once(&mut sensors, &mut db).await;
+        clock.advance(60);
+        measure_once(&mut sensors, &mut db).await;
+        let events = db.get_events(&EventQuery::default());
+        assert!(matches!(
+            events[..],
+            [PlantEvent {
+                kind: PlantEventKind::Pumped { dry_run: true, .. },
+                ..
+            }]
+        ));
+        let info = db.get_plant(0).unwrap();
+        assert!(!info.actuators[0].running);
+        let controller = info.controller.unwrap();
+        assert!(matches!(
+            controller.status,
+            ControllerStatus::Soaking { .. }
+        ));
+        assert_eq!(controller.given_today_ml, 0.0);
+
+        let update = PlantUpdate {
+            controller: Some(Some(settings())),
+            ..Default::default()
+        };
+        db.update_plant(0, update).unwrap();
+        measure_once(&mut sensors, &mut db).await;
+        clock.advance(60);
+        measure_once(&mut sensors, &mut db).await;
+        assert!(db.get_plant(0).unwrap().actuators[0].running);
+        clock.advance(600);
+        measure_once(&mut sensors, &mut db).await;
+        let info = db.get_plant(0).unwrap().controller.unwrap();
+        assert!(matches!(info.status, ControllerStatus::LockedOut(_)));
+        // only the real dose counts against the budget
+        assert_eq!(info.given_today_ml, 100.0);
+
+        db.reset_controller(0).unwrap();
+        let events = db.get_events(&EventQuery::default());
+        assert_eq!(events.last().unwrap().kind, PlantEventKind::ControllerReset);
+        assert_eq!(db.reset_controller(1), Err(ErrStatus::PlantNotFound(1)));
+    }
+}
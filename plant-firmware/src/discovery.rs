@@ -0,0 +1,101 @@
+//! Lets clients find boards on the local network.
+//!
+//! A client sends [`DISCOVERY_PROBE`] to the broadcast address on [`DISCOVERY_PORT`], every
+//! board answers the sender with a [`BoardAnnouncement`].
+//!
+//! [`DISCOVERY_PORT`]: plant_common::DISCOVERY_PORT
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::warn;
+use plant_common::{BoardAnnouncement, API_VERSION, DISCOVERY_PROBE};
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+
+use crate::hal::KeyValueStore;
+use crate::plant_db::PlantDB;
+
+pub const FIRMWARE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Answers the probes arriving on `socket` for the board serving its API on `api`.
+pub async fn answer_probes<K: KeyValueStore>(
+    socket: UdpSocket,
+    plants: Arc<Mutex<PlantDB<K>>>,
+    api: SocketAddr,
+) {
+    let mut buf = [0; 64];
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                warn!("Cannot receive discovery probes: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        if &buf[..len] != DISCOVERY_PROBE {
+            continue;
+        }
+        let name = plants.lock().await.get_name().clone();
+        let announcement = serde_json::to_vec(&announcement(name, api)).unwrap();
+        if let Err(e) = socket.send_to(&announcement, peer).await {
+            warn!("Cannot answer the discovery probe of {}: {}", peer, e);
+        }
+    }
+}
+
+fn announcement(name: String, api: SocketAddr) -> BoardAnnouncement {
+    // a board serving on all addresses is reached through the address it answers from
+    let address = match api.ip() {
+        IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+        _ => None,
+    };
+    BoardAnnouncement {
+        name,
+        firmware_version: FIRMWARE_VERSION.to_string(),
+        api_version: API_VERSION,
+        address,
+        port: api.port(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use plant_common::BoardUpdate;
+
+    use super::*;
+    use crate::host::MemoryStore;
+
+    #[tokio::test]
+    async fn probes_are_answered() {
+        let mut db = PlantDB::new(MemoryStore::new());
+        let update = BoardUpdate {
+            name: Some("Kitchen".to_string()),
+            ..Default::default()
+        };
+        db.update_board(update).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let responder = socket.local_addr().unwrap();
+        let api = SocketAddr::from(([127, 0, 0, 2], 80));
+        tokio::spawn(answer_probes(socket, Arc::new(Mutex::new(db)), api));
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.send_to(b"something else", responder).await.unwrap();
+        client.send_to(DISCOVERY_PROBE, responder).await.unwrap();
+        let mut buf = [0; 512];
+        let (len, from) = client.recv_from(&mut buf).await.unwrap();
+        assert_eq!(from, responder);
+        let answer: BoardAnnouncement = serde_json::from_slice(&buf[..len]).unwrap();
+        assert_eq!(answer.name, "Kitchen");
+        assert_eq!(answer.api_version, API_VERSION);
+        assert_eq!(answer.address, Some(Ipv4Addr::new(127, 0, 0, 2)));
+        assert_eq!(answer.port, 80);
+
+        let api = SocketAddr::from(([0, 0, 0, 0], 8080));
+        assert_eq!(announcement("Kitchen".to_string(), api).address, None);
+    }
+}
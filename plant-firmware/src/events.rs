@@ -0,0 +1,86 @@
+//! The most recent events of all plants of a board.
+
+use std::collections::VecDeque;
+
+use log::error;
+use plant_common::{EventQuery, PlantEvent, PlantEventKind, Timestamp};
+
+use crate::hal::{KeyValueStore, StorageError};
+use crate::history;
+
+/// Number of events kept, older ones are dropped.
+const MAX_EVENTS: usize = 100;
+/// Size of the stored log, an event takes at most 40 bytes.
+const BUFFER_SIZE: usize = MAX_EVENTS * 40 + 8;
+
+/// The log is stored next to the histories, it changes far more often than the settings.
+fn key() -> String {
+    format!("{}events", history::KEY_PREFIX)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EventLog {
+    events: VecDeque<PlantEvent>,
+    next_id: u32,
+}
+
+impl EventLog {
+    /// Reads the stored events, the log starts out empty if they can't be read.
+    pub fn load<K: KeyValueStore>(nvs: &K) -> EventLog {
+        let mut buf = vec![0; BUFFER_SIZE];
+        let events: VecDeque<PlantEvent> = match nvs.get_raw(&key(), &mut buf) {
+            Ok(Some(bytes)) => postcard::from_bytes(bytes).unwrap_or_else(|e| {
+                error!("Cannot decode the events: {:?}.", e);
+                VecDeque::new()
+            }),
+            Ok(None) => VecDeque::new(),
+            Err(e) => {
+                error!("Cannot read the events: {}.", e);
+                VecDeque::new()
+            }
+        };
+        let next_id = events.back().map_or(0, |event| event.id + 1);
+        EventLog { events, next_id }
+    }
+
+    /// Records an event, it is kept in memory even if it can't be stored.
+    pub fn push<K: KeyValueStore>(
+        &mut self,
+        nvs: &mut K,
+        plant: u16,
+        time: Timestamp,
+        kind: PlantEventKind,
+    ) -> Result<(), StorageError> {
+        self.events.push_back(PlantEvent {
+            id: self.next_id,
+            plant,
+            time,
+            kind,
+        });
+        self.next_id += 1;
+        if self.events.len() > MAX_EVENTS {
+            self.events.pop_front();
+        }
+        let mut buf = vec![0; BUFFER_SIZE];
+        let bytes = postcard::to_slice(&self.events, &mut buf)
+            .map_err(|e| StorageError::Failed(e.to_string()))?;
+        nvs.set_raw(&key(), bytes)
+    }
+
+    /// The most recent event.
+    #[must_use]
+    pub fn last(&self) -> Option<&PlantEvent> {
+        self.events.back()
+    }
+
+    /// The events matching the query, oldest first.
+    #[must_use]
+    pub fn query(&self, query: &EventQuery) -> Vec<PlantEvent> {
+        self.events
+            .iter()
+            .filter(|event| query.plant.is_none() || query.plant == Some(event.plant))
+            .filter(|event| query.after.is_none() || query.after < Some(event.id))
+            .cloned()
+            .collect()
+    }
+}
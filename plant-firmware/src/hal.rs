@@ -0,0 +1,111 @@
+use std::future::Future;
+
+use plant_common::{Connector, ErrStatus, Timestamp};
+
+pub struct SensorReading {
+    /// Voltage in mV for analog sensors, the raw reading for digital sensors.
+    pub value: f32,
+    /// Temperature in °C, for sensors that report one.
+    pub temperature: Option<f32>,
+}
+
+/// Reads the soil sensors of the plants.
+pub trait SensorSource: Send {
+    fn read(
+        &mut self,
+        connector: &Connector,
+    ) -> impl Future<Output = anyhow::Result<SensorReading>> + Send;
+}
+
+/// Switches the relays or MOSFETs of the actuators and counts the pulses of flow sensors.
+pub trait OutputPins: Send {
+    /// Drives the pin high or low, it is made an output first if needed.
+    fn set_level(&mut self, pin: u8, high: bool) -> anyhow::Result<()>;
+
+    /// Pulses counted on the pin, counting starts with the first call.
+    fn pulses(&mut self, pin: u8) -> anyhow::Result<u32>;
+}
+
+/// Wall-clock time of the board, with the time since the start as fallback.
+pub trait Clock: Send + Sync {
+    /// Seconds since the Unix epoch, `None` until the time was synchronized.
+    fn unix_time(&self) -> Option<u64>;
+
+    /// Seconds since the board started, never goes backwards.
+    fn uptime(&self) -> u64;
+
+    /// Changes the SNTP server the time is synchronized with.
+    fn set_server(&self, server: &str) -> anyhow::Result<()>;
+
+    fn now(&self) -> Timestamp {
+        match self.unix_time() {
+            Some(seconds) => Timestamp {
+                seconds,
+                synced: true,
+            },
+            None => Timestamp {
+                seconds: self.uptime(),
+                synced: false,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StorageError {
+    /// There is no space left for the value.
+    Full,
+    Failed(String),
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::Full => write!(f, "Storage is full"),
+            StorageError::Failed(e) => write!(f, "Storage failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<StorageError> for ErrStatus {
+    fn from(e: StorageError) -> Self {
+        match e {
+            StorageError::Full => ErrStatus::StorageFull,
+            StorageError::Failed(e) => ErrStatus::StorageWriteFailed(e),
+        }
+    }
+}
+
+/// Persistent key-value storage, modeled after the ESP-IDF NVS.
+pub trait KeyValueStore: Send {
+    fn get_u16(&self, key: &str) -> Result<Option<u16>, StorageError>;
+    fn set_u16(&mut self, key: &str, value: u16) -> Result<(), StorageError>;
+    /// Reads the value of `key` into `buf`, returns the part of `buf` that was filled.
+    fn get_raw<'a>(&self, key: &str, buf: &'a mut [u8]) -> Result<Option<&'a [u8]>, StorageError>;
+    fn set_raw(&mut self, key: &str, value: &[u8]) -> Result<(), StorageError>;
+    fn remove(&mut self, key: &str) -> Result<(), StorageError>;
+}
+
+impl<K: KeyValueStore> KeyValueStore for &mut K {
+    fn get_u16(&self, key: &str) -> Result<Option<u16>, StorageError> {
+        (**self).get_u16(key)
+    }
+
+    fn set_u16(&mut self, key: &str, value: u16) -> Result<(), StorageError> {
+        (**self).set_u16(key, value)
+    }
+
+    fn get_raw<'a>(&self, key: &str, buf: &'a mut [u8]) -> Result<Option<&'a [u8]>, StorageError> {
+        (**self).get_raw(key, buf)
+    }
+
+    fn set_raw(&mut self, key: &str, value: &[u8]) -> Result<(), StorageError> {
+        (**self).set_raw(key, value)
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), StorageError> {
+        (**self).remove(key)
+    }
+}
@@ -0,0 +1,363 @@
+//! Measurement history of a plant, downsampled into tiers of minutes, hours and days.
+//!
+//! Samples are averaged per minute, a finished hour is the average of its minutes and a
+//! finished day the average of its hours. Every tier is a ring of fixed length, so the history
+//! of a plant never needs more than a few KB of storage.
+
+use std::collections::VecDeque;
+
+use log::error;
+use plant_common::{HistoryPoint, HistoryResolution, TREND_WINDOW_DAYS};
+use serde::{Deserialize, Serialize};
+
+use crate::hal::{KeyValueStore, StorageError};
+
+/// Prefix of the storage keys of all histories, the ESP32 firmware keeps them in their own
+/// partition.
+pub const KEY_PREFIX: &str = "hist_";
+
+/// Number of intervals kept per resolution, a day of minutes, a month of hours and a year of days.
+#[must_use]
+pub fn capacity(resolution: HistoryResolution) -> usize {
+    match resolution {
+        HistoryResolution::Minute => 24 * 60,
+        HistoryResolution::Hour => 31 * 24,
+        HistoryResolution::Day => 366,
+    }
+}
+
+fn key(plant: u16, resolution: HistoryResolution) -> String {
+    let suffix = match resolution {
+        HistoryResolution::Minute => "m",
+        HistoryResolution::Hour => "h",
+        HistoryResolution::Day => "d",
+    };
+    format!("{}{}_{}", KEY_PREFIX, plant, suffix)
+}
+
+/// Size of a stored tier, every value takes at most 3 bytes, the start and length at most 15.
+fn buffer_size(resolution: HistoryResolution) -> usize {
+    capacity(resolution) * 3 + 16
+}
+
+fn align(time: u64, resolution: HistoryResolution) -> u64 {
+    time - time % resolution.seconds()
+}
+
+/// Consecutive intervals of one resolution, `None` where nothing was measured.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Tier {
+    /// Start of the oldest interval.
+    start: u64,
+    /// Readings are stored rounded, which is finer than the noise of the sensors.
+    values: VecDeque<Option<u16>>,
+}
+
+impl Tier {
+    fn newest(&self, resolution: HistoryResolution) -> Option<u64> {
+        let len = self.values.len() as u64;
+        (len > 0).then(|| self.start + (len - 1) * resolution.seconds())
+    }
+
+    /// Appends the interval starting at `time`, which must be newer than the newest one.
+    fn push(&mut self, resolution: HistoryResolution, time: u64, value: f32) {
+        let capacity = capacity(resolution);
+        let value = Some(value.round().clamp(0.0, u16::MAX as f32) as u16);
+        let next = self.start + self.values.len() as u64 * resolution.seconds();
+        let gap = (time - next) / resolution.seconds();
+        if self.values.is_empty() || gap >= capacity as u64 {
+            self.start = time;
+            self.values.clear();
+        } else {
+            self.values.extend((0..gap).map(|_| None));
+        }
+        self.values.push_back(value);
+        while self.values.len() > capacity {
+            self.values.pop_front();
+            self.start += resolution.seconds();
+        }
+    }
+
+    fn points(
+        &self,
+        resolution: HistoryResolution,
+        from: u64,
+        to: u64,
+    ) -> impl Iterator<Item = HistoryPoint> + '_ {
+        self.values
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, value)| {
+                Some(HistoryPoint {
+                    time: self.start + i as u64 * resolution.seconds(),
+                    measured_voltage: (*value)? as f32,
+                })
+            })
+            .filter(move |point| (from..=to).contains(&point.time))
+    }
+}
+
+/// Samples of the current minute, not yet part of the history.
+#[derive(Clone, Debug)]
+struct Minute {
+    start: u64,
+    sum: f32,
+    count: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PlantHistory {
+    /// One tier per entry of [`HistoryResolution::ALL`].
+    tiers: [Tier; 3],
+    /// Tiers that changed since they were last stored.
+    changed: [bool; 3],
+    minute: Option<Minute>,
+}
+
+impl PlantHistory {
+    /// Reads the history of a plant, tiers that can't be read start out empty.
+    pub fn load<K: KeyValueStore>(nvs: &K, plant: u16) -> PlantHistory {
+        let mut history = PlantHistory::default();
+        for (level, resolution) in HistoryResolution::ALL.into_iter().enumerate() {
+            let mut buf = vec![0; buffer_size(resolution)];
+            match nvs.get_raw(&key(plant, resolution), &mut buf) {
+                Ok(Some(bytes)) => match postcard::from_bytes(bytes) {
+                    Ok(tier) => history.tiers[level] = tier,
+                    Err(e) => error!(
+                        "Cannot decode {:?} history of plant {}: {:?}.",
+                        resolution, plant, e
+                    ),
+                },
+                Ok(None) => {}
+                Err(e) => error!(
+                    "Cannot read {:?} history of plant {}: {}.",
+                    resolution, plant, e
+                ),
+            }
+        }
+        history
+    }
+
+    /// Writes the tiers that changed since they were last stored.
+    pub fn store<K: KeyValueStore>(&mut self, nvs: &mut K, plant: u16) -> Result<(), StorageError> {
+        for (level, resolution) in HistoryResolution::ALL.into_iter().enumerate() {
+            if self.changed[level] {
+                let mut buf = vec![0; buffer_size(resolution)];
+                let bytes = postcard::to_slice(&self.tiers[level], &mut buf)
+                    .map_err(|e| StorageError::Failed(e.to_string()))?;
+                nvs.set_raw(&key(plant, resolution), bytes)?;
+                self.changed[level] = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the stored history of a plant.
+    pub fn remove<K: KeyValueStore>(nvs: &mut K, plant: u16) -> Result<(), StorageError> {
+        HistoryResolution::ALL
+            .into_iter()
+            .try_for_each(|resolution| nvs.remove(&key(plant, resolution)))
+    }
+
+    /// Forgets all measurements, for example because the plant got another sensor.
+    pub fn clear(&mut self) {
+        *self = PlantHistory {
+            changed: [true; 3],
+            ..Default::default()
+        };
+    }
+
+    /// Adds a sample measured at `time`, in seconds since the Unix epoch.
+    ///
+    /// Samples older than the history are dropped, the clock of the board went backwards.
+    pub fn add(&mut self, time: u64, value: f32) {
+        let start = align(time, HistoryResolution::Minute);
+        match &mut self.minute {
+            Some(minute) if minute.start == start => {
+                minute.sum += value;
+                minute.count += 1;
+                return;
+            }
+            Some(minute) if minute.start > start => return,
+            Some(minute) => {
+                let (time, average) = (minute.start, minute.sum / minute.count as f32);
+                self.push(0, time, average);
+            }
+            None if self.tiers[0]
+                .newest(HistoryResolution::Minute)
+                .is_some_and(|newest| newest >= start) =>
+            {
+                return
+            }
+            None => {}
+        }
+        self.minute = Some(Minute {
+            start,
+            sum: value,
+            count: 1,
+        });
+    }
+
+    /// Appends an interval to the tier `level`, finishing the interval of the next coarser tier
+    /// if this one is the first after it.
+    fn push(&mut self, level: usize, time: u64, value: f32) {
+        let resolution = HistoryResolution::ALL[level];
+        let previous = self.tiers[level].newest(resolution);
+        if previous.is_some_and(|previous| previous >= time) {
+            return;
+        }
+        self.tiers[level].push(resolution, time, value);
+        self.changed[level] = true;
+
+        let (Some(previous), Some(&coarser)) = (previous, HistoryResolution::ALL.get(level + 1))
+        else {
+            return;
+        };
+        let finished = align(previous, coarser);
+        if finished != align(time, coarser) {
+            let (sum, count) = self.tiers[level]
+                .points(resolution, finished, finished + coarser.seconds() - 1)
+                .fold((0.0, 0), |(sum, count), point| {
+                    (sum + point.measured_voltage, count + 1)
+                });
+            if count > 0 {
+                self.push(level + 1, finished, sum / count as f32);
+            }
+        }
+    }
+
+    /// The time of the oldest interval of a resolution.
+    #[must_use]
+    pub fn oldest(&self, resolution: HistoryResolution) -> Option<u64> {
+        let tier = &self.tiers[resolution as usize];
+        (!tier.values.is_empty()).then_some(tier.start)
+    }
+
+    /// The intervals of a resolution that start between `from` and `to`, both inclusive.
+    #[must_use]
+    pub fn points(&self, resolution: HistoryResolution, from: u64, to: u64) -> Vec<HistoryPoint> {
+        self.tiers[resolution as usize]
+            .points(resolution, from, to)
+            .collect()
+    }
+
+    /// The readings of the last days to fit the drying trend to.
+    ///
+    /// These are the hours, followed by the minutes of the unfinished hour, averaged over
+    /// ten minutes each so they don't outweigh the hours.
+    #[must_use]
+    pub fn trend_points(&self, now: u64) -> Vec<HistoryPoint> {
+        let from = now.saturating_sub(TREND_WINDOW_DAYS * HistoryResolution::Day.seconds());
+        let mut points = self.points(HistoryResolution::Hour, from, now);
+        let minutes_from = points
+            .last()
+            .map_or(from, |hour| hour.time + HistoryResolution::Hour.seconds());
+        let minutes = self.points(HistoryResolution::Minute, minutes_from, now);
+        points.extend(minutes.chunks(10).map(|chunk| HistoryPoint {
+            time: chunk[0].time,
+            measured_voltage: chunk.iter().map(|p| p.measured_voltage).sum::<f32>()
+                / chunk.len() as f32,
+        }));
+        points
+    }
+
+    /// The finest resolution that reaches back to `from`, otherwise the one reaching back furthest.
+    #[must_use]
+    pub fn resolution_for(&self, from: u64) -> HistoryResolution {
+        let reaching_back = HistoryResolution::ALL
+            .into_iter()
+            .filter_map(|resolution| Some((resolution, self.oldest(resolution)?)));
+        reaching_back
+            .clone()
+            .find(|(_, oldest)| *oldest <= from)
+            .or_else(|| reaching_back.min_by_key(|(_, oldest)| *oldest))
+            .map_or(HistoryResolution::Minute, |(resolution, _)| resolution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::MemoryStore;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    #[test]
+    fn samples_are_averaged_per_minute() {
+        let mut history = PlantHistory::default();
+        for second in 0..120 {
+            history.add(DAY + second, if second < 60 { 1000.0 } else { 2000.0 });
+        }
+        // the second minute is still open
+        history.add(DAY + 300, 3000.0);
+
+        let points = history.points(HistoryResolution::Minute, 0, u64::MAX);
+        let values: Vec<_> = points
+            .iter()
+            .map(|p| (p.time, p.measured_voltage))
+            .collect();
+        assert_eq!(values, [(DAY, 1000.0), (DAY + 60, 2000.0)]);
+        // the gap is kept, so the ring still covers exactly one day
+        assert_eq!(history.tiers[0].values.len(), 2);
+        history.add(DAY + 360, 3000.0);
+        assert_eq!(history.tiers[0].values.len(), 6);
+    }
+
+    #[test]
+    fn finished_intervals_are_downsampled() {
+        let mut history = PlantHistory::default();
+        // one sample per minute for two days, the first day at 1000 mV, the second at 2000 mV
+        for minute in 0..2 * 24 * 60 + 1 {
+            let value = if minute < 24 * 60 { 1000.0 } else { 2000.0 };
+            history.add(DAY + minute * 60, value);
+        }
+        history.add(2 * DAY + DAY + 60, 0.0);
+
+        assert_eq!(history.points(HistoryResolution::Minute, 0, DAY).len(), 0);
+        let hours = history.points(HistoryResolution::Hour, 0, u64::MAX);
+        assert_eq!(hours.len(), 48);
+        assert_eq!(hours[0].measured_voltage, 1000.0);
+        assert_eq!(hours[47].measured_voltage, 2000.0);
+        let days = history.points(HistoryResolution::Day, 0, u64::MAX);
+        let days: Vec<_> = days.iter().map(|p| (p.time, p.measured_voltage)).collect();
+        // the second day is finished once the first hour of the third day is
+        assert_eq!(days, [(DAY, 1000.0)]);
+
+        assert_eq!(
+            history.resolution_for(2 * DAY + 3600),
+            HistoryResolution::Minute
+        );
+        assert_eq!(history.resolution_for(DAY), HistoryResolution::Hour);
+        assert_eq!(history.resolution_for(0), HistoryResolution::Hour);
+    }
+
+    #[test]
+    fn history_survives_restart() {
+        let mut store = MemoryStore::new();
+        let mut history = PlantHistory::default();
+        for minute in 0..3 {
+            history.add(DAY + minute * 60, 1500.0);
+        }
+        history.store(&mut store, 7).unwrap();
+
+        let mut history = PlantHistory::load(&store, 7);
+        assert_eq!(
+            history.points(HistoryResolution::Minute, 0, u64::MAX).len(),
+            2
+        );
+        // samples from before the restart don't overwrite the stored minutes
+        history.add(DAY + 60, 0.0);
+        history.add(DAY + 180, 1500.0);
+        history.add(DAY + 240, 1500.0);
+        assert_eq!(
+            history.points(HistoryResolution::Minute, 0, u64::MAX).len(),
+            3
+        );
+
+        PlantHistory::remove(&mut store, 7).unwrap();
+        assert_eq!(
+            PlantHistory::load(&store, 7).oldest(HistoryResolution::Minute),
+            None
+        );
+    }
+}
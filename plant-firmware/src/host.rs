@@ -0,0 +1,380 @@
+//! Implementations of the [`hal`](crate::hal) traits for running the board logic on a PC.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use log::info;
+use plant_common::Connector;
+
+use crate::hal::{Clock, KeyValueStore, OutputPins, SensorReading, SensorSource, StorageError};
+
+/// Keeps all values in memory, optionally limited to `capacity` bytes.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    values: HashMap<String, Vec<u8>>,
+    capacity: Option<usize>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> MemoryStore {
+        MemoryStore {
+            values: HashMap::new(),
+            capacity: Some(capacity),
+        }
+    }
+
+    fn used(&self) -> usize {
+        self.values.iter().map(|(k, v)| k.len() + v.len()).sum()
+    }
+}
+
+impl KeyValueStore for MemoryStore {
+    fn get_u16(&self, key: &str) -> Result<Option<u16>, StorageError> {
+        match self.values.get(key) {
+            Some(value) => value
+                .as_slice()
+                .try_into()
+                .map(|bytes| Some(u16::from_le_bytes(bytes)))
+                .map_err(|_| StorageError::Failed(format!("'{}' is not a u16", key))),
+            None => Ok(None),
+        }
+    }
+
+    fn set_u16(&mut self, key: &str, value: u16) -> Result<(), StorageError> {
+        self.set_raw(key, &value.to_le_bytes())
+    }
+
+    fn get_raw<'a>(&self, key: &str, buf: &'a mut [u8]) -> Result<Option<&'a [u8]>, StorageError> {
+        copy_into(self.values.get(key).map(Vec::as_slice), key, buf)
+    }
+
+    fn set_raw(&mut self, key: &str, value: &[u8]) -> Result<(), StorageError> {
+        if let Some(capacity) = self.capacity {
+            let old = self.values.get(key).map_or(0, |v| key.len() + v.len());
+            if self.used() - old + key.len() + value.len() > capacity {
+                return Err(StorageError::Full);
+            }
+        }
+        self.values.insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), StorageError> {
+        self.values.remove(key);
+        Ok(())
+    }
+}
+
+/// Stores every key as a file in a directory.
+#[derive(Clone, Debug)]
+pub struct FileStore {
+    directory: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(directory: impl Into<PathBuf>) -> std::io::Result<FileStore> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        Ok(FileStore { directory })
+    }
+
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        match std::fs::read(self.directory.join(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(StorageError::Failed(e.to_string())),
+        }
+    }
+}
+
+impl KeyValueStore for FileStore {
+    fn get_u16(&self, key: &str) -> Result<Option<u16>, StorageError> {
+        match self.read(key)? {
+            Some(value) => value
+                .as_slice()
+                .try_into()
+                .map(|bytes| Some(u16::from_le_bytes(bytes)))
+                .map_err(|_| StorageError::Failed(format!("'{}' is not a u16", key))),
+            None => Ok(None),
+        }
+    }
+
+    fn set_u16(&mut self, key: &str, value: u16) -> Result<(), StorageError> {
+        self.set_raw(key, &value.to_le_bytes())
+    }
+
+    fn get_raw<'a>(&self, key: &str, buf: &'a mut [u8]) -> Result<Option<&'a [u8]>, StorageError> {
+        copy_into(self.read(key)?.as_deref(), key, buf)
+    }
+
+    fn set_raw(&mut self, key: &str, value: &[u8]) -> Result<(), StorageError> {
+        std::fs::write(self.directory.join(key), value)
+            .map_err(|e| StorageError::Failed(e.to_string()))
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), StorageError> {
+        match std::fs::remove_file(self.directory.join(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(StorageError::Failed(e.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn copy_into<'a>(
+    value: Option<&[u8]>,
+    key: &str,
+    buf: &'a mut [u8],
+) -> Result<Option<&'a [u8]>, StorageError> {
+    match value {
+        Some(value) if value.len() > buf.len() => Err(StorageError::Failed(format!(
+            "'{}' does not fit into the buffer",
+            key
+        ))),
+        Some(value) => {
+            buf[..value.len()].copy_from_slice(value);
+            Ok(Some(&buf[..value.len()]))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Sensors returning fixed readings, connectors without a reading fail like a missing sensor.
+#[derive(Clone, Debug, Default)]
+pub struct FixedSensors {
+    readings: HashMap<Connector, f32>,
+}
+
+impl FixedSensors {
+    pub fn new() -> FixedSensors {
+        FixedSensors::default()
+    }
+
+    pub fn set(&mut self, connector: Connector, value: f32) {
+        self.readings.insert(connector, value);
+    }
+
+    pub fn disconnect(&mut self, connector: &Connector) {
+        self.readings.remove(connector);
+    }
+}
+
+impl SensorSource for FixedSensors {
+    async fn read(&mut self, connector: &Connector) -> anyhow::Result<SensorReading> {
+        match self.readings.get(connector) {
+            Some(value) => Ok(SensorReading {
+                value: *value,
+                temperature: None,
+            }),
+            None => anyhow::bail!("No sensor connected to {}", connector),
+        }
+    }
+}
+
+/// Output pins that only remember their level, all clones share the same pins.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryOutputs(Arc<Mutex<OutputLevels>>);
+
+#[derive(Debug, Default)]
+struct OutputLevels {
+    levels: HashMap<u8, bool>,
+    pulses: HashMap<u8, u32>,
+}
+
+impl MemoryOutputs {
+    pub fn new() -> MemoryOutputs {
+        MemoryOutputs::default()
+    }
+
+    /// The level the pin was last set to, `None` if it was never set.
+    pub fn level(&self, pin: u8) -> Option<bool> {
+        self.0.lock().unwrap().levels.get(&pin).copied()
+    }
+
+    /// Counts pulses on the pin, as a flow sensor would send them.
+    pub fn add_pulses(&self, pin: u8, count: u32) {
+        *self.0.lock().unwrap().pulses.entry(pin).or_default() += count;
+    }
+}
+
+impl OutputPins for MemoryOutputs {
+    fn set_level(&mut self, pin: u8, high: bool) -> anyhow::Result<()> {
+        if self.0.lock().unwrap().levels.insert(pin, high) != Some(high) {
+            info!("GPIO {} is {}", pin, if high { "high" } else { "low" });
+        }
+        Ok(())
+    }
+
+    fn pulses(&mut self, pin: u8) -> anyhow::Result<u32> {
+        Ok(*self.0.lock().unwrap().pulses.entry(pin).or_default())
+    }
+}
+
+/// The clock of the PC, which is assumed to be synchronized already.
+#[derive(Clone, Debug)]
+pub struct SystemClock {
+    started: Instant,
+    synced: bool,
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock {
+            started: Instant::now(),
+            synced: true,
+        }
+    }
+
+    /// A clock that never gets synchronized, like a board without internet access.
+    pub fn unsynced() -> SystemClock {
+        SystemClock {
+            synced: false,
+            ..SystemClock::new()
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn unix_time(&self) -> Option<u64> {
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+        self.synced.then_some(since_epoch.as_secs())
+    }
+
+    fn uptime(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    fn set_server(&self, _server: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// A clock that only moves when told to, all clones share the same time.
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock(Arc<Mutex<ManualTime>>);
+
+#[derive(Debug, Default)]
+struct ManualTime {
+    uptime: u64,
+    unix_time: Option<u64>,
+    server: Option<String>,
+}
+
+impl ManualClock {
+    /// An unsynchronized clock at the start of the board.
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        let mut time = self.0.lock().unwrap();
+        time.uptime += seconds;
+        time.unix_time = time.unix_time.map(|t| t + seconds);
+    }
+
+    /// Synchronizes the clock to `unix_time`, as an SNTP server would.
+    pub fn sync(&self, unix_time: u64) {
+        self.0.lock().unwrap().unix_time = Some(unix_time);
+    }
+
+    /// The server last passed to [`Clock::set_server`].
+    pub fn server(&self) -> Option<String> {
+        self.0.lock().unwrap().server.clone()
+    }
+}
+
+impl Clock for ManualClock {
+    fn unix_time(&self) -> Option<u64> {
+        self.0.lock().unwrap().unix_time
+    }
+
+    fn uptime(&self) -> u64 {
+        self.0.lock().unwrap().uptime
+    }
+
+    fn set_server(&self, server: &str) -> anyhow::Result<()> {
+        self.0.lock().unwrap().server = Some(server.to_string());
+        Ok(())
+    }
+}
+
+/// Reading of the default GPIO calibration for a moisture of the potting soil.
+#[cfg(test)]
+pub fn voltage_for(percent: f32) -> f32 {
+    match percent {
+        p if p >= 60.0 => 500.0 + (80.0 - p) * 25.0,
+        p if p >= 40.0 => 1000.0 + (60.0 - p) * 25.0,
+        p => 1500.0 + (40.0 - p) * 40.0,
+    }
+}
+
+/// Basil on GPIO 32, the plant of most tests.
+#[cfg(test)]
+pub fn basil() -> plant_common::PlantSettings {
+    plant_common::PlantSettings {
+        name: "Basil".to_string(),
+        connection: Connector::GPIO(32),
+        ..Default::default()
+    }
+}
+
+/// A board on a clock the test advances, with `plants` created from id 0 on.
+#[cfg(test)]
+pub fn clocked_db(
+    plants: impl IntoIterator<Item = plant_common::PlantSettings>,
+) -> (crate::plant_db::PlantDB<MemoryStore>, ManualClock) {
+    let clock = ManualClock::new();
+    let mut db = crate::plant_db::PlantDB::with_clock(MemoryStore::new(), Arc::new(clock.clone()));
+    for plant in plants {
+        db.create_plant(plant).unwrap();
+    }
+    (db, clock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_store_runs_full() {
+        let mut store = MemoryStore::with_capacity(16);
+        store.set_raw("plant_0", &[1; 8]).unwrap();
+        assert_eq!(store.set_raw("plant_1", &[1; 8]), Err(StorageError::Full));
+        // overwriting a value reuses its space
+        store.set_raw("plant_0", &[2; 9]).unwrap();
+        assert!(store.get_raw("plant_0", &mut [0; 4]).is_err());
+        assert_eq!(
+            store.get_raw("plant_0", &mut [0; 16]).unwrap(),
+            Some(&[2; 9][..])
+        );
+    }
+
+    #[test]
+    fn file_store_round_trip() {
+        let directory = std::env::temp_dir().join(format!("plant-store-{}", std::process::id()));
+        let mut store = FileStore::new(&directory).unwrap();
+        assert_eq!(store.get_u16("next_id"), Ok(None));
+        store.set_u16("next_id", 513).unwrap();
+        assert_eq!(
+            FileStore::new(&directory).unwrap().get_u16("next_id"),
+            Ok(Some(513))
+        );
+        store.remove("next_id").unwrap();
+        store.remove("next_id").unwrap();
+        assert_eq!(store.get_u16("next_id"), Ok(None));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,19 @@
+//! Board logic of the plant firmware, independent of the hardware it runs on.
+//!
+//! The ESP32 firmware plugs its ADC, I2C and NVS drivers in through the traits in [`hal`],
+//! [`host`] provides implementations to run and test the same code on a PC.
+
+pub mod actuator;
+pub mod controller;
+pub mod discovery;
+pub mod events;
+pub mod hal;
+pub mod history;
+pub mod host;
+pub mod pins;
+pub mod plant;
+pub mod plant_db;
+mod plant_legacy;
+pub mod server;
+pub mod watering;
+pub mod wifi;
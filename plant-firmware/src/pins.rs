@@ -0,0 +1,75 @@
+//! Capabilities of the ESP32 pins and the I2C connectors of the board.
+
+use plant_common::{Connector, PinCapability};
+
+/// Number of GPIOs of the ESP32, not all of them exist.
+pub const GPIO_COUNT: u8 = 40;
+/// Number of I2C buses, see `i2c_sensor.rs` of the ESP32 firmware for their pins.
+pub const I2C_BUSES: u8 = 2;
+/// Number of channels of the TCA9548A multiplexer.
+pub const MULTIPLEXER_CHANNELS: u8 = 8;
+
+#[must_use]
+pub fn capability(pin: u8) -> PinCapability {
+    match pin {
+        32..=36 | 39 => PinCapability::Adc1,
+        // the SDA and SCL pins of the I2C buses
+        21 | 22 | 25 | 26 => PinCapability::I2c,
+        0 | 2 | 5 | 12 | 15 => PinCapability::Strapping,
+        4 | 13 | 14 | 27 => PinCapability::Adc2,
+        16..=19 | 23 => PinCapability::Digital,
+        // serial console, flash and pins that are not broken out
+        _ => PinCapability::Reserved,
+    }
+}
+
+/// Whether the board can read a sensor on the connector.
+#[must_use]
+pub fn is_readable(connector: &Connector) -> bool {
+    match *connector {
+        Connector::GPIO(pin) => capability(pin) == PinCapability::Adc1,
+        Connector::I2c {
+            bus,
+            address,
+            channel,
+        } => {
+            bus < I2C_BUSES
+                // addresses outside are reserved by the I2C specification
+                && (0x08..=0x77).contains(&address)
+                && channel.unwrap_or(0) < MULTIPLEXER_CHANNELS
+        }
+    }
+}
+
+/// Whether the pin can switch the relay or MOSFET of an actuator.
+#[must_use]
+pub fn is_switchable(pin: u8) -> bool {
+    // ADC2 pins work fine as digital outputs, 34 to 39 are inputs only
+    matches!(
+        capability(pin),
+        PinCapability::Digital | PinCapability::Adc2
+    )
+}
+
+/// Whether the pin can count the pulses of a flow sensor.
+#[must_use]
+pub fn is_countable(pin: u8) -> bool {
+    is_switchable(pin) || capability(pin) == PinCapability::Adc1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `i2c_sensor.rs` of the ESP32 firmware drives these pins behind the back of the GPIO
+    /// wrapper, which is only sound while nothing else can be put on them.
+    #[test]
+    fn i2c_pins_are_reserved_for_the_buses() {
+        for pin in [21, 22, 25, 26] {
+            assert_eq!(capability(pin), PinCapability::I2c);
+            assert!(!is_readable(&Connector::GPIO(pin)));
+            assert!(!is_switchable(pin));
+            assert!(!is_countable(pin));
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::sync::Arc;
+
+use log::warn;
+use plant_common::{
+    Actuator, Calibration, CareProfileId, Connector, ControllerSettings, Moisture, PlantInfo,
+    SoilType, Timestamp,
+};
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::controller::Controller;
+use crate::hal::{KeyValueStore, SensorSource};
+use crate::history::PlantHistory;
+use crate::plant_db::PlantDB;
+use crate::plant_legacy;
+use crate::watering::WateringDetector;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PlantData {
+    pub id: u16,
+    pub connection: Connector,
+    pub name: String,
+    pub calibration: Calibration,
+    pub soil: SoilType,
+    pub care_profile: CareProfileId,
+    /// Volume of the pot in millilitres.
+    pub pot_volume: Option<f32>,
+    pub actuators: Vec<Actuator>,
+    pub controller: Option<ControllerSettings>,
+}
+
+impl PlantData {
+    /// Decodes a plant stored in NVS, falling back to the layouts of older firmware versions.
+    pub fn from_bytes(bytes: &[u8]) -> postcard::Result<PlantData> {
+        plant_legacy::decode(bytes)
+    }
+
+    /// The GPIOs of the actuators and their flow sensors.
+    pub fn actuator_pins(&self) -> impl Iterator<Item = u8> + '_ {
+        self.actuators.iter().flat_map(|actuator| {
+            std::iter::once(actuator.pin).chain(actuator.flow_sensor.as_ref().map(|f| f.pin))
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Plant {
+    pub info: PlantData,
+    pub measured_values: AllocRingBuffer<f32>,
+    /// Last temperature in °C, for sensors that report one.
+    pub temperature: Option<f32>,
+    /// When the sensor was last read.
+    pub measured_at: Option<Timestamp>,
+    pub history: PlantHistory,
+    pub watering: WateringDetector,
+    pub controller: Controller,
+}
+
+impl From<PlantData> for Plant {
+    fn from(plant: PlantData) -> Self {
+        Plant {
+            info: plant,
+            measured_values: AllocRingBuffer::new(60),
+            temperature: None,
+            measured_at: None,
+            history: PlantHistory::default(),
+            watering: WateringDetector::default(),
+            controller: Controller::default(),
+        }
+    }
+}
+
+impl Plant {
+    /// Average of the last `count` measured values, `None` if nothing was measured yet.
+    pub fn average_of_last(&self, count: usize) -> Option<f32> {
+        let len = self.measured_values.len();
+        if len == 0 || count == 0 {
+            return None;
+        }
+        let skip = len.saturating_sub(count);
+        Some(self.measured_values.iter().skip(skip).sum::<f32>() / (len - skip) as f32)
+    }
+}
+
+impl From<&Plant> for PlantInfo {
+    fn from(plant: &Plant) -> Self {
+        let moisture = plant.average_of_last(plant.measured_values.len());
+        PlantInfo {
+            id: plant.info.id,
+            name: plant.info.name.clone(),
+            measured_moisture: Moisture {
+                measured_voltage: moisture,
+                soil: plant.info.soil.clone(),
+                pot_volume: plant.info.pot_volume,
+                calibration: plant.info.calibration.clone(),
+                profile: None,
+            },
+            connection: plant.info.connection.clone(),
+            care_profile: plant.info.care_profile.clone(),
+            temperature: plant.temperature,
+            measured_at: plant.measured_at,
+            drying: None,
+            actuators: Vec::new(),
+            controller: None,
+        }
+    }
+}
+
+pub async fn measure_plants<S: SensorSource, K: KeyValueStore>(
+    mut sensors: S,
+    plants: Arc<Mutex<PlantDB<K>>>,
+) {
+    let mut stored = tokio::time::Instant::now();
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        let mut data = plants.lock().await;
+        measure_once(&mut sensors, &mut data).await;
+        if stored.elapsed() >= HISTORY_STORE_INTERVAL {
+            data.store_history();
+            stored = tokio::time::Instant::now();
+        }
+    }
+}
+
+/// Time between writes of the history, to spare the flash.
+///
+/// Up to this much of the history is lost when the board loses power.
+const HISTORY_STORE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+/// Reads the sensor of every plant once, plants whose sensor can't be read are skipped.
+///
+/// Readings are only added to the history once the clock is synchronized, as the history has
+/// to be comparable across restarts. The watering controllers react to the new readings.
+pub async fn measure_once<S: SensorSource, K: KeyValueStore>(
+    sensors: &mut S,
+    plants: &mut PlantDB<K>,
+) {
+    let now = plants.now();
+    let mut waterings = vec![];
+    for plant in plants.plants_iter_mut() {
+        match sensors.read(&plant.info.connection).await {
+            Ok(reading) => {
+                if reading.temperature.is_some() {
+                    plant.temperature = reading.temperature;
+                }
+                plant.measured_values.push(reading.value);
+                plant.measured_at = Some(now);
+                if now.synced {
+                    plant.history.add(now.seconds, reading.value);
+                }
+                let calibration = &plant.info.calibration;
+                let dosing = plant.controller.is_soaking();
+                if let Some(watering) =
+                    plant
+                        .watering
+                        .update(calibration, &plant.measured_values, dosing, now)
+                {
+                    waterings.push((plant.info.id, watering));
+                }
+            }
+            Err(e) => warn!("Cannot read sensor of plant {}: {:?}", plant.info.id, e),
+        }
+    }
+    for (id, watering) in waterings {
+        plants.record_watering(id, watering);
+    }
+    plants.run_controllers();
+    plants.publish_readings();
+}
+
+#[cfg(test)]
+mod tests {
+    use plant_common::{EventQuery, HistoryResolution, PlantEventKind, PlantSettings};
+
+    use super::*;
+    use crate::host::{basil, clocked_db, voltage_for, FixedSensors, MemoryStore};
+
+    #[tokio::test]
+    async fn unreadable_sensors_are_skipped() {
+        let mut db = PlantDB::new(MemoryStore::new());
+        for pin in [32, 33] {
+            let settings = PlantSettings {
+                name: pin.to_string(),
+                connection: Connector::GPIO(pin),
+                ..Default::default()
+            };
+            db.create_plant(settings).unwrap();
+        }
+        let mut sensors = FixedSensors::new();
+        sensors.set(Connector::GPIO(32), 1200.0);
+
+        measure_once(&mut sensors, &mut db).await;
+        sensors.set(Connector::GPIO(32), 1400.0);
+        measure_once(&mut sensors, &mut db).await;
+
+        let plants = db.get_plants();
+        assert_eq!(plants[0].average_of_last(2), Some(1300.0));
+        assert_eq!(plants[0].average_of_last(1), Some(1400.0));
+        assert_eq!(plants[1].average_of_last(2), None);
+    }
+
+    #[tokio::test]
+    async fn readings_are_timestamped() {
+        let (mut db, clock) = clocked_db([basil()]);
+        let mut sensors = FixedSensors::new();
+        sensors.set(Connector::GPIO(32), 1200.0);
+
+        clock.advance(90);
+        measure_once(&mut sensors, &mut db).await;
+        let unsynced = Timestamp {
+            seconds: 90,
+            synced: false,
+        };
+        assert_eq!(db.get_plant(0).unwrap().measured_at, Some(unsynced));
+        // without wall-clock time nothing is added to the history
+        assert_eq!(
+            db.get_plants()[0].history.oldest(HistoryResolution::Minute),
+            None
+        );
+
+        clock.sync(1_700_000_000);
+        for _ in 0..2 {
+            measure_once(&mut sensors, &mut db).await;
+            clock.advance(60);
+        }
+        let state = db.get_state();
+        let measured_at = state.plants[0].measured_at.unwrap();
+        assert!(measured_at.synced);
+        assert_eq!(measured_at.age_at(&state.time.unwrap()), Some(60));
+        assert_eq!(unsynced.age_at(&state.time.unwrap()), None);
+        assert!(db.get_plants()[0]
+            .history
+            .oldest(HistoryResolution::Minute)
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn drying_trend_predicts_watering() {
+        const START: u64 = 1_700_000_000 - 1_700_000_000 % 86_400;
+        let (mut db, clock) = clocked_db([basil()]);
+
+        // dry for two hours, then watered to 75 % and drying by 10 % a day for a day and a half
+        let watered = START + 2 * 3600;
+        let end = watered + 36 * 3600;
+        let history = &mut db.plants_iter_mut().next().unwrap().history;
+        for time in (START..end).step_by(60) {
+            let percent = match time {
+                t if t < watered => 30.0,
+                // a glitch of the sensor
+                t if t == watered + 10 * 3600 => 5.0,
+                t => 75.0 - (t - watered) as f32 / 86_400.0 * 10.0,
+            };
+            history.add(time, voltage_for(percent));
+        }
+        clock.sync(end);
+
+        let drying = db.get_plant(0).unwrap().drying.unwrap();
+        assert!((drying.percent_per_day + 10.0).abs() < 1.0, "{:?}", drying);
+        // 75 % reaches the 40 % of the soil after 3.5 days
+        let expected = watered + 84 * 3600;
+        let needs_water_at = drying.needs_water_at.unwrap();
+        assert!(needs_water_at.abs_diff(expected) < 2 * 3600, "{:?}", drying);
+        assert_eq!(drying.needs_water_in(end), Some(needs_water_at - end));
+    }
+
+    #[tokio::test]
+    async fn watering_is_detected() {
+        let settings = PlantSettings {
+            pot_volume: Some(1000.0),
+            ..basil()
+        };
+        let (mut db, clock) = clocked_db([settings]);
+        clock.sync(1_700_000_000);
+        let mut sensors = FixedSensors::new();
+        sensors.set(Connector::GPIO(32), voltage_for(15.0));
+        for _ in 0..100 {
+            measure_once(&mut sensors, &mut db).await;
+            clock.advance(1);
+        }
+        let watered = db.now();
+        sensors.set(Connector::GPIO(32), voltage_for(60.0));
+        for _ in 0..300 {
+            measure_once(&mut sensors, &mut db).await;
+            clock.advance(1);
+        }
+
+        let events = db.get_events(&EventQuery::default());
+        assert_eq!(events.len(), 1, "{:?}", events);
+        assert_eq!(events[0].plant, 0);
+        assert!(events[0].time.seconds.abs_diff(watered.seconds) < 5);
+        let PlantEventKind::Watered {
+            moisture_before,
+            moisture_after,
+            amount_ml,
+        } = events[0].kind
+        else {
+            panic!("unexpected event {:?}", events[0]);
+        };
+        assert!((moisture_before.unwrap() - 15.0).abs() < 0.5);
+        assert!((moisture_after.unwrap() - 60.0).abs() < 0.5);
+        // 45 % of the water the potting soil holds
+        assert!((amount_ml.unwrap() - 225.0).abs() < 5.0);
+    }
+}
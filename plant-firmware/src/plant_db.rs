@@ -0,0 +1,1565 @@
+use std::collections::HashMap;
+use std::mem::Discriminant;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, error, info};
+use plant_common::{
+    Actuator, ActuatorInfo, BoardMetadata, BoardState, BoardUpdate, Calibration, CalibrationCheck,
+    CalibrationReference, CareProfile, CareProfileId, Connector, ConnectorInfo, ConnectorStatus,
+    ControllerInfo, ControllerSettings, ControllerStatus, CustomCareProfile, ErrStatus, EventQuery,
+    History, HistoryQuery, LiveUpdate, ManualWatering, PlantEvent, PlantEventKind, PlantInfo,
+    PlantSettings, PlantUpdate, RunRequest, Timestamp, WifiNetwork, WifiStatus, MAX_ACTUATORS,
+    MAX_DESCRIPTION_LENGTH, MAX_HOST_LENGTH, MAX_ICON_LENGTH, MAX_NAME_LENGTH, MAX_RUN_SECONDS,
+};
+use ringbuffer::RingBuffer;
+use tokio::sync::broadcast;
+
+use crate::actuator::{ActuatorRuns, FlowTarget};
+use crate::controller::Step;
+use crate::events::EventLog;
+use crate::hal::{Clock, KeyValueStore, OutputPins};
+use crate::history::PlantHistory;
+use crate::host::SystemClock;
+use crate::pins;
+use crate::plant::{Plant, PlantData};
+use crate::watering::Watering;
+use crate::wifi::{access_point_ssid, WifiAction, WifiEvent, WifiNetworks, WifiSupervisor};
+
+pub struct PlantDB<K: KeyValueStore> {
+    board_name: String,
+    sntp_server: String,
+    metadata: BoardMetadata,
+    clock: Arc<dyn Clock>,
+    plants: Vec<Plant>,
+    care_profiles: Vec<CustomCareProfile>,
+    events: EventLog,
+    runs: ActuatorRuns,
+    wifi_networks: WifiNetworks,
+    wifi: WifiSupervisor,
+    live: broadcast::Sender<LiveUpdate>,
+    /// What the live stream was last told about each plant.
+    live_summaries: HashMap<u16, LiveSummary>,
+    nvs: K,
+    next_id: u16,
+    next_profile_id: u16,
+}
+const PLANT_BUFFER_SIZE: usize = 256;
+/// Size of the stored metadata, the description takes most of it.
+const METADATA_BUFFER_SIZE: usize = 512;
+const DEFAULT_BOARD_NAME: &str = "Board1";
+const DEFAULT_SNTP_SERVER: &str = "pool.ntp.org";
+/// Seconds after which the last reading of a plant is too old for its watering controller.
+const MAX_READING_AGE: u64 = 10;
+/// Number of the most recent samples averaged when capturing a calibration reference.
+const CALIBRATION_SAMPLES: usize = 10;
+/// Number of updates buffered for a slow client of the live stream before it loses them.
+const LIVE_BUFFER_SIZE: usize = 32;
+impl<K: KeyValueStore> PlantDB<K> {
+    /// Loads the database, timestamps are taken from the clock of the system.
+    pub fn new(nvs: K) -> PlantDB<K> {
+        PlantDB::with_clock(nvs, Arc::new(SystemClock::new()))
+    }
+
+    /// Loads the database and points the clock to the stored SNTP server.
+    pub fn with_clock(mut nvs: K, clock: Arc<dyn Clock>) -> PlantDB<K> {
+        let next_id = read_u16(&mut nvs, "next_id");
+        let plants = read_u16(&mut nvs, "plant_count");
+        let next_profile_id = read_u16(&mut nvs, "next_profile_id");
+        let profiles = read_u16(&mut nvs, "profile_count");
+        let mut plant_infos = vec![];
+        for i in 0..plants {
+            let key = format!("plant_{}", i);
+            let buf: &mut [u8] = &mut [0; PLANT_BUFFER_SIZE];
+            match nvs.get_raw(&key, buf) {
+                Ok(v) => {
+                    if let Some(obj) = v {
+                        match PlantData::from_bytes(obj) {
+                            Ok(plant) => {
+                                info!("Read plant {} as '{:?}'", i, plant);
+                                plant_infos.push(plant);
+                            }
+                            Err(e) => error!("Cannot decode plant {}: {:?}.", i, e),
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Cannot read plant {} from NVS: {:?}.", i, e);
+                }
+            }
+        }
+        let mut care_profiles = vec![];
+        for i in 0..profiles {
+            let key = format!("profile_{}", i);
+            let buf: &mut [u8] = &mut [0; PLANT_BUFFER_SIZE];
+            match nvs.get_raw(&key, buf) {
+                Ok(v) => {
+                    if let Some(obj) = v {
+                        match postcard::from_bytes::<CustomCareProfile>(obj) {
+                            Ok(profile) => {
+                                info!("Read care profile {} as '{:?}'", i, profile);
+                                care_profiles.push(profile);
+                            }
+                            Err(e) => error!("Cannot decode care profile {}: {:?}.", i, e),
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Cannot read care profile {} from NVS: {:?}.", i, e);
+                }
+            }
+        }
+        let board_name = read_string(&nvs, "board_name", DEFAULT_BOARD_NAME);
+        let sntp_server = read_string(&nvs, "sntp_server", DEFAULT_SNTP_SERVER);
+        let metadata = read_metadata(&nvs);
+        if let Err(e) = clock.set_server(&sntp_server) {
+            error!(
+                "Cannot synchronize the time with '{}': {:?}.",
+                sntp_server, e
+            );
+        }
+        let mut db = PlantDB {
+            board_name,
+            sntp_server,
+            metadata,
+            clock,
+            plants: plant_infos
+                .into_iter()
+                .map(|info| {
+                    let history = PlantHistory::load(&nvs, info.id);
+                    Plant {
+                        history,
+                        ..info.into()
+                    }
+                })
+                .collect(),
+            care_profiles,
+            events: EventLog::load(&nvs),
+            runs: ActuatorRuns::default(),
+            wifi_networks: WifiNetworks::load(&nvs),
+            wifi: WifiSupervisor::default(),
+            live: broadcast::channel(LIVE_BUFFER_SIZE).0,
+            live_summaries: HashMap::new(),
+            nvs,
+            next_id,
+            next_profile_id,
+        };
+        // close the gaps of unreadable entries, otherwise the slots would be shifted against the indices
+        if db.plants.len() != plants as usize {
+            error!(
+                "Dropped {} unreadable plants.",
+                plants as usize - db.plants.len()
+            );
+            let stored = (0..db.plants.len())
+                .try_for_each(|index| db.store_plant(index, &db.plants[index].info.clone()))
+                .and_then(|_| Ok(db.nvs.set_u16("plant_count", db.plants.len() as u16)?));
+            if let Err(e) = stored {
+                error!("Cannot store the remaining plants: {}.", e);
+            }
+        }
+        if db.care_profiles.len() != profiles as usize {
+            error!(
+                "Dropped {} unreadable care profiles.",
+                profiles as usize - db.care_profiles.len()
+            );
+            let stored = (0..db.care_profiles.len())
+                .try_for_each(|index| db.store_profile(index, &db.care_profiles[index].clone()))
+                .and_then(|_| {
+                    Ok(db
+                        .nvs
+                        .set_u16("profile_count", db.care_profiles.len() as u16)?)
+                });
+            if let Err(e) = stored {
+                error!("Cannot store the remaining care profiles: {}.", e);
+            }
+        }
+        db
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.board_name
+    }
+
+    /// Names the board unless the user named it, the name isn't stored.
+    pub fn set_default_name(&mut self, name: &str) {
+        let buf: &mut [u8] = &mut [0; PLANT_BUFFER_SIZE];
+        if let Ok(None) = self.nvs.get_raw("board_name", buf) {
+            self.board_name = name.to_string();
+        }
+    }
+
+    /// Changes the fields set in `update`, nothing is changed if one of them is invalid.
+    pub fn update_board(&mut self, update: BoardUpdate) -> Result<(), ErrStatus> {
+        if let Some(name) = &update.name {
+            check_name(name)?;
+        }
+        if let Some(server) = &update.sntp_server {
+            check_host(server)?;
+        }
+        let mut metadata = self.metadata.clone();
+        if let Some(location) = update.location {
+            check_name(&location)?;
+            metadata.location = location;
+        }
+        if let Some(description) = update.description {
+            if description.len() > MAX_DESCRIPTION_LENGTH {
+                return Err(ErrStatus::DescriptionTooLong);
+            }
+            metadata.description = description;
+        }
+        if let Some(color) = update.color {
+            metadata.color = color;
+        }
+        if let Some(icon) = update.icon {
+            if icon
+                .as_ref()
+                .is_some_and(|icon| icon.is_empty() || icon.len() > MAX_ICON_LENGTH)
+            {
+                return Err(ErrStatus::InvalidIcon);
+            }
+            metadata.icon = icon;
+        }
+        if metadata != self.metadata {
+            let buf: &mut [u8] = &mut [0; METADATA_BUFFER_SIZE];
+            let bytes = postcard::to_slice(&metadata, buf)
+                .map_err(|e| ErrStatus::StorageWriteFailed(e.to_string()))?;
+            self.nvs.set_raw("board_meta", bytes)?;
+            self.metadata = metadata;
+        }
+        if let Some(name) = update.name {
+            self.nvs.set_raw("board_name", name.as_bytes())?;
+            self.board_name = name;
+        }
+        if let Some(server) = update.sntp_server {
+            self.nvs.set_raw("sntp_server", server.as_bytes())?;
+            if let Err(e) = self.clock.set_server(&server) {
+                error!("Cannot synchronize the time with '{}': {:?}.", server, e);
+            }
+            self.sntp_server = server;
+        }
+        self.publish_state();
+        Ok(())
+    }
+
+    /// Whether only the Wi-Fi may be set up, see [`WifiSupervisor::access_point_open`].
+    pub fn is_provisioning(&self) -> bool {
+        self.wifi.access_point_open()
+    }
+
+    pub fn get_wifi_status(&self) -> WifiStatus {
+        WifiStatus {
+            mode: self.wifi.mode(),
+            networks: self.wifi_networks.known(),
+        }
+    }
+
+    /// Adds a network or replaces a known one, returns whether it was known.
+    pub fn add_wifi_network(&mut self, network: WifiNetwork) -> Result<bool, ErrStatus> {
+        let known = self.wifi_networks.add(&mut self.nvs, network)?;
+        self.wifi.networks_changed(self.clock.uptime());
+        Ok(known)
+    }
+
+    pub fn remove_wifi_network(&mut self, ssid: &str) -> Result<(), ErrStatus> {
+        self.wifi_networks.remove(&mut self.nvs, ssid)?;
+        self.wifi.networks_changed(self.clock.uptime());
+        Ok(())
+    }
+
+    /// Called by the Wi-Fi driver once it started, the board manages its network from now on.
+    pub fn start_wifi(&mut self) {
+        self.wifi.start(self.clock.uptime());
+    }
+
+    /// What the Wi-Fi driver should do next, see [`WifiSupervisor`].
+    pub fn next_wifi_action(&mut self) -> WifiAction {
+        let ssid = access_point_ssid(&self.board_name);
+        let uptime = self.clock.uptime();
+        self.wifi
+            .next_action(self.wifi_networks.networks(), &ssid, uptime)
+    }
+
+    pub fn handle_wifi_event(&mut self, event: WifiEvent) {
+        self.wifi.handle(event, self.clock.uptime());
+    }
+
+    /// The current time of the board.
+    pub fn now(&self) -> Timestamp {
+        self.clock.now()
+    }
+
+    pub fn get_plants(&self) -> &Vec<Plant> {
+        &self.plants
+    }
+
+    pub fn get_plant(&self, id: u16) -> Option<PlantInfo> {
+        self.get_index(id)
+            .map(|index| self.get_plant_info(&self.plants[index]))
+    }
+
+    /// The plant as sent to clients, with its care profile resolved and its drying trend.
+    pub fn get_plant_info(&self, plant: &Plant) -> PlantInfo {
+        let mut info: PlantInfo = plant.into();
+        info.measured_moisture.profile = info.care_profile.resolve(&self.care_profiles);
+        let now = self.now();
+        if now.synced {
+            let history = plant.history.trend_points(now.seconds);
+            info.drying = info.measured_moisture.drying_trend(&history, now.seconds);
+        }
+        let uptime = self.clock.uptime();
+        info.actuators = plant
+            .info
+            .actuators
+            .iter()
+            .map(|actuator| ActuatorInfo {
+                actuator: actuator.clone(),
+                running: self.runs.is_running(actuator.pin),
+                blocked_for: self.runs.blocked_for(actuator.pin, uptime),
+            })
+            .collect();
+        info.controller = plant
+            .info
+            .controller
+            .clone()
+            .map(|settings| ControllerInfo {
+                status: plant.controller.status(&settings, uptime),
+                given_today_ml: plant.controller.given_today(uptime),
+                settings,
+            });
+        info
+    }
+
+    pub fn get_state(&self) -> BoardState {
+        BoardState {
+            name: self.board_name.clone(),
+            plants: self.plants.iter().map(|x| self.get_plant_info(x)).collect(),
+            care_profiles: self.care_profiles.clone(),
+            time: Some(self.now()),
+            sntp_server: Some(self.sntp_server.clone()),
+            metadata: self.metadata.clone(),
+        }
+    }
+    pub fn plants_iter_mut(&mut self) -> std::slice::IterMut<'_, Plant> {
+        self.plants.iter_mut()
+    }
+
+    pub fn create_plant(&mut self, settings: PlantSettings) -> Result<(), ErrStatus> {
+        check_name(&settings.name)?;
+        check_pot_volume(settings.pot_volume)?;
+        self.check_connector(&settings.connection, None)?;
+        self.check_care_profile(&settings.care_profile)?;
+
+        let plant = PlantData {
+            id: self.next_id,
+            name: settings.name,
+            calibration: settings.connection.default_calibration(),
+            connection: settings.connection,
+            soil: settings.soil,
+            care_profile: settings.care_profile,
+            pot_volume: settings.pot_volume,
+            actuators: settings.actuators,
+            controller: settings.controller,
+        };
+        self.check_actuators(&plant)?;
+        check_controller(&plant)?;
+        // the id is stored first, so it is never given out twice, even if storing the plant fails
+        self.nvs.set_u16("next_id", self.next_id + 1)?;
+        self.next_id += 1;
+        self.store_plant(self.plants.len(), &plant)?;
+        self.nvs
+            .set_u16("plant_count", self.plants.len() as u16 + 1)?;
+        self.plants.push(plant.into());
+        self.publish_state();
+        Ok(())
+    }
+
+    /// Writes a plant to the NVS slot `index`.
+    fn store_plant(&mut self, index: usize, plant: &PlantData) -> Result<(), ErrStatus> {
+        let key = format!("plant_{}", index);
+        let buf: &mut [u8] = &mut [0; PLANT_BUFFER_SIZE];
+        let buf = postcard::to_slice(plant, buf)
+            .map_err(|e| ErrStatus::StorageWriteFailed(e.to_string()))?;
+        self.nvs.set_raw(&key, buf)?;
+        Ok(())
+    }
+
+    #[must_use]
+    fn get_index(&self, id: u16) -> Option<usize> {
+        //maybe add a hashset to the struct
+        for (i, plant) in self.plants.iter().enumerate() {
+            if plant.info.id == id {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    fn find_plant(&self, id: u16) -> Result<usize, ErrStatus> {
+        self.get_index(id).ok_or(ErrStatus::PlantNotFound(id))
+    }
+
+    /// Checks that a connector can be read and is not used by another plant than `plant`.
+    fn check_connector(&self, connector: &Connector, plant: Option<u16>) -> Result<(), ErrStatus> {
+        if !pins::is_readable(connector) {
+            return Err(match *connector {
+                Connector::GPIO(pin) => ErrStatus::PinNotAdcCapable {
+                    pin,
+                    capability: pins::capability(pin),
+                },
+                Connector::I2c { .. } => ErrStatus::InvalidConnector(connector.clone()),
+            });
+        }
+        let others = self.plants.iter().filter(|p| Some(p.info.id) != plant);
+        for other in others {
+            if other.info.connection.conflicts_with(connector) {
+                return Err(ErrStatus::ConnectorInUse {
+                    connector: other.info.connection.clone(),
+                    plant: other.info.id,
+                });
+            }
+            // the outputs turn actuator and flow sensor pins into digital pins
+            if let Connector::GPIO(pin) = *connector {
+                if other.info.actuator_pins().any(|used| used == pin) {
+                    return Err(ErrStatus::ConnectorInUse {
+                        connector: connector.clone(),
+                        plant: other.info.id,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the actuators of a plant, their pins must not be used by its sensor or another plant.
+    fn check_actuators(&self, plant: &PlantData) -> Result<(), ErrStatus> {
+        if plant.actuators.len() > MAX_ACTUATORS {
+            return Err(ErrStatus::TooManyActuators);
+        }
+        let mut used: Vec<(u8, u16)> = self
+            .plants
+            .iter()
+            .filter(|p| p.info.id != plant.id)
+            .flat_map(|p| p.info.actuator_pins().map(move |pin| (pin, p.info.id)))
+            .collect();
+        used.extend(self.plants.iter().filter_map(|p| match p.info.connection {
+            Connector::GPIO(pin) if p.info.id != plant.id => Some((pin, p.info.id)),
+            _ => None,
+        }));
+        if let Connector::GPIO(pin) = plant.connection {
+            used.push((pin, plant.id));
+        }
+        for actuator in &plant.actuators {
+            let flow_sensor = actuator.flow_sensor.as_ref();
+            if actuator.flow_rate.is_some_and(|rate| !positive(rate))
+                || flow_sensor.is_some_and(|sensor| !positive(sensor.pulses_per_litre))
+            {
+                return Err(ErrStatus::BadRequest);
+            }
+            let pins = std::iter::once((actuator.pin, pins::is_switchable(actuator.pin)))
+                .chain(flow_sensor.map(|sensor| (sensor.pin, pins::is_countable(sensor.pin))));
+            for (pin, usable) in pins {
+                if !usable {
+                    return Err(ErrStatus::PinNotSwitchable {
+                        pin,
+                        capability: pins::capability(pin),
+                    });
+                }
+                if let Some((_, other)) = used.iter().find(|(used, _)| *used == pin) {
+                    return Err(ErrStatus::ConnectorInUse {
+                        connector: Connector::GPIO(pin),
+                        plant: *other,
+                    });
+                }
+                used.push((pin, plant.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// All GPIOs and the I2C connectors in use.
+    pub fn get_connectors(&self) -> Vec<ConnectorInfo> {
+        let used_by = |connector: &Connector| {
+            self.plants
+                .iter()
+                .find(|p| match *connector {
+                    Connector::GPIO(pin) if p.info.actuator_pins().any(|used| used == pin) => true,
+                    _ => &p.info.connection == connector,
+                })
+                .map(|p| p.info.id)
+        };
+        let gpios = (0..pins::GPIO_COUNT).map(|pin| {
+            let connector = Connector::GPIO(pin);
+            let status = match used_by(&connector) {
+                Some(id) => ConnectorStatus::Used(id),
+                None if pins::is_readable(&connector) => ConnectorStatus::Free,
+                None => ConnectorStatus::Unsupported,
+            };
+            ConnectorInfo {
+                connector,
+                capability: Some(pins::capability(pin)),
+                status,
+            }
+        });
+        let i2c = self
+            .plants
+            .iter()
+            .filter(|p| matches!(p.info.connection, Connector::I2c { .. }))
+            .map(|p| ConnectorInfo {
+                connector: p.info.connection.clone(),
+                capability: None,
+                status: ConnectorStatus::Used(p.info.id),
+            });
+        gpios.chain(i2c).collect()
+    }
+
+    /// Applies all fields of `update` that are set, nothing is changed if one of them is invalid.
+    ///
+    /// Moving a plant to a different kind of connector resets its calibration and measurements.
+    pub fn update_plant(&mut self, id: u16, update: PlantUpdate) -> Result<(), ErrStatus> {
+        let index = self.find_plant(id)?;
+        if let Some(name) = &update.name {
+            check_name(name)?;
+        }
+        if let Some(profile) = &update.care_profile {
+            self.check_care_profile(profile)?;
+        }
+        let mut info = self.plants[index].info.clone();
+        let mut reconnected = false;
+        if let Some(connection) = update.connection {
+            if connection != info.connection {
+                self.check_connector(&connection, Some(id))?;
+                if std::mem::discriminant(&connection) != std::mem::discriminant(&info.connection) {
+                    info.calibration = connection.default_calibration();
+                }
+                info.connection = connection;
+                reconnected = true;
+            }
+        }
+        if let Some(name) = update.name {
+            info.name = name;
+        }
+        if let Some(soil) = update.soil {
+            info.soil = soil;
+        }
+        if let Some(pot_volume) = update.pot_volume {
+            check_pot_volume(pot_volume)?;
+            info.pot_volume = pot_volume;
+        }
+        if let Some(profile) = update.care_profile {
+            info.care_profile = profile;
+        }
+        let old_actuators = self.plants[index].info.actuators.clone();
+        if let Some(actuators) = update.actuators {
+            info.actuators = actuators;
+        }
+        if reconnected || info.actuators != old_actuators {
+            self.check_actuators(&info)?;
+        }
+        if let Some(controller) = update.controller {
+            info.controller = controller;
+        }
+        let old_controller = self.plants[index].info.controller.clone();
+        check_controller(&info)?;
+        self.store_plant(index, &info)?;
+        if info.actuators != old_actuators {
+            for actuator in &old_actuators {
+                self.runs.stop(actuator.pin);
+            }
+        }
+        let plant = &mut self.plants[index];
+        if info.controller != old_controller || info.actuators != old_actuators {
+            let actuator = |controller: &Option<ControllerSettings>, actuators: &[Actuator]| {
+                controller
+                    .as_ref()
+                    .and_then(|c| actuators.get(c.actuator as usize).cloned())
+            };
+            let actuator_changed = actuator(&info.controller, &info.actuators)
+                != actuator(&old_controller, &old_actuators);
+            plant.controller.settings_changed(actuator_changed);
+        }
+        plant.info = info;
+        if reconnected {
+            plant.measured_values.clear();
+            plant.temperature = None;
+            plant.measured_at = None;
+            plant.history.clear();
+            plant.watering = Default::default();
+        }
+        self.publish_state();
+        Ok(())
+    }
+
+    pub fn delete_plant(&mut self, id: u16) -> Result<(), ErrStatus> {
+        let index = self.find_plant(id)?;
+        let last = self.plants.len() - 1;
+        //store the last plant at the index of the deleted plant
+        if index != last {
+            self.store_plant(index, &self.plants[last].info.clone())?;
+        }
+        self.nvs.set_u16("plant_count", last as u16)?;
+        let plant = self.plants.swap_remove(index);
+        for actuator in &plant.info.actuators {
+            self.runs.stop(actuator.pin);
+        }
+        if let Err(e) = PlantHistory::remove(&mut self.nvs, id) {
+            error!("Cannot remove the history of plant {}: {}.", id, e);
+        }
+        self.publish_state();
+        Ok(())
+    }
+
+    /// Writes the histories that changed since they were last stored.
+    pub fn store_history(&mut self) {
+        for plant in self.plants.iter_mut() {
+            if let Err(e) = plant.history.store(&mut self.nvs, plant.info.id) {
+                error!(
+                    "Cannot store the history of plant {}: {}.",
+                    plant.info.id, e
+                );
+            }
+        }
+    }
+
+    /// Records a watering found by the measuring loop, a failure to store it is only logged.
+    pub fn record_watering(&mut self, id: u16, watering: Watering) {
+        let Some(index) = self.get_index(id) else {
+            return;
+        };
+        let moisture = self.get_plant_info(&self.plants[index]).measured_moisture;
+        let before = moisture.percent_of(watering.before);
+        let after = moisture.percent_of(watering.after);
+        let amount_ml =
+            moisture
+                .pot_volume
+                .zip(before.zip(after))
+                .map(|(volume, (before, after))| {
+                    volume * moisture.soil.water_capacity() * (after - before).max(0.0) / 100.0
+                });
+        info!("Plant {} was watered, about {:?} ml", id, amount_ml);
+        let kind = PlantEventKind::Watered {
+            moisture_before: before,
+            moisture_after: after,
+            amount_ml,
+        };
+        self.record_event(id, watering.time, kind);
+    }
+
+    /// Adds an event to the log, a failure to store it is only logged.
+    fn record_event(&mut self, id: u16, time: Timestamp, kind: PlantEventKind) {
+        if let Err(e) = self.events.push(&mut self.nvs, id, time, kind) {
+            error!("Cannot store an event of plant {}: {}.", id, e);
+        }
+        self.publish_event();
+    }
+
+    /// Lets the watering controllers react to the latest readings of their plants.
+    pub fn run_controllers(&mut self) {
+        let now = self.now();
+        let uptime = self.clock.uptime();
+        let mut steps = vec![];
+        for plant in self.plants.iter() {
+            let Some(settings) = plant.info.controller.clone() else {
+                continue;
+            };
+            let mut moisture = PlantInfo::from(plant).measured_moisture;
+            moisture.profile = plant.info.care_profile.resolve(&self.care_profiles);
+            let age = plant.measured_at.and_then(|t| t.age_at(&now));
+            let fresh = age.is_some_and(|age| age <= MAX_READING_AGE);
+            steps.push((plant.info.id, settings, moisture, fresh));
+        }
+        for (id, settings, moisture, fresh) in steps {
+            let Some(index) = self.get_index(id) else {
+                continue;
+            };
+            let controller = &mut self.plants[index].controller;
+            match controller.update(&settings, &moisture, fresh, uptime) {
+                Some(Step::Record(kind)) => {
+                    info!("Controller of plant {}: {:?}", id, kind);
+                    self.record_event(id, now, kind);
+                }
+                Some(Step::Water {
+                    amount_ml,
+                    moisture,
+                }) => {
+                    if !settings.dry_run {
+                        let request = RunRequest::Millilitres(amount_ml);
+                        if let Err(e) = self.start_run(id, settings.actuator, request) {
+                            debug!("Controller of plant {} can't water: {}", id, e);
+                            continue;
+                        }
+                    }
+                    self.plants[index].controller.watered(
+                        amount_ml,
+                        moisture,
+                        settings.dry_run,
+                        uptime,
+                    );
+                    let kind = PlantEventKind::Pumped {
+                        amount_ml,
+                        moisture_before: moisture,
+                        dry_run: settings.dry_run,
+                    };
+                    self.record_event(id, now, kind);
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Lets the controller of a plant water again after a lockout.
+    pub fn reset_controller(&mut self, id: u16) -> Result<(), ErrStatus> {
+        let index = self.find_plant(id)?;
+        if self.plants[index].info.controller.is_none() {
+            return Err(ErrStatus::NoController(id));
+        }
+        if self.plants[index].controller.reset() {
+            let now = self.now();
+            self.record_event(id, now, PlantEventKind::ControllerReset);
+        }
+        self.publish_plant(id);
+        Ok(())
+    }
+
+    /// Records that someone watered a plant by hand.
+    pub fn record_manual_watering(
+        &mut self,
+        id: u16,
+        watering: ManualWatering,
+    ) -> Result<(), ErrStatus> {
+        self.find_plant(id)?;
+        if watering
+            .amount_ml
+            .is_some_and(|amount| !amount.is_finite() || amount <= 0.0)
+        {
+            return Err(ErrStatus::BadRequest);
+        }
+        let now = self.now();
+        let kind = PlantEventKind::WateredByHand {
+            amount_ml: watering.amount_ml,
+        };
+        let stored = self.events.push(&mut self.nvs, id, now, kind);
+        // the event is kept in memory even if it wasn't stored
+        self.publish_event();
+        stored?;
+        Ok(())
+    }
+
+    pub fn get_events(&self, query: &EventQuery) -> Vec<PlantEvent> {
+        self.events.query(query)
+    }
+
+    /// Runs an actuator of a plant for the requested time or volume, within the safety limits.
+    pub fn start_run(&mut self, id: u16, index: u8, request: RunRequest) -> Result<(), ErrStatus> {
+        let actuator = self.find_actuator(id, index)?;
+        let (RunRequest::Seconds(amount) | RunRequest::Millilitres(amount)) = request;
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err(ErrStatus::BadRequest);
+        }
+        let (seconds, flow) = match request {
+            RunRequest::Seconds(seconds) => (seconds, None),
+            RunRequest::Millilitres(ml) => match (&actuator.flow_sensor, actuator.flow_rate) {
+                // the sensor measures the volume, the time only limits the run
+                (Some(sensor), _) => {
+                    let pulses = (ml / 1000.0 * sensor.pulses_per_litre).ceil() as u32;
+                    let target = FlowTarget::new(sensor.pin, pulses);
+                    (MAX_RUN_SECONDS as f32, Some(target))
+                }
+                (None, Some(rate)) => (ml / rate, None),
+                (None, None) => {
+                    return Err(ErrStatus::FlowUnknown {
+                        plant: id,
+                        actuator: index,
+                    })
+                }
+            },
+        };
+        if seconds > MAX_RUN_SECONDS as f32 {
+            return Err(ErrStatus::RunTooLong { seconds });
+        }
+        let duration = Duration::from_secs_f32(seconds);
+        let uptime = self.clock.uptime();
+        self.runs
+            .start(&actuator, duration, flow, uptime, id, index)?;
+        info!("Running actuator {} of plant {}: {:?}", index, id, request);
+        self.publish_plant(id);
+        Ok(())
+    }
+
+    /// Stops an actuator of a plant, it is fine if it isn't running.
+    pub fn stop_run(&mut self, id: u16, index: u8) -> Result<(), ErrStatus> {
+        let actuator = self.find_actuator(id, index)?;
+        self.runs.stop(actuator.pin);
+        self.publish_plant(id);
+        Ok(())
+    }
+
+    fn find_actuator(&self, id: u16, index: u8) -> Result<Actuator, ErrStatus> {
+        let plant = &self.plants[self.find_plant(id)?];
+        plant
+            .info
+            .actuators
+            .get(index as usize)
+            .cloned()
+            .ok_or(ErrStatus::ActuatorNotFound {
+                plant: id,
+                actuator: index,
+            })
+    }
+
+    /// Switches the actuators on and off, `elapsed` is the time since the last call.
+    pub fn drive_actuators<O: OutputPins>(&mut self, outputs: &mut O, elapsed: Duration) {
+        let actuators: Vec<Actuator> = self
+            .plants
+            .iter()
+            .flat_map(|p| p.info.actuators.iter().cloned())
+            .collect();
+        self.runs.drive(outputs, &actuators, elapsed);
+    }
+
+    /// The measurements of a plant between `query.from` and `query.to`.
+    pub fn get_history(&self, id: u16, query: HistoryQuery) -> Result<History, ErrStatus> {
+        let plant = &self.plants[self.find_plant(id)?];
+        let from = query.from.unwrap_or(0);
+        let to = query.to.unwrap_or(u64::MAX);
+        if from > to {
+            return Err(ErrStatus::BadRequest);
+        }
+        let resolution = query
+            .resolution
+            .unwrap_or_else(|| plant.history.resolution_for(from));
+        Ok(History {
+            plant: id,
+            resolution,
+            points: plant.history.points(resolution, from, to),
+        })
+    }
+
+    /// Uses the current averaged reading of a plant as the dry or wet reference of its calibration.
+    ///
+    /// Intermediate calibration points are dropped, as they don't match the new references.
+    pub fn capture_calibration(
+        &mut self,
+        id: u16,
+        reference: CalibrationReference,
+    ) -> Result<CalibrationCheck, ErrStatus> {
+        let index = self.find_plant(id)?;
+        let value = self.plants[index]
+            .average_of_last(CALIBRATION_SAMPLES)
+            .ok_or(ErrStatus::NoMeasurements(id))?;
+        let mut info = self.plants[index].info.clone();
+        info.calibration = match reference {
+            CalibrationReference::Dry => {
+                Calibration::two_point(value, info.calibration.wet_voltage)
+            }
+            CalibrationReference::Wet => {
+                Calibration::two_point(info.calibration.dry_voltage, value)
+            }
+        };
+        info!(
+            "Captured {:?} reference of plant {} as {} mV",
+            reference, id, value
+        );
+        let check = info.calibration.check(&info.connection);
+        self.store_plant(index, &info)?;
+        self.plants[index].info = info;
+        self.publish_state();
+        Ok(check)
+    }
+
+    #[must_use]
+    fn get_profile_index(&self, id: u16) -> Option<usize> {
+        self.care_profiles.iter().position(|p| p.id == id)
+    }
+
+    fn check_care_profile(&self, profile: &CareProfileId) -> Result<(), ErrStatus> {
+        match profile {
+            CareProfileId::Custom(id) if self.get_profile_index(*id).is_none() => {
+                Err(ErrStatus::CareProfileNotFound(*id))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes a care profile to the NVS slot `index`.
+    fn store_profile(
+        &mut self,
+        index: usize,
+        profile: &CustomCareProfile,
+    ) -> Result<(), ErrStatus> {
+        let key = format!("profile_{}", index);
+        let buf: &mut [u8] = &mut [0; PLANT_BUFFER_SIZE];
+        let buf = postcard::to_slice(profile, buf)
+            .map_err(|e| ErrStatus::StorageWriteFailed(e.to_string()))?;
+        self.nvs.set_raw(&key, buf)?;
+        Ok(())
+    }
+
+    pub fn create_care_profile(&mut self, profile: CareProfile) -> Result<(), ErrStatus> {
+        check_name(&profile.name)?;
+        let profile = CustomCareProfile {
+            id: self.next_profile_id,
+            profile,
+        };
+        self.nvs
+            .set_u16("next_profile_id", self.next_profile_id + 1)?;
+        self.next_profile_id += 1;
+        self.store_profile(self.care_profiles.len(), &profile)?;
+        self.nvs
+            .set_u16("profile_count", self.care_profiles.len() as u16 + 1)?;
+        self.care_profiles.push(profile);
+        self.publish_state();
+        Ok(())
+    }
+
+    /// Deletes a custom care profile, fails if it is still used by a plant.
+    pub fn delete_care_profile(&mut self, id: u16) -> Result<(), ErrStatus> {
+        let index = self
+            .get_profile_index(id)
+            .ok_or(ErrStatus::CareProfileNotFound(id))?;
+        if let Some(plant) = self
+            .plants
+            .iter()
+            .find(|p| p.info.care_profile == CareProfileId::Custom(id))
+        {
+            return Err(ErrStatus::CareProfileInUse {
+                profile: id,
+                plant: plant.info.id,
+            });
+        }
+        let last = self.care_profiles.len() - 1;
+        if index != last {
+            self.store_profile(index, &self.care_profiles[last].clone())?;
+        }
+        self.nvs.set_u16("profile_count", last as u16)?;
+        self.care_profiles.swap_remove(index);
+        self.publish_state();
+        Ok(())
+    }
+
+    /// Receives the changes of the board from now on, see [`LiveUpdate`].
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveUpdate> {
+        self.live.subscribe()
+    }
+
+    /// Sends the plants whose reading or status changed to the live stream, after they were
+    /// measured.
+    ///
+    /// Readings are compared in whole percent, so the noise of the sensors doesn't send every
+    /// plant every second.
+    pub fn publish_readings(&mut self) {
+        if self.live.receiver_count() == 0 {
+            self.live_summaries.clear();
+            return;
+        }
+        let uptime = self.clock.uptime();
+        for plant in &self.plants {
+            let summary = LiveSummary::of(plant, &self.runs, uptime);
+            if self.live_summaries.get(&plant.info.id) != Some(&summary) {
+                self.live_summaries.insert(plant.info.id, summary);
+                self.publish(|db| LiveUpdate::Plant(db.get_plant_info(plant)));
+            }
+        }
+    }
+
+    fn publish_state(&self) {
+        self.publish(|db| LiveUpdate::State(db.get_state()));
+    }
+
+    fn publish_plant(&self, id: u16) {
+        if let Some(index) = self.get_index(id) {
+            self.publish(|db| LiveUpdate::Plant(db.get_plant_info(&db.plants[index])));
+        }
+    }
+
+    fn publish_event(&self) {
+        if let Some(event) = self.events.last() {
+            self.publish(|_| LiveUpdate::Event(event.clone()));
+        }
+    }
+
+    /// The update is only put together if someone listens, most of the time no one does.
+    fn publish(&self, update: impl FnOnce(&Self) -> LiveUpdate) {
+        if self.live.receiver_count() > 0 {
+            // fails only if the last client went away meanwhile
+            let _ = self.live.send(update(self));
+        }
+    }
+}
+
+/// What a client shows of a plant at a glance, the plant is sent again once it changes.
+#[derive(Debug, PartialEq)]
+struct LiveSummary {
+    percent: Option<i32>,
+    /// In tenths of a degree.
+    temperature: Option<i32>,
+    running: Vec<bool>,
+    controller: Option<Discriminant<ControllerStatus>>,
+}
+
+impl LiveSummary {
+    fn of(plant: &Plant, runs: &ActuatorRuns, uptime: u64) -> LiveSummary {
+        let reading = plant.average_of_last(plant.measured_values.len());
+        LiveSummary {
+            percent: reading
+                .and_then(|reading| plant.info.calibration.percent(reading))
+                .map(|percent| percent.round() as i32),
+            temperature: plant.temperature.map(|t| (t * 10.0).round() as i32),
+            running: plant
+                .info
+                .actuators
+                .iter()
+                .map(|actuator| runs.is_running(actuator.pin))
+                .collect(),
+            controller: plant
+                .info
+                .controller
+                .as_ref()
+                .map(|settings| std::mem::discriminant(&plant.controller.status(settings, uptime))),
+        }
+    }
+}
+
+/// Reads a string stored as UTF-8, `default` if there is none or it can't be read.
+fn read_string<K: KeyValueStore>(nvs: &K, key: &str, default: &str) -> String {
+    let buf: &mut [u8] = &mut [0; PLANT_BUFFER_SIZE];
+    match nvs.get_raw(key, buf) {
+        Ok(Some(value)) => String::from_utf8_lossy(value).into_owned(),
+        Ok(None) => default.to_string(),
+        Err(e) => {
+            error!("Cannot read '{}' from NVS: {:?}.", key, e);
+            default.to_string()
+        }
+    }
+}
+
+/// Reads the metadata of the board, it is empty if there is none or it can't be read.
+fn read_metadata<K: KeyValueStore>(nvs: &K) -> BoardMetadata {
+    let buf: &mut [u8] = &mut [0; METADATA_BUFFER_SIZE];
+    match nvs.get_raw("board_meta", buf) {
+        Ok(Some(bytes)) => postcard::from_bytes(bytes).unwrap_or_else(|e| {
+            error!("Cannot decode the board metadata: {:?}.", e);
+            BoardMetadata::default()
+        }),
+        Ok(None) => BoardMetadata::default(),
+        Err(e) => {
+            error!("Cannot read the board metadata from NVS: {:?}.", e);
+            BoardMetadata::default()
+        }
+    }
+}
+
+/// Reads a counter from NVS, initializing it with 0 if it can't be read.
+fn read_u16<K: KeyValueStore>(nvs: &mut K, key: &str) -> u16 {
+    match nvs.get_u16(key) {
+        Ok(Some(v)) => {
+            info!("Read '{}' as {}", key, v);
+            v
+        }
+        Ok(None) => {
+            error!("Read '{}' as 'None' from NVS.", key);
+            if let Err(e) = nvs.set_u16(key, 0) {
+                error!("Cannot initialize '{}': {}.", key, e);
+            }
+            0
+        }
+        Err(e) => {
+            error!("Cannot read '{}' from NVS: {:?}.", key, e);
+            if let Err(e) = nvs.set_u16(key, 0) {
+                error!("Cannot initialize '{}': {}.", key, e);
+            }
+            0
+        }
+    }
+}
+
+fn check_name(name: &str) -> Result<(), ErrStatus> {
+    if name.len() > MAX_NAME_LENGTH {
+        Err(ErrStatus::NameTooLong)
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks that the controller of a plant can water it with the actuator it uses.
+fn check_controller(plant: &PlantData) -> Result<(), ErrStatus> {
+    let Some(controller) = &plant.controller else {
+        return Ok(());
+    };
+    let actuator =
+        plant
+            .actuators
+            .get(controller.actuator as usize)
+            .ok_or(ErrStatus::ActuatorNotFound {
+                plant: plant.id,
+                actuator: controller.actuator,
+            })?;
+    if !positive(controller.max_dose_ml) || !positive(controller.daily_budget_ml) {
+        return Err(ErrStatus::BadRequest);
+    }
+    match (&actuator.flow_sensor, actuator.flow_rate) {
+        (Some(_), _) => Ok(()),
+        (None, Some(rate)) if controller.max_dose_ml / rate > MAX_RUN_SECONDS as f32 => {
+            Err(ErrStatus::RunTooLong {
+                seconds: controller.max_dose_ml / rate,
+            })
+        }
+        (None, Some(_)) => Ok(()),
+        (None, None) => Err(ErrStatus::FlowUnknown {
+            plant: plant.id,
+            actuator: controller.actuator,
+        }),
+    }
+}
+
+fn positive(value: f32) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+/// The water estimates are derived from the volume, unknown volumes are fine.
+fn check_pot_volume(volume: Option<f32>) -> Result<(), ErrStatus> {
+    if volume.is_some_and(|volume| !positive(volume)) {
+        Err(ErrStatus::BadRequest)
+    } else {
+        Ok(())
+    }
+}
+
+fn check_host(host: &str) -> Result<(), ErrStatus> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == ':';
+    if host.is_empty() || host.len() > MAX_HOST_LENGTH || !host.chars().all(valid) {
+        Err(ErrStatus::InvalidHost(host.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plant_common::{FlowSensor, PinCapability, SoilType, Timestamp};
+
+    use super::*;
+    use crate::host::MemoryStore;
+
+    fn plant(name: &str, pin: u8) -> PlantSettings {
+        PlantSettings {
+            name: name.to_string(),
+            connection: Connector::GPIO(pin),
+            ..Default::default()
+        }
+    }
+
+    fn db_with_plant() -> PlantDB<MemoryStore> {
+        let mut db = PlantDB::new(MemoryStore::new());
+        db.create_plant(plant("Basil", 32)).unwrap();
+        db
+    }
+
+    fn profile(profile: CareProfileId) -> PlantUpdate {
+        PlantUpdate {
+            care_profile: Some(profile),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn plants_survive_restart() {
+        let mut db = db_with_plant();
+        db.create_plant(plant("Fern", 33)).unwrap();
+        let update = PlantUpdate {
+            soil: Some(SoilType::Bark),
+            pot_volume: Some(Some(750.0)),
+            ..Default::default()
+        };
+        db.update_plant(0, update).unwrap();
+        db.update_board(BoardUpdate {
+            name: Some("Kitchen".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let db = PlantDB::new(db.nvs);
+        let plants = db.get_plants();
+        assert_eq!(plants.len(), 2);
+        assert_eq!(plants[0].info.name, "Basil");
+        assert_eq!(plants[0].info.soil, SoilType::Bark);
+        assert_eq!(plants[0].info.pot_volume, Some(750.0));
+        assert_eq!(plants[1].info.connection, Connector::GPIO(33));
+        assert_eq!(db.next_id, 2);
+        assert_eq!(db.get_name(), "Kitchen");
+    }
+
+    #[test]
+    fn board_metadata_survives_restart() {
+        let mut db = PlantDB::new(MemoryStore::new());
+        db.set_default_name("Board 3A4F");
+        assert_eq!(db.get_name(), "Board 3A4F");
+        db.update_board(BoardUpdate {
+            location: Some("Kitchen".to_string()),
+            description: Some("Windowsill above the sink".to_string()),
+            color: Some(Some([40, 160, 80])),
+            icon: Some(Some("🌿".to_string())),
+            ..Default::default()
+        })
+        .unwrap();
+        let invalid = [
+            BoardUpdate {
+                description: Some("x".repeat(MAX_DESCRIPTION_LENGTH + 1)),
+                ..Default::default()
+            },
+            BoardUpdate {
+                icon: Some(Some(String::new())),
+                ..Default::default()
+            },
+            BoardUpdate {
+                name: Some("Hall".to_string()),
+                location: Some("x".repeat(MAX_NAME_LENGTH + 1)),
+                ..Default::default()
+            },
+        ];
+        for update in invalid {
+            assert!(db.update_board(update).is_err());
+        }
+        db.update_board(BoardUpdate {
+            color: Some(None),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut db = PlantDB::new(db.nvs);
+        let metadata = db.get_state().metadata;
+        assert_eq!(metadata.location, "Kitchen");
+        assert_eq!(metadata.description, "Windowsill above the sink");
+        assert_eq!(metadata.color, None);
+        assert_eq!(metadata.icon.as_deref(), Some("🌿"));
+        // the name wasn't changed by the invalid update
+        assert_eq!(db.get_name(), DEFAULT_BOARD_NAME);
+        db.update_board(BoardUpdate {
+            name: Some("Kitchen board".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        db.set_default_name("Board 3A4F");
+        assert_eq!(db.get_name(), "Kitchen board");
+    }
+
+    #[test]
+    fn events_survive_restart() {
+        let mut db = db_with_plant();
+        let watering = Watering {
+            time: Timestamp {
+                seconds: 1_700_000_000,
+                synced: true,
+            },
+            before: 2500.0,
+            after: 1000.0,
+        };
+        db.record_watering(0, watering.clone());
+        // waterings of unknown plants are ignored
+        db.record_watering(7, watering);
+
+        let db = PlantDB::new(db.nvs);
+        let events = db.get_events(&EventQuery::default());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].time.seconds, 1_700_000_000);
+        let after = EventQuery {
+            after: Some(events[0].id),
+            ..Default::default()
+        };
+        assert!(db.get_events(&after).is_empty());
+    }
+
+    #[test]
+    fn moving_to_i2c_resets_calibration() {
+        let mut db = db_with_plant();
+        for plant in db.plants_iter_mut() {
+            plant.measured_values.push(1200.0);
+        }
+        let connection = Connector::I2c {
+            bus: 0,
+            address: Connector::SEESAW_ADDRESS,
+            channel: None,
+        };
+        let update = PlantUpdate {
+            name: Some("Thyme".to_string()),
+            connection: Some(connection.clone()),
+            ..Default::default()
+        };
+        db.update_plant(0, update).unwrap();
+
+        let plant = db.get_plant(0).unwrap();
+        assert_eq!(plant.name, "Thyme");
+        assert_eq!(
+            plant.measured_moisture.calibration,
+            connection.default_calibration()
+        );
+        assert_eq!(plant.measured_moisture.measured_voltage, None);
+        assert!(db.update_plant(1, PlantUpdate::default()).is_err());
+    }
+
+    #[test]
+    fn invalid_plants_are_rejected() {
+        let mut db = db_with_plant();
+        assert_eq!(
+            db.create_plant(plant("Fern", 32)),
+            Err(ErrStatus::ConnectorInUse {
+                connector: Connector::GPIO(32),
+                plant: 0
+            })
+        );
+        assert_eq!(
+            db.create_plant(plant("Fern", 25)),
+            Err(ErrStatus::PinNotAdcCapable {
+                pin: 25,
+                capability: PinCapability::I2c
+            })
+        );
+        assert_eq!(
+            db.create_plant(plant("Fern", 4)),
+            Err(ErrStatus::PinNotAdcCapable {
+                pin: 4,
+                capability: PinCapability::Adc2
+            })
+        );
+        let name = "x".repeat(MAX_NAME_LENGTH + 1);
+        assert_eq!(
+            db.create_plant(plant(&name, 33)),
+            Err(ErrStatus::NameTooLong)
+        );
+        // a plant may keep its own connector
+        let update = PlantUpdate {
+            connection: Some(Connector::GPIO(32)),
+            ..Default::default()
+        };
+        db.update_plant(0, update).unwrap();
+        assert_eq!(db.get_plants().len(), 1);
+    }
+
+    #[test]
+    fn multiplexed_sensors_conflict_with_direct_ones() {
+        let mut db = db_with_plant();
+        let i2c = |channel| PlantSettings {
+            name: "Fern".to_string(),
+            connection: Connector::I2c {
+                bus: 0,
+                address: Connector::SEESAW_ADDRESS,
+                channel,
+            },
+            ..Default::default()
+        };
+        db.create_plant(i2c(Some(0))).unwrap();
+        db.create_plant(i2c(Some(1))).unwrap();
+        assert!(matches!(
+            db.create_plant(i2c(None)),
+            Err(ErrStatus::ConnectorInUse { plant: 1, .. })
+        ));
+        assert!(matches!(
+            db.create_plant(i2c(Some(8))),
+            Err(ErrStatus::InvalidConnector(_))
+        ));
+
+        let connectors = db.get_connectors();
+        assert_eq!(connectors.len(), pins::GPIO_COUNT as usize + 2);
+        assert_eq!(connectors[32].status, ConnectorStatus::Used(0));
+        assert_eq!(connectors[33].status, ConnectorStatus::Free);
+        assert_eq!(connectors[6].status, ConnectorStatus::Unsupported);
+        assert_eq!(connectors[41].status, ConnectorStatus::Used(2));
+    }
+
+    #[test]
+    fn actuator_pins_are_checked() {
+        let mut db = db_with_plant();
+        let pump = |pin| Actuator {
+            pin,
+            ..Default::default()
+        };
+        let with_actuators = |actuators| PlantUpdate {
+            actuators: Some(actuators),
+            ..Default::default()
+        };
+        assert_eq!(
+            db.update_plant(0, with_actuators(vec![pump(34)])),
+            Err(ErrStatus::PinNotSwitchable {
+                pin: 34,
+                capability: PinCapability::Adc1
+            })
+        );
+        assert_eq!(
+            db.update_plant(0, with_actuators(vec![pump(16); MAX_ACTUATORS + 1])),
+            Err(ErrStatus::TooManyActuators)
+        );
+        db.update_plant(0, with_actuators(vec![pump(16)])).unwrap();
+
+        // neither other actuators nor sensors can use the pin
+        let mut fern = plant("Fern", 33);
+        fern.actuators = vec![pump(16)];
+        assert_eq!(
+            db.create_plant(fern.clone()),
+            Err(ErrStatus::ConnectorInUse {
+                connector: Connector::GPIO(16),
+                plant: 0
+            })
+        );
+        fern.actuators = vec![Actuator {
+            flow_sensor: Some(FlowSensor {
+                pin: 32,
+                pulses_per_litre: 450.0,
+            }),
+            ..pump(17)
+        }];
+        assert_eq!(
+            db.create_plant(fern.clone()),
+            Err(ErrStatus::ConnectorInUse {
+                connector: Connector::GPIO(32),
+                plant: 0
+            })
+        );
+        fern.actuators[0].flow_sensor = None;
+        db.create_plant(fern).unwrap();
+        let connectors = db.get_connectors();
+        assert_eq!(connectors[17].status, ConnectorStatus::Used(1));
+    }
+
+    #[test]
+    fn sensors_cannot_use_actuator_pins() {
+        let mut db = db_with_plant();
+        let update = PlantUpdate {
+            actuators: Some(vec![Actuator {
+                pin: 16,
+                flow_sensor: Some(FlowSensor {
+                    pin: 33,
+                    pulses_per_litre: 450.0,
+                }),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        db.update_plant(0, update).unwrap();
+        assert_eq!(
+            db.create_plant(plant("Fern", 33)),
+            Err(ErrStatus::ConnectorInUse {
+                connector: Connector::GPIO(33),
+                plant: 0
+            })
+        );
+        db.create_plant(plant("Fern", 34)).unwrap();
+        let update = PlantUpdate {
+            connection: Some(Connector::GPIO(33)),
+            ..Default::default()
+        };
+        assert_eq!(
+            db.update_plant(1, update),
+            Err(ErrStatus::ConnectorInUse {
+                connector: Connector::GPIO(33),
+                plant: 0
+            })
+        );
+    }
+
+    #[test]
+    fn full_storage_changes_nothing() {
+        let mut db = PlantDB::new(MemoryStore::with_capacity(150));
+        db.create_plant(plant("Basil", 32)).unwrap();
+        assert_eq!(
+            db.create_plant(plant("Fern", 33)),
+            Err(ErrStatus::StorageFull)
+        );
+        assert_eq!(db.get_plants().len(), 1);
+
+        let db = PlantDB::new(db.nvs);
+        assert_eq!(db.get_plants().len(), 1);
+        // the id of the failed plant is not given out again
+        assert_eq!(db.next_id, 2);
+    }
+
+    #[test]
+    fn delete_moves_last_plant() {
+        let mut db = db_with_plant();
+        db.create_plant(plant("Fern", 33)).unwrap();
+        db.create_plant(plant("Cactus", 34)).unwrap();
+        db.delete_plant(0).unwrap();
+        assert!(db.delete_plant(0).is_err());
+
+        let db = PlantDB::new(db.nvs);
+        let names: Vec<_> = db.get_plants().iter().map(|p| &p.info.name).collect();
+        assert_eq!(names, ["Cactus", "Fern"]);
+    }
+
+    #[test]
+    fn pot_volumes_must_be_positive() {
+        let mut db = db_with_plant();
+        for volume in [0.0, -500.0, f32::NAN, f32::INFINITY] {
+            let mut fern = plant("Fern", 33);
+            fern.pot_volume = Some(volume);
+            assert_eq!(db.create_plant(fern), Err(ErrStatus::BadRequest));
+            let update = PlantUpdate {
+                pot_volume: Some(Some(volume)),
+                ..Default::default()
+            };
+            assert_eq!(db.update_plant(0, update), Err(ErrStatus::BadRequest));
+        }
+        let update = PlantUpdate {
+            pot_volume: Some(None),
+            ..Default::default()
+        };
+        db.update_plant(0, update).unwrap();
+        assert_eq!(db.get_plants().len(), 1);
+    }
+
+    #[test]
+    fn unreadable_plant_is_dropped() {
+        let mut db = db_with_plant();
+        db.create_plant(plant("Fern", 33)).unwrap();
+        db.create_plant(plant("Cactus", 34)).unwrap();
+        let mut store = db.nvs;
+        store.set_raw("plant_1", &[0xFF; 16]).unwrap();
+
+        let mut db = PlantDB::new(store);
+        db.create_plant(plant("Aloe", 35)).unwrap();
+        let db = PlantDB::new(db.nvs);
+        let names: Vec<_> = db.get_plants().iter().map(|p| &p.info.name).collect();
+        assert_eq!(names, ["Basil", "Cactus", "Aloe"]);
+    }
+
+    #[test]
+    fn calibration_needs_measurements() {
+        let mut db = db_with_plant();
+        assert!(db
+            .capture_calibration(0, CalibrationReference::Dry)
+            .is_err());
+
+        for plant in db.plants_iter_mut() {
+            plant.measured_values.push(2900.0);
+        }
+        db.capture_calibration(0, CalibrationReference::Dry)
+            .unwrap();
+        for plant in db.plants_iter_mut() {
+            for _ in 0..CALIBRATION_SAMPLES {
+                plant.measured_values.push(800.0);
+            }
+        }
+        let check = db
+            .capture_calibration(0, CalibrationReference::Wet)
+            .unwrap();
+        assert_eq!(check, CalibrationCheck::Plausible);
+        let calibration = &db.get_plants()[0].info.calibration;
+        assert_eq!(calibration.dry_voltage, 2900.0);
+        assert_eq!(calibration.wet_voltage, 800.0);
+    }
+
+    #[test]
+    fn used_care_profile_is_kept() {
+        let mut db = db_with_plant();
+        db.create_care_profile(CareProfile::default()).unwrap();
+        db.update_plant(0, profile(CareProfileId::Custom(0)))
+            .unwrap();
+        assert!(db.delete_care_profile(0).is_err());
+        assert!(db
+            .update_plant(0, profile(CareProfileId::Custom(1)))
+            .is_err());
+
+        db.update_plant(0, profile(CareProfileId::Herb)).unwrap();
+        db.delete_care_profile(0).unwrap();
+        let db = PlantDB::new(db.nvs);
+        assert!(db.get_state().care_profiles.is_empty());
+    }
+}
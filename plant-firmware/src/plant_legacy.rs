@@ -0,0 +1,256 @@
+//! Layouts of [`PlantData`] written by older firmware versions, kept to read old NVS entries.
+//!
+//! Every layout converts into the next newer one, the newest into [`PlantData`].
+
+use plant_common::{Actuator, Calibration, CareProfileId, Connector, SoilType};
+use serde::{Deserialize, Serialize};
+
+use crate::plant::PlantData;
+
+/// Decodes the newest layout first, as older layouts would also accept a prefix of newer entries.
+pub fn decode(bytes: &[u8]) -> postcard::Result<PlantData> {
+    postcard::from_bytes::<PlantData>(bytes)
+        .or_else(|_| postcard::from_bytes::<PlantDataV5>(bytes).map(PlantData::from))
+        .or_else(|_| postcard::from_bytes::<PlantDataV4>(bytes).map(PlantData::from))
+        .or_else(|_| postcard::from_bytes::<PlantDataV3>(bytes).map(PlantData::from))
+        .or_else(|_| postcard::from_bytes::<PlantDataV2>(bytes).map(PlantData::from))
+        .or_else(|_| postcard::from_bytes::<PlantDataV1>(bytes).map(PlantData::from))
+        .or_else(|_| postcard::from_bytes::<PlantDataV0>(bytes).map(PlantData::from))
+}
+
+/// Layout of [`PlantData`] before calibrations were stored.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PlantDataV0 {
+    pub id: u16,
+    pub connection: Connector,
+    pub name: String,
+}
+
+impl From<PlantDataV0> for PlantDataV1 {
+    fn from(plant: PlantDataV0) -> Self {
+        PlantDataV1 {
+            id: plant.id,
+            connection: plant.connection,
+            name: plant.name,
+            calibration: Calibration::default(),
+        }
+    }
+}
+
+/// Layout of [`PlantData`] before the soil type was stored.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PlantDataV1 {
+    pub id: u16,
+    pub connection: Connector,
+    pub name: String,
+    pub calibration: Calibration,
+}
+
+impl From<PlantDataV1> for PlantDataV2 {
+    fn from(plant: PlantDataV1) -> Self {
+        PlantDataV2 {
+            id: plant.id,
+            connection: plant.connection,
+            name: plant.name,
+            calibration: plant.calibration,
+            soil: SoilType::default(),
+        }
+    }
+}
+
+/// Layout of [`PlantData`] before care profiles were stored.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PlantDataV2 {
+    pub id: u16,
+    pub connection: Connector,
+    pub name: String,
+    pub calibration: Calibration,
+    pub soil: SoilType,
+}
+
+impl From<PlantDataV2> for PlantDataV3 {
+    fn from(plant: PlantDataV2) -> Self {
+        PlantDataV3 {
+            id: plant.id,
+            connection: plant.connection,
+            name: plant.name,
+            calibration: plant.calibration,
+            soil: plant.soil,
+            care_profile: CareProfileId::default(),
+        }
+    }
+}
+
+/// Layout of [`PlantData`] before the pot volume was stored.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PlantDataV3 {
+    pub id: u16,
+    pub connection: Connector,
+    pub name: String,
+    pub calibration: Calibration,
+    pub soil: SoilType,
+    pub care_profile: CareProfileId,
+}
+
+impl From<PlantDataV3> for PlantDataV4 {
+    fn from(plant: PlantDataV3) -> Self {
+        PlantDataV4 {
+            id: plant.id,
+            connection: plant.connection,
+            name: plant.name,
+            calibration: plant.calibration,
+            soil: plant.soil,
+            care_profile: plant.care_profile,
+            pot_volume: None,
+        }
+    }
+}
+
+/// Layout of [`PlantData`] before actuators were stored.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PlantDataV4 {
+    pub id: u16,
+    pub connection: Connector,
+    pub name: String,
+    pub calibration: Calibration,
+    pub soil: SoilType,
+    pub care_profile: CareProfileId,
+    pub pot_volume: Option<f32>,
+}
+
+impl From<PlantDataV4> for PlantDataV5 {
+    fn from(plant: PlantDataV4) -> Self {
+        PlantDataV5 {
+            id: plant.id,
+            connection: plant.connection,
+            name: plant.name,
+            calibration: plant.calibration,
+            soil: plant.soil,
+            care_profile: plant.care_profile,
+            pot_volume: plant.pot_volume,
+            actuators: Vec::new(),
+        }
+    }
+}
+
+/// Layout of [`PlantData`] before watering controllers were stored.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PlantDataV5 {
+    pub id: u16,
+    pub connection: Connector,
+    pub name: String,
+    pub calibration: Calibration,
+    pub soil: SoilType,
+    pub care_profile: CareProfileId,
+    pub pot_volume: Option<f32>,
+    pub actuators: Vec<Actuator>,
+}
+
+impl From<PlantDataV5> for PlantData {
+    fn from(plant: PlantDataV5) -> Self {
+        PlantData {
+            id: plant.id,
+            connection: plant.connection,
+            name: plant.name,
+            calibration: plant.calibration,
+            soil: plant.soil,
+            care_profile: plant.care_profile,
+            pot_volume: plant.pot_volume,
+            actuators: plant.actuators,
+            controller: None,
+        }
+    }
+}
+
+impl From<PlantDataV4> for PlantData {
+    fn from(plant: PlantDataV4) -> Self {
+        PlantDataV5::from(plant).into()
+    }
+}
+
+impl From<PlantDataV3> for PlantData {
+    fn from(plant: PlantDataV3) -> Self {
+        PlantDataV4::from(plant).into()
+    }
+}
+
+impl From<PlantDataV2> for PlantData {
+    fn from(plant: PlantDataV2) -> Self {
+        PlantDataV3::from(plant).into()
+    }
+}
+
+impl From<PlantDataV1> for PlantData {
+    fn from(plant: PlantDataV1) -> Self {
+        PlantDataV2::from(plant).into()
+    }
+}
+
+impl From<PlantDataV0> for PlantData {
+    fn from(plant: PlantDataV0) -> Self {
+        PlantDataV1::from(plant).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plant_common::ControllerSettings;
+
+    use super::*;
+
+    #[test]
+    fn decodes_oldest_layout() {
+        let old = PlantDataV0 {
+            id: 3,
+            connection: Connector::GPIO(33),
+            name: "Basil".to_string(),
+        };
+        let plant = decode(postcard::to_slice(&old, &mut [0; 256]).unwrap()).unwrap();
+        assert_eq!(plant.id, 3);
+        assert_eq!(plant.connection, Connector::GPIO(33));
+        assert_eq!(plant.name, "Basil");
+        assert_eq!(plant.soil, SoilType::default());
+        assert_eq!(plant.pot_volume, None);
+    }
+
+    #[test]
+    fn decodes_newest_layout() {
+        let plant = PlantData {
+            id: 1,
+            connection: Connector::GPIO(32),
+            name: "Fern".to_string(),
+            calibration: Calibration::two_point(2800.0, 900.0),
+            soil: SoilType::Peat,
+            care_profile: CareProfileId::Fern,
+            pot_volume: Some(1500.0),
+            actuators: vec![Actuator {
+                pin: 16,
+                active_low: true,
+                ..Default::default()
+            }],
+            controller: Some(ControllerSettings::default()),
+        };
+        let decoded = decode(postcard::to_slice(&plant, &mut [0; 256]).unwrap()).unwrap();
+        assert_eq!(decoded.soil, SoilType::Peat);
+        assert_eq!(decoded.care_profile, CareProfileId::Fern);
+        assert_eq!(decoded.pot_volume, Some(1500.0));
+        assert_eq!(decoded.actuators, plant.actuators);
+        assert_eq!(decoded.controller, plant.controller);
+    }
+
+    #[test]
+    fn decodes_layout_without_actuators() {
+        let old = PlantDataV4 {
+            id: 2,
+            connection: Connector::GPIO(34),
+            name: "Mint".to_string(),
+            calibration: Calibration::default(),
+            soil: SoilType::Bark,
+            care_profile: CareProfileId::default(),
+            pot_volume: Some(800.0),
+        };
+        let plant = decode(postcard::to_slice(&old, &mut [0; 256]).unwrap()).unwrap();
+        assert_eq!(plant.pot_volume, Some(800.0));
+        assert!(plant.actuators.is_empty());
+    }
+}
@@ -0,0 +1,968 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::extract::{Path, Query, Request};
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::{middleware, routing::*, Json, Router};
+use log::warn;
+use plant_common::{
+    BoardState, BoardUpdate, CalibrationRequest, CareProfile, CareProfileRequest, ConnectorInfo,
+    CustomCareProfile, ErrStatus, EventQuery, History, HistoryQuery, LiveUpdate, ManualWatering,
+    OkStatus, PlantEvent, PlantInfo, PlantSettings, PlantUpdate, PotVolumeRequest, Reply,
+    ReplyStatus, RunRequest, SoilRequest, WifiNetwork, WifiStatus,
+};
+use tokio::sync::Mutex;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::hal::KeyValueStore;
+use crate::plant_db::PlantDB;
+
+pub async fn auxum_serve<K: KeyValueStore + 'static>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    addr: SocketAddr,
+) {
+    axum_server::bind(addr)
+        .serve(router(plants).into_make_service())
+        .await
+        .unwrap();
+}
+
+pub fn router<K: KeyValueStore + 'static>(plants: Arc<Mutex<PlantDB<K>>>) -> Router {
+    Router::new()
+        .route(
+            "/state",
+            get({
+                let plants = Arc::clone(&plants);
+                move || get_current_state(plants)
+            }),
+        )
+        .route(
+            "/board",
+            get({
+                let plants = Arc::clone(&plants);
+                move || get_board(plants)
+            })
+            .patch({
+                let plants = Arc::clone(&plants);
+                move |Json(update)| update_board(plants, update)
+            }),
+        )
+        .route(
+            "/plants",
+            get({
+                let plants = Arc::clone(&plants);
+                move || get_plants(plants)
+            })
+            .post({
+                let plants = Arc::clone(&plants);
+                move |Json(settings)| create_plant(plants, settings)
+            }),
+        )
+        .route(
+            "/plants/:id",
+            get({
+                let plants = Arc::clone(&plants);
+                move |Path(id)| get_plant(plants, id)
+            })
+            .put({
+                let plants = Arc::clone(&plants);
+                move |Path(id), Json(settings): Json<PlantSettings>| {
+                    update_plant(plants, id, settings.into())
+                }
+            })
+            .patch({
+                let plants = Arc::clone(&plants);
+                move |Path(id), Json(update)| update_plant(plants, id, update)
+            })
+            .delete({
+                let plants = Arc::clone(&plants);
+                move |Path(id)| delete_plant(plants, id)
+            }),
+        )
+        .route(
+            "/plants/:id/history",
+            get({
+                let plants = Arc::clone(&plants);
+                move |Path(id), Query(query)| get_history(plants, id, query)
+            }),
+        )
+        .route(
+            "/plants/:id/actuators/:actuator/run",
+            post({
+                let plants = Arc::clone(&plants);
+                move |Path((id, actuator)), Json(request)| start_run(plants, id, actuator, request)
+            }),
+        )
+        .route(
+            "/plants/:id/actuators/:actuator/stop",
+            post({
+                let plants = Arc::clone(&plants);
+                move |Path((id, actuator))| stop_run(plants, id, actuator)
+            }),
+        )
+        .route(
+            "/plants/:id/watered",
+            post({
+                let plants = Arc::clone(&plants);
+                move |Path(id), Json(watering)| record_manual_watering(plants, id, watering)
+            }),
+        )
+        .route(
+            "/plants/:id/controller/reset",
+            post({
+                let plants = Arc::clone(&plants);
+                move |Path(id)| reset_controller(plants, id)
+            }),
+        )
+        .route(
+            "/plants/:id/events",
+            get({
+                let plants = Arc::clone(&plants);
+                move |Path(id), Query(query)| get_plant_events(plants, id, query)
+            }),
+        )
+        .route(
+            "/events",
+            get({
+                let plants = Arc::clone(&plants);
+                move |Query(query)| get_events(plants, query)
+            }),
+        )
+        .route(
+            "/live",
+            get({
+                let plants = Arc::clone(&plants);
+                move || get_live(plants)
+            }),
+        )
+        .route(
+            "/connectors",
+            get({
+                let plants = Arc::clone(&plants);
+                move || get_connectors(plants)
+            }),
+        )
+        .route(
+            "/calibrate",
+            post({
+                let plants = Arc::clone(&plants);
+                move |body| calibrate(plants, body)
+            }),
+        )
+        .route(
+            "/create_care_profile",
+            post({
+                let plants = Arc::clone(&plants);
+                move |body| create_care_profile(plants, body)
+            }),
+        )
+        .route(
+            "/delete_care_profile",
+            delete({
+                let plants = Arc::clone(&plants);
+                move |body| delete_care_profile(plants, body)
+            }),
+        )
+        .route(
+            "/wifi",
+            get({
+                let plants = Arc::clone(&plants);
+                move || get_wifi(plants)
+            }),
+        )
+        .route(
+            "/wifi/networks",
+            post({
+                let plants = Arc::clone(&plants);
+                move |Json(network)| add_wifi_network(plants, network)
+            }),
+        )
+        .route(
+            "/wifi/networks/:ssid",
+            delete({
+                let plants = Arc::clone(&plants);
+                move |Path(ssid): Path<String>| remove_wifi_network(plants, ssid)
+            }),
+        )
+        .merge(deprecated_router(Arc::clone(&plants)))
+        .layer(middleware::from_fn(move |request, next| {
+            only_wifi_while_provisioning(Arc::clone(&plants), request, next)
+        }))
+}
+
+/// The access point has no password, so while it is open anyone in range could run the pumps or
+/// change the plants. Only the Wi-Fi can be set up then.
+async fn only_wifi_while_provisioning<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let db = plants.lock().await;
+    if !db.is_provisioning() || path == "/wifi" || path.starts_with("/wifi/") {
+        drop(db);
+        return next.run(request).await;
+    }
+    let state = db.get_state();
+    drop(db);
+    reply(Err(ErrStatus::Provisioning), state).into_response()
+}
+
+/// The routes used before plants became resources, kept for older clients.
+fn deprecated_router<K: KeyValueStore + 'static>(plants: Arc<Mutex<PlantDB<K>>>) -> Router {
+    Router::new()
+        .route(
+            "/create_plant",
+            post({
+                let plants = Arc::clone(&plants);
+                move |Json(plant): Json<PlantInfo>| create_plant(plants, plant.into())
+            }),
+        )
+        .route(
+            "/delete_plant",
+            delete({
+                let plants = Arc::clone(&plants);
+                move |Json(plant): Json<PlantInfo>| delete_plant(plants, plant.id)
+            }),
+        )
+        .route(
+            "/set_soil",
+            post({
+                let plants = Arc::clone(&plants);
+                move |Json(request): Json<SoilRequest>| {
+                    let update = PlantUpdate {
+                        soil: Some(request.soil),
+                        ..Default::default()
+                    };
+                    update_plant(plants, request.id, update)
+                }
+            }),
+        )
+        .route(
+            "/set_pot_volume",
+            post({
+                let plants = Arc::clone(&plants);
+                move |Json(request): Json<PotVolumeRequest>| {
+                    let update = PlantUpdate {
+                        pot_volume: Some(request.pot_volume),
+                        ..Default::default()
+                    };
+                    update_plant(plants, request.id, update)
+                }
+            }),
+        )
+        .route(
+            "/set_care_profile",
+            post({
+                let plants = Arc::clone(&plants);
+                move |Json(request): Json<CareProfileRequest>| {
+                    let update = PlantUpdate {
+                        care_profile: Some(request.profile),
+                        ..Default::default()
+                    };
+                    update_plant(plants, request.id, update)
+                }
+            }),
+        )
+        .route_layer(middleware::map_response(mark_deprecated))
+}
+
+async fn mark_deprecated(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .insert("deprecation", HeaderValue::from_static("true"));
+    response
+}
+
+async fn get_current_state<K: KeyValueStore>(plants: Arc<Mutex<PlantDB<K>>>) -> Json<Reply> {
+    let db = plants.lock().await;
+    let state = db.get_state();
+    drop(db);
+    Json(Reply {
+        status: ReplyStatus::Ok(OkStatus::Empty),
+        detail: None,
+        state,
+    })
+}
+
+/// Builds the reply to a request, errors are sent with a matching HTTP status code.
+fn reply(result: Result<OkStatus, ErrStatus>, state: BoardState) -> (StatusCode, Json<Reply>) {
+    match result {
+        Ok(status) => (
+            StatusCode::OK,
+            Json(Reply {
+                status: ReplyStatus::Ok(status),
+                detail: None,
+                state,
+            }),
+        ),
+        Err(e) => {
+            warn!("Request failed: {}", e);
+            let code =
+                StatusCode::from_u16(e.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            (
+                code,
+                Json(Reply {
+                    detail: Some(e.to_string()),
+                    status: ReplyStatus::Err(e),
+                    state,
+                }),
+            )
+        }
+    }
+}
+
+async fn get_board<K: KeyValueStore>(plants: Arc<Mutex<PlantDB<K>>>) -> Json<BoardState> {
+    Json(plants.lock().await.get_state())
+}
+
+async fn update_board<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    update: BoardUpdate,
+) -> (StatusCode, Json<Reply>) {
+    let mut db = plants.lock().await;
+    let result = db.update_board(update).map(|_| OkStatus::Updated);
+    let state = db.get_state();
+    drop(db);
+    reply(result, state)
+}
+
+async fn get_plants<K: KeyValueStore>(plants: Arc<Mutex<PlantDB<K>>>) -> Json<Vec<PlantInfo>> {
+    Json(plants.lock().await.get_state().plants)
+}
+
+async fn get_plant<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    id: u16,
+) -> Result<Json<PlantInfo>, (StatusCode, Json<Reply>)> {
+    let db = plants.lock().await;
+    db.get_plant(id)
+        .map(Json)
+        .ok_or_else(|| reply(Err(ErrStatus::PlantNotFound(id)), db.get_state()))
+}
+
+async fn get_history<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    id: u16,
+    query: HistoryQuery,
+) -> Result<Json<History>, (StatusCode, Json<Reply>)> {
+    let db = plants.lock().await;
+    db.get_history(id, query)
+        .map(Json)
+        .map_err(|e| reply(Err(e), db.get_state()))
+}
+
+async fn get_events<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    query: EventQuery,
+) -> Json<Vec<PlantEvent>> {
+    Json(plants.lock().await.get_events(&query))
+}
+
+/// Streams the changes of the board, starting with its current state.
+///
+/// The stream ends if the client falls too far behind, it starts over with the state when the
+/// client reconnects. The database is only locked to subscribe.
+async fn get_live<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let (state, updates) = {
+        let db = plants.lock().await;
+        (db.get_state(), db.subscribe())
+    };
+    let updates = BroadcastStream::new(updates).map_while(Result::ok);
+    let stream = tokio_stream::once(LiveUpdate::State(state))
+        .chain(updates)
+        .map(|update| Event::default().json_data(update));
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn get_plant_events<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    id: u16,
+    query: EventQuery,
+) -> Result<Json<Vec<PlantEvent>>, (StatusCode, Json<Reply>)> {
+    let db = plants.lock().await;
+    if db.get_plant(id).is_none() {
+        return Err(reply(Err(ErrStatus::PlantNotFound(id)), db.get_state()));
+    }
+    let query = EventQuery {
+        plant: Some(id),
+        ..query
+    };
+    Ok(Json(db.get_events(&query)))
+}
+
+async fn start_run<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    id: u16,
+    actuator: u8,
+    request: RunRequest,
+) -> (StatusCode, Json<Reply>) {
+    let mut db = plants.lock().await;
+    let result = db
+        .start_run(id, actuator, request)
+        .map(|_| OkStatus::Started);
+    let state = db.get_state();
+    drop(db);
+    reply(result, state)
+}
+
+async fn stop_run<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    id: u16,
+    actuator: u8,
+) -> (StatusCode, Json<Reply>) {
+    let mut db = plants.lock().await;
+    let result = db.stop_run(id, actuator).map(|_| OkStatus::Stopped);
+    let state = db.get_state();
+    drop(db);
+    reply(result, state)
+}
+
+async fn record_manual_watering<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    id: u16,
+    watering: ManualWatering,
+) -> (StatusCode, Json<Reply>) {
+    let mut db = plants.lock().await;
+    let result = db
+        .record_manual_watering(id, watering)
+        .map(|_| OkStatus::Recorded);
+    let state = db.get_state();
+    drop(db);
+    reply(result, state)
+}
+
+async fn reset_controller<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    id: u16,
+) -> (StatusCode, Json<Reply>) {
+    let mut db = plants.lock().await;
+    let result = db.reset_controller(id).map(|_| OkStatus::Updated);
+    let state = db.get_state();
+    drop(db);
+    reply(result, state)
+}
+
+async fn get_wifi<K: KeyValueStore>(plants: Arc<Mutex<PlantDB<K>>>) -> Json<WifiStatus> {
+    Json(plants.lock().await.get_wifi_status())
+}
+
+async fn add_wifi_network<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    network: WifiNetwork,
+) -> (StatusCode, Json<Reply>) {
+    let mut db = plants.lock().await;
+    let result = db.add_wifi_network(network).map(|known| {
+        if known {
+            OkStatus::Updated
+        } else {
+            OkStatus::Created
+        }
+    });
+    let state = db.get_state();
+    drop(db);
+    reply(result, state)
+}
+
+async fn remove_wifi_network<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    ssid: String,
+) -> (StatusCode, Json<Reply>) {
+    let mut db = plants.lock().await;
+    let result = db.remove_wifi_network(&ssid).map(|_| OkStatus::Deleted);
+    let state = db.get_state();
+    drop(db);
+    reply(result, state)
+}
+
+async fn get_connectors<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+) -> Json<Vec<ConnectorInfo>> {
+    Json(plants.lock().await.get_connectors())
+}
+
+async fn create_plant<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    settings: PlantSettings,
+) -> (StatusCode, Json<Reply>) {
+    let mut db = plants.lock().await;
+    let created = db.create_plant(settings).map(|_| OkStatus::Created);
+    let state = db.get_state();
+    drop(db);
+    reply(created, state)
+}
+
+async fn update_plant<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    id: u16,
+    update: PlantUpdate,
+) -> (StatusCode, Json<Reply>) {
+    let mut db = plants.lock().await;
+    let updated = db.update_plant(id, update).map(|_| OkStatus::Updated);
+    let state = db.get_state();
+    drop(db);
+    reply(updated, state)
+}
+
+async fn delete_plant<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    id: u16,
+) -> (StatusCode, Json<Reply>) {
+    let mut db = plants.lock().await;
+    let deleted = db.delete_plant(id).map(|_| OkStatus::Deleted);
+    let state = db.get_state();
+    drop(db);
+    reply(deleted, state)
+}
+
+async fn calibrate<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    request: Json<CalibrationRequest>,
+) -> (StatusCode, Json<Reply>) {
+    let request = request.0;
+    let mut db = plants.lock().await;
+    let calibrated = db
+        .capture_calibration(request.id, request.reference)
+        .map(OkStatus::Calibrated);
+    let state = db.get_state();
+    drop(db);
+    reply(calibrated, state)
+}
+
+async fn create_care_profile<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    request: Json<CareProfile>,
+) -> (StatusCode, Json<Reply>) {
+    let request = request.0;
+    let mut db = plants.lock().await;
+    let result = db.create_care_profile(request).map(|_| OkStatus::Created);
+    let state = db.get_state();
+    drop(db);
+    reply(result, state)
+}
+
+async fn delete_care_profile<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    request: Json<CustomCareProfile>,
+) -> (StatusCode, Json<Reply>) {
+    let request = request.0;
+    let mut db = plants.lock().await;
+    let result = db
+        .delete_care_profile(request.id)
+        .map(|_| OkStatus::Deleted);
+    let state = db.get_state();
+    drop(db);
+    reply(result, state)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{header, Method, Request};
+    use plant_common::{
+        Actuator, Connector, ErrStatus, HistoryResolution, Moisture, PlantEventKind, SoilType,
+    };
+    use serde::de::DeserializeOwned;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::host::{basil, clocked_db, FixedSensors, MemoryStore};
+    use crate::plant::measure_once;
+    use crate::watering::Watering;
+    use crate::wifi::{WifiAction, WifiEvent};
+
+    async fn send(app: &Router, method: Method, uri: &str, body: String) -> Response {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap()
+    }
+
+    async fn read<T: DeserializeOwned>(response: Response) -> T {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn app() -> Router {
+        router(Arc::new(Mutex::new(PlantDB::new(MemoryStore::new()))))
+    }
+
+    fn plant(id: u16) -> String {
+        serde_json::to_string(&PlantInfo {
+            id,
+            name: "Basil".to_string(),
+            measured_moisture: Moisture::default(),
+            connection: Connector::GPIO(32),
+            care_profile: Default::default(),
+            temperature: None,
+            measured_at: None,
+            drying: None,
+            actuators: Vec::new(),
+            controller: None,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn deprecated_routes() {
+        let app = app();
+
+        let response = send(&app, Method::POST, "/create_plant", plant(0)).await;
+        assert_eq!(response.headers()["deprecation"], "true");
+        let reply: Reply = read(response).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Created));
+        assert_eq!(reply.state.plants.len(), 1);
+
+        let body = r#"{"id":0,"soil":"Bark"}"#.to_string();
+        let reply: Reply = read(send(&app, Method::POST, "/set_soil", body).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Updated));
+        assert_eq!(reply.state.plants[0].measured_moisture.soil, SoilType::Bark);
+
+        let reply: Reply = read(send(&app, Method::GET, "/state", String::new()).await).await;
+        assert_eq!(reply.state.plants[0].name, "Basil");
+
+        let reply: Reply = read(send(&app, Method::DELETE, "/delete_plant", plant(0)).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Deleted));
+        let response = send(&app, Method::DELETE, "/delete_plant", plant(0)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let reply: Reply = read(response).await;
+        assert_eq!(reply.status, ReplyStatus::Err(ErrStatus::PlantNotFound(0)));
+        assert_eq!(reply.detail.unwrap(), "There is no plant with id 0");
+        assert!(reply.state.plants.is_empty());
+    }
+
+    #[tokio::test]
+    async fn plant_resources() {
+        let app = app();
+        let body = r#"{"name":"Basil","connection":{"GPIO":32},"pot_volume":500}"#.to_string();
+        let reply: Reply = read(send(&app, Method::POST, "/plants", body).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Created));
+
+        let body = r#"{"name":"Thyme","connection":{"GPIO":4}}"#.to_string();
+        let response = send(&app, Method::PATCH, "/plants/0", body).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let reply: Reply = read(response).await;
+        assert!(matches!(
+            reply.status,
+            ReplyStatus::Err(ErrStatus::PinNotAdcCapable { pin: 4, .. })
+        ));
+        assert_eq!(reply.state.plants[0].name, "Basil");
+
+        let body = r#"{"name":"Thyme","pot_volume":null}"#.to_string();
+        let reply: Reply = read(send(&app, Method::PATCH, "/plants/0", body).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Updated));
+
+        let plant: PlantInfo =
+            read(send(&app, Method::GET, "/plants/0", String::new()).await).await;
+        assert_eq!(plant.id, 0);
+        assert_eq!(plant.name, "Thyme");
+        assert_eq!(plant.measured_moisture.pot_volume, None);
+        let response = send(&app, Method::GET, "/plants/1", String::new()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body = r#"{"name":"Mint","connection":{"GPIO":33}}"#.to_string();
+        let reply: Reply = read(send(&app, Method::PUT, "/plants/0", body).await).await;
+        assert_eq!(reply.state.plants[0].connection, Connector::GPIO(33));
+
+        let reply: Reply = read(send(&app, Method::DELETE, "/plants/0", String::new()).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Deleted));
+        let plants: Vec<PlantInfo> =
+            read(send(&app, Method::GET, "/plants", String::new()).await).await;
+        assert!(plants.is_empty());
+    }
+
+    #[tokio::test]
+    async fn plant_history() {
+        let (db, clock) = clocked_db([basil()]);
+        clock.sync(0);
+        let plants = Arc::new(Mutex::new(db));
+        let app = router(Arc::clone(&plants));
+        let mut sensors = FixedSensors::new();
+        sensors.set(Connector::GPIO(32), 1200.0);
+        for _ in 0..5 {
+            measure_once(&mut sensors, &mut *plants.lock().await).await;
+            clock.advance(60);
+        }
+
+        let uri = "/plants/0/history?from=60&to=180&resolution=minute";
+        let history: History = read(send(&app, Method::GET, uri, String::new()).await).await;
+        let times: Vec<_> = history.points.iter().map(|p| p.time).collect();
+        assert_eq!(times, [60, 120, 180]);
+        assert_eq!(history.points[0].measured_voltage, 1200.0);
+
+        let history: History =
+            read(send(&app, Method::GET, "/plants/0/history", String::new()).await).await;
+        assert_eq!(history.resolution, HistoryResolution::Minute);
+        assert_eq!(history.points.len(), 4);
+
+        let response = send(
+            &app,
+            Method::GET,
+            "/plants/0/history?from=2&to=1",
+            String::new(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = send(&app, Method::GET, "/plants/1/history", String::new()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn plant_events() {
+        let plants = Arc::new(Mutex::new(PlantDB::new(MemoryStore::new())));
+        let app = router(Arc::clone(&plants));
+        for pin in [32, 33] {
+            let body = format!(r#"{{"name":"Basil","connection":{{"GPIO":{}}}}}"#, pin);
+            send(&app, Method::POST, "/plants", body).await;
+        }
+        let watering = Watering {
+            time: plants.lock().await.now(),
+            before: 2500.0,
+            after: 1000.0,
+        };
+        for id in [0, 1, 1] {
+            plants.lock().await.record_watering(id, watering.clone());
+        }
+
+        let events: Vec<PlantEvent> =
+            read(send(&app, Method::GET, "/events?after=0", String::new()).await).await;
+        assert_eq!(events.iter().map(|e| e.id).collect::<Vec<_>>(), [1, 2]);
+        let events: Vec<PlantEvent> =
+            read(send(&app, Method::GET, "/plants/0/events", String::new()).await).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].plant, 0);
+        let response = send(&app, Method::GET, "/plants/5/events", String::new()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn watered_by_hand() {
+        let app = app();
+        let body = r#"{"name":"Basil","connection":{"GPIO":32}}"#.to_string();
+        send(&app, Method::POST, "/plants", body).await;
+        let body = r#"{"amount_ml":250}"#.to_string();
+        let reply: Reply = read(send(&app, Method::POST, "/plants/0/watered", body).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Recorded));
+        let events: Vec<PlantEvent> =
+            read(send(&app, Method::GET, "/events", String::new()).await).await;
+        assert_eq!(
+            events[0].kind,
+            PlantEventKind::WateredByHand {
+                amount_ml: Some(250.0)
+            }
+        );
+        let body = r#"{"amount_ml":-1}"#.to_string();
+        let response = send(&app, Method::POST, "/plants/0/watered", body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = send(&app, Method::POST, "/plants/1/watered", "{}".to_string()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn wifi_provisioning() {
+        let app = app();
+        let status: WifiStatus = read(send(&app, Method::GET, "/wifi", String::new()).await).await;
+        assert!(status.networks.is_empty());
+
+        let body = r#"{"ssid":"Home Wi-Fi","password":"correct horse"}"#.to_string();
+        let reply: Reply =
+            read(send(&app, Method::POST, "/wifi/networks", body.clone()).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Created));
+        let reply: Reply = read(send(&app, Method::POST, "/wifi/networks", body).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Updated));
+        let body = r#"{"ssid":"Guests","password":"short"}"#.to_string();
+        let response = send(&app, Method::POST, "/wifi/networks", body).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = send(&app, Method::GET, "/wifi", String::new()).await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(!text.contains("correct horse"));
+        let status: WifiStatus = serde_json::from_str(&text).unwrap();
+        assert_eq!(status.networks[0].ssid, "Home Wi-Fi");
+        assert!(status.networks[0].secured);
+
+        let uri = "/wifi/networks/Home%20Wi-Fi";
+        let reply: Reply = read(send(&app, Method::DELETE, uri, String::new()).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Deleted));
+        let response = send(&app, Method::DELETE, uri, String::new()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn access_point_only_serves_wifi() {
+        let plants = Arc::new(Mutex::new(PlantDB::new(MemoryStore::new())));
+        let app = router(Arc::clone(&plants));
+        {
+            let mut db = plants.lock().await;
+            db.start_wifi();
+            let WifiAction::OpenAccessPoint(ssid) = db.next_wifi_action() else {
+                panic!("the access point doesn't open without networks");
+            };
+            db.handle_wifi_event(WifiEvent::AccessPointOpened(ssid));
+        }
+        let plant = r#"{"name":"Basil","connection":{"GPIO":32}}"#.to_string();
+        let response = send(&app, Method::POST, "/plants", plant.clone()).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let reply: Reply = read(response).await;
+        assert_eq!(reply.status, ReplyStatus::Err(ErrStatus::Provisioning));
+        let response = send(
+            &app,
+            Method::POST,
+            "/plants/0/actuators/0/run",
+            "{}".to_string(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let body = r#"{"ssid":"Home Wi-Fi","password":"correct horse"}"#.to_string();
+        let reply: Reply = read(send(&app, Method::POST, "/wifi/networks", body).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Created));
+        let status: WifiStatus = read(send(&app, Method::GET, "/wifi", String::new()).await).await;
+        assert_eq!(status.networks.len(), 1);
+
+        plants
+            .lock()
+            .await
+            .handle_wifi_event(WifiEvent::AccessPointClosed);
+        let response = send(&app, Method::POST, "/plants", plant).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn run_actuator() {
+        let pump = Actuator {
+            pin: 16,
+            ..Default::default()
+        };
+        let settings = PlantSettings {
+            actuators: vec![pump],
+            ..basil()
+        };
+        let (db, clock) = clocked_db([settings]);
+        let app = router(Arc::new(Mutex::new(db)));
+        let run = r#"{"Seconds":5}"#.to_string();
+
+        let uri = "/plants/0/actuators/0/run";
+        let response = send(&app, Method::POST, uri, run.clone()).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        clock.advance(plant_common::MIN_RUN_INTERVAL);
+        let reply: Reply = read(send(&app, Method::POST, uri, run.clone()).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Started));
+        assert!(reply.state.plants[0].actuators[0].running);
+
+        let uri = "/plants/0/actuators/0/stop";
+        let reply: Reply = read(send(&app, Method::POST, uri, String::new()).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Stopped));
+        assert!(!reply.state.plants[0].actuators[0].running);
+        let uri = "/plants/0/actuators/1/run";
+        let response = send(&app, Method::POST, uri, run).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let uri = "/plants/0/controller/reset";
+        let response = send(&app, Method::POST, uri, String::new()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn rename_board() {
+        let app = app();
+        let body = r#"{"name":"Kitchen"}"#.to_string();
+        let reply: Reply = read(send(&app, Method::PATCH, "/board", body).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Updated));
+        let board: BoardState = read(send(&app, Method::GET, "/board", String::new()).await).await;
+        assert_eq!(board.name, "Kitchen");
+
+        let body = r#"{"location":"Living room","color":[200,60,60],"icon":"🪴"}"#.to_string();
+        let reply: Reply = read(send(&app, Method::PATCH, "/board", body).await).await;
+        assert_eq!(reply.state.metadata.location, "Living room");
+        assert_eq!(reply.state.metadata.color, Some([200, 60, 60]));
+        let body = r#"{"icon":null}"#.to_string();
+        let reply: Reply = read(send(&app, Method::PATCH, "/board", body).await).await;
+        assert_eq!(reply.state.metadata.icon, None);
+        assert_eq!(reply.state.metadata.location, "Living room");
+        let body = r#"{"icon":""}"#.to_string();
+        let response = send(&app, Method::PATCH, "/board", body).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    /// Reads the next update of a live stream, each event arrives in its own chunk.
+    async fn next_update(stream: &mut axum::body::BodyDataStream) -> LiveUpdate {
+        let chunk = stream.next().await.unwrap().unwrap();
+        let text = std::str::from_utf8(&chunk).unwrap();
+        let data = text.trim_end().strip_prefix("data: ").unwrap();
+        serde_json::from_str(data).unwrap()
+    }
+
+    #[tokio::test]
+    async fn live_updates() {
+        let plants = Arc::new(Mutex::new(PlantDB::new(MemoryStore::new())));
+        let app = router(Arc::clone(&plants));
+        let response = send(&app, Method::GET, "/live", String::new()).await;
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        let mut stream = response.into_body().into_data_stream();
+        let LiveUpdate::State(state) = next_update(&mut stream).await else {
+            panic!("the stream doesn't start with the state");
+        };
+        assert!(state.plants.is_empty());
+
+        let body = r#"{"name":"Basil","connection":{"GPIO":32}}"#.to_string();
+        send(&app, Method::POST, "/plants", body).await;
+        let LiveUpdate::State(state) = next_update(&mut stream).await else {
+            panic!("adding a plant doesn't send the state");
+        };
+        assert_eq!(state.plants[0].name, "Basil");
+
+        let mut sensors = FixedSensors::new();
+        sensors.set(Connector::GPIO(32), 1200.0);
+        measure_once(&mut sensors, &mut *plants.lock().await).await;
+        let LiveUpdate::Plant(plant) = next_update(&mut stream).await else {
+            panic!("a measurement doesn't send the plant");
+        };
+        assert_eq!(plant.id, 0);
+        assert!(plant.measured_at.is_some());
+
+        // the same reading again isn't sent, the next update is the event
+        measure_once(&mut sensors, &mut *plants.lock().await).await;
+        send(&app, Method::POST, "/plants/0/watered", "{}".to_string()).await;
+        let LiveUpdate::Event(event) = next_update(&mut stream).await else {
+            panic!("an event isn't sent");
+        };
+        assert_eq!(
+            event.kind,
+            PlantEventKind::WateredByHand { amount_ml: None }
+        );
+    }
+
+    #[tokio::test]
+    async fn change_sntp_server() {
+        let (db, clock) = clocked_db([]);
+        let app = router(Arc::new(Mutex::new(db)));
+        assert_eq!(clock.server().unwrap(), "pool.ntp.org");
+
+        let body = r#"{"sntp_server":"192.168.1.1"}"#.to_string();
+        let reply: Reply = read(send(&app, Method::PATCH, "/board", body).await).await;
+        assert_eq!(reply.status, ReplyStatus::Ok(OkStatus::Updated));
+        assert_eq!(reply.state.sntp_server.unwrap(), "192.168.1.1");
+        assert_eq!(clock.server().unwrap(), "192.168.1.1");
+
+        let body = r#"{"sntp_server":"not a host"}"#.to_string();
+        let response = send(&app, Method::PATCH, "/board", body).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(clock.server().unwrap(), "192.168.1.1");
+    }
+}
@@ -0,0 +1,187 @@
+//! Detection of waterings from steps in the readings of a sensor.
+
+use plant_common::{Calibration, Timestamp};
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+
+/// Rise of the calibrated sensor reading in percentage points that counts as a watering.
+const STEP_PERCENT: f32 = 10.0;
+/// Number of the oldest buffered samples averaged as the reading before a watering.
+const BASELINE_SAMPLES: usize = 10;
+/// Number of the newest samples averaged as the current reading.
+const RECENT_SAMPLES: usize = 5;
+/// Samples to wait after a step for the water to soak in, before the moisture after is taken.
+const SETTLE_SAMPLES: u32 = 3 * 60;
+/// Samples after a watering during which no new watering is detected.
+const COOLDOWN_SAMPLES: u32 = 15 * 60;
+
+/// A watering found in the readings, with the averaged readings before and after it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Watering {
+    pub time: Timestamp,
+    pub before: f32,
+    pub after: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+enum State {
+    #[default]
+    Idle,
+    /// A step was seen, the readings are still rising.
+    Settling {
+        time: Timestamp,
+        before: f32,
+        samples: u32,
+    },
+    CoolingDown {
+        samples: u32,
+    },
+}
+
+/// Looks for steps in the readings of a plant, it expects one new sample per call.
+#[derive(Clone, Debug, Default)]
+pub struct WateringDetector {
+    state: State,
+}
+
+impl WateringDetector {
+    /// Checks the newest sample, returns the watering once the readings settled after a step.
+    ///
+    /// While a dose of the watering controller soaks in, `dosing` is set and nothing is detected,
+    /// the controller already records its doses.
+    pub fn update(
+        &mut self,
+        calibration: &Calibration,
+        values: &AllocRingBuffer<f32>,
+        dosing: bool,
+        now: Timestamp,
+    ) -> Option<Watering> {
+        if dosing {
+            // the step of the dose is still in the buffer once it soaked in
+            self.state = State::CoolingDown { samples: 0 };
+            return None;
+        }
+        let average = |skip: usize, take: usize| {
+            let sum: f32 = values.iter().skip(skip).take(take).sum();
+            sum / take as f32
+        };
+        let len = values.len();
+        if len < BASELINE_SAMPLES + RECENT_SAMPLES {
+            return None;
+        }
+        let recent = average(len - RECENT_SAMPLES, RECENT_SAMPLES);
+        match &mut self.state {
+            State::Idle => {
+                let before = average(0, BASELINE_SAMPLES);
+                let rise = calibration.percent(recent)? - calibration.percent(before)?;
+                if rise >= STEP_PERCENT {
+                    self.state = State::Settling {
+                        time: now,
+                        before,
+                        samples: 0,
+                    };
+                }
+                None
+            }
+            State::Settling {
+                time,
+                before,
+                samples,
+            } => {
+                *samples += 1;
+                if *samples < SETTLE_SAMPLES {
+                    return None;
+                }
+                // readings that fell back were only a spike
+                if calibration.percent(recent)? - calibration.percent(*before)? < STEP_PERCENT {
+                    self.state = State::Idle;
+                    return None;
+                }
+                let watering = Watering {
+                    time: *time,
+                    before: *before,
+                    after: recent,
+                };
+                self.state = State::CoolingDown { samples: 0 };
+                Some(watering)
+            }
+            State::CoolingDown { samples } => {
+                *samples += 1;
+                if *samples >= COOLDOWN_SAMPLES {
+                    self.state = State::Idle;
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: Timestamp = Timestamp {
+        seconds: 1_700_000_000,
+        synced: true,
+    };
+
+    /// Reading of a sensor from 3000 mV in the air to 0 mV in water.
+    fn voltage(percent: f32) -> f32 {
+        3000.0 - percent * 30.0
+    }
+
+    /// Feeds one sample per second, returns the waterings found.
+    fn feed(
+        detector: &mut WateringDetector,
+        values: &mut AllocRingBuffer<f32>,
+        percent: f32,
+        samples: u64,
+        dosing: bool,
+    ) -> Vec<Watering> {
+        let calibration = Calibration::two_point(3000.0, 0.0);
+        let mut waterings = vec![];
+        for _ in 0..samples {
+            values.push(voltage(percent));
+            let now = Timestamp {
+                seconds: START.seconds + values.len() as u64,
+                ..START
+            };
+            waterings.extend(detector.update(&calibration, values, dosing, now));
+        }
+        waterings
+    }
+
+    #[test]
+    fn step_is_detected_once_settled() {
+        let mut detector = WateringDetector::default();
+        let mut values = AllocRingBuffer::new(60);
+        assert!(feed(&mut detector, &mut values, 20.0, 60, false).is_empty());
+        assert!(feed(&mut detector, &mut values, 50.0, 100, false).is_empty());
+        let waterings = feed(&mut detector, &mut values, 50.0, 200, false);
+        let [Watering { before, after, .. }] = waterings[..] else {
+            panic!("unexpected waterings {:?}", waterings);
+        };
+        assert_eq!(before, voltage(20.0));
+        assert_eq!(after, voltage(50.0));
+        // the readings stay up, but it is the same watering
+        assert!(feed(&mut detector, &mut values, 50.0, 900, false).is_empty());
+    }
+
+    #[test]
+    fn spike_is_not_a_watering() {
+        let mut detector = WateringDetector::default();
+        let mut values = AllocRingBuffer::new(60);
+        feed(&mut detector, &mut values, 20.0, 60, false);
+        feed(&mut detector, &mut values, 50.0, 10, false);
+        assert!(feed(&mut detector, &mut values, 20.0, 600, false).is_empty());
+    }
+
+    #[test]
+    fn doses_of_the_controller_are_skipped() {
+        let mut detector = WateringDetector::default();
+        let mut values = AllocRingBuffer::new(60);
+        feed(&mut detector, &mut values, 20.0, 60, false);
+        assert!(feed(&mut detector, &mut values, 50.0, 30, true).is_empty());
+        // a short soak leaves the step in the buffer
+        assert!(feed(&mut detector, &mut values, 50.0, 600, false).is_empty());
+    }
+}
@@ -0,0 +1,479 @@
+//! The Wi-Fi networks a board knows and when it opens its access point for provisioning.
+//!
+//! The board tries its known networks in turn. If it has none or can't join any of them for a
+//! while, it opens an open access point with a [provisioning API](crate::server) at
+//! [`PROVISIONING_ADDRESS`](plant_common::PROVISIONING_ADDRESS) and keeps retrying meanwhile.
+//! The driver of the radio asks [`WifiSupervisor::next_action`] what to do and reports back.
+
+use log::error;
+use plant_common::{
+    ErrStatus, KnownNetwork, WifiMode, WifiNetwork, MAX_SSID_LENGTH, MAX_WIFI_NETWORKS,
+};
+
+use crate::hal::{KeyValueStore, StorageError};
+
+const KEY: &str = "wifi_networks";
+/// Size of the stored networks, a network takes at most 100 bytes.
+const BUFFER_SIZE: usize = MAX_WIFI_NETWORKS * 100 + 8;
+/// Seconds the board tries to join its networks before it opens its access point.
+pub const JOIN_TIMEOUT: u64 = 60;
+/// Seconds between two rounds through the known networks while the access point is open.
+pub const RETRY_INTERVAL: u64 = 5 * 60;
+/// Seconds between two attempts to join while the access point is closed.
+const RETRY_DELAY: u64 = 2;
+
+/// The networks the board joins, in the order they are tried.
+#[derive(Clone, Debug, Default)]
+pub struct WifiNetworks {
+    networks: Vec<WifiNetwork>,
+}
+
+impl WifiNetworks {
+    /// Reads the stored networks, there are none if they can't be read.
+    pub fn load<K: KeyValueStore>(nvs: &K) -> WifiNetworks {
+        let mut buf = vec![0; BUFFER_SIZE];
+        let networks = match nvs.get_raw(KEY, &mut buf) {
+            Ok(Some(bytes)) => postcard::from_bytes(bytes).unwrap_or_else(|e| {
+                error!("Cannot decode the Wi-Fi networks: {:?}.", e);
+                Vec::new()
+            }),
+            Ok(None) => Vec::new(),
+            Err(e) => {
+                error!("Cannot read the Wi-Fi networks: {}.", e);
+                Vec::new()
+            }
+        };
+        WifiNetworks { networks }
+    }
+
+    #[must_use]
+    pub fn networks(&self) -> &[WifiNetwork] {
+        &self.networks
+    }
+
+    /// The networks without their passwords.
+    #[must_use]
+    pub fn known(&self) -> Vec<KnownNetwork> {
+        self.networks
+            .iter()
+            .map(|network| KnownNetwork {
+                ssid: network.ssid.clone(),
+                secured: !network.password.is_empty(),
+            })
+            .collect()
+    }
+
+    /// Adds a network or replaces the password of a known one, returns whether it was known.
+    pub fn add<K: KeyValueStore>(
+        &mut self,
+        nvs: &mut K,
+        network: WifiNetwork,
+    ) -> Result<bool, ErrStatus> {
+        check_network(&network)?;
+        let mut networks = self.networks.clone();
+        let known = networks.iter().position(|n| n.ssid == network.ssid);
+        match known {
+            Some(index) => networks[index] = network,
+            None if networks.len() >= MAX_WIFI_NETWORKS => return Err(ErrStatus::TooManyNetworks),
+            None => networks.push(network),
+        }
+        store(nvs, &networks)?;
+        self.networks = networks;
+        Ok(known.is_some())
+    }
+
+    pub fn remove<K: KeyValueStore>(&mut self, nvs: &mut K, ssid: &str) -> Result<(), ErrStatus> {
+        let index = self
+            .networks
+            .iter()
+            .position(|n| n.ssid == ssid)
+            .ok_or_else(|| ErrStatus::NetworkNotFound(ssid.to_string()))?;
+        let mut networks = self.networks.clone();
+        networks.remove(index);
+        store(nvs, &networks)?;
+        self.networks = networks;
+        Ok(())
+    }
+}
+
+fn store<K: KeyValueStore>(nvs: &mut K, networks: &[WifiNetwork]) -> Result<(), StorageError> {
+    let mut buf = vec![0; BUFFER_SIZE];
+    let bytes =
+        postcard::to_slice(networks, &mut buf).map_err(|e| StorageError::Failed(e.to_string()))?;
+    nvs.set_raw(KEY, bytes)
+}
+
+fn check_network(network: &WifiNetwork) -> Result<(), ErrStatus> {
+    if network.ssid.is_empty() || network.ssid.len() > MAX_SSID_LENGTH {
+        return Err(ErrStatus::InvalidSsid);
+    }
+    let password = &network.password;
+    let passphrase = (8..=63).contains(&password.len())
+        && password
+            .chars()
+            .all(|c| c.is_ascii() && !c.is_ascii_control());
+    let hex_key = password.len() == 64 && password.chars().all(|c| c.is_ascii_hexdigit());
+    if password.is_empty() || passphrase || hex_key {
+        Ok(())
+    } else {
+        Err(ErrStatus::InvalidPassword)
+    }
+}
+
+/// The SSID of the access point of a board, `Plant ` followed by as much of its name as fits.
+#[must_use]
+pub fn access_point_ssid(board_name: &str) -> String {
+    let mut ssid = "Plant ".to_string();
+    for c in board_name.chars() {
+        if ssid.len() + c.len_utf8() > MAX_SSID_LENGTH {
+            break;
+        }
+        ssid.push(c);
+    }
+    ssid
+}
+
+/// What the driver of the radio should do next.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WifiAction {
+    /// Try to join the network, then report [`WifiEvent::Joined`] or [`WifiEvent::JoinFailed`].
+    Join(WifiNetwork),
+    /// Open the access point with the SSID, keeping the station running.
+    OpenAccessPoint(String),
+    /// The board joined a network, the access point isn't needed anymore.
+    CloseAccessPoint,
+    /// Nothing to do, ask again in a second or once something changed.
+    Wait,
+}
+
+/// What happened to the connection, reported by the driver of the radio.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WifiEvent {
+    Joined(String),
+    JoinFailed,
+    /// The connection to the network was lost.
+    Disconnected,
+    AccessPointOpened(String),
+    AccessPointClosed,
+}
+
+/// Decides when to join which network and when to open the access point.
+///
+/// Times are seconds since the start of the board.
+#[derive(Clone, Debug, Default)]
+pub struct WifiSupervisor {
+    /// `false` until the driver starts, the board doesn't manage its network before.
+    managed: bool,
+    connected: Option<String>,
+    access_point: Option<String>,
+    /// Index of the network tried next.
+    next: usize,
+    /// Since when the board has no connection.
+    offline_since: u64,
+    retry_at: u64,
+}
+
+impl WifiSupervisor {
+    /// Starts managing the network, the board isn't connected yet.
+    pub fn start(&mut self, now: u64) {
+        *self = WifiSupervisor {
+            managed: true,
+            offline_since: now,
+            retry_at: now,
+            ..Default::default()
+        };
+    }
+
+    #[must_use]
+    pub fn mode(&self) -> WifiMode {
+        match (&self.connected, &self.access_point) {
+            _ if !self.managed => WifiMode::Unmanaged,
+            (Some(ssid), _) => WifiMode::Connected { ssid: ssid.clone() },
+            (None, Some(ssid)) => WifiMode::AccessPoint { ssid: ssid.clone() },
+            (None, None) => WifiMode::Joining,
+        }
+    }
+
+    /// Whether the access point is open, it has no password so anyone in range can connect.
+    #[must_use]
+    pub fn access_point_open(&self) -> bool {
+        self.access_point.is_some()
+    }
+
+    /// The next thing to do with the known `networks`, `ssid` is the one of the access point.
+    pub fn next_action(&mut self, networks: &[WifiNetwork], ssid: &str, now: u64) -> WifiAction {
+        if !self.managed {
+            return WifiAction::Wait;
+        }
+        if self.connected.is_some() {
+            return match self.access_point {
+                Some(_) => WifiAction::CloseAccessPoint,
+                None => WifiAction::Wait,
+            };
+        }
+        let timed_out = now.saturating_sub(self.offline_since) >= JOIN_TIMEOUT;
+        if self.access_point.is_none() && (networks.is_empty() || timed_out) {
+            return WifiAction::OpenAccessPoint(ssid.to_string());
+        }
+        if networks.is_empty() || now < self.retry_at {
+            return WifiAction::Wait;
+        }
+        // the networks might have been removed since the last attempt
+        let network = networks[self.next % networks.len()].clone();
+        self.next = (self.next + 1) % networks.len();
+        // a round ends after the last network, the access point waits before the next one
+        self.retry_at = if self.access_point.is_some() && self.next == 0 {
+            now + RETRY_INTERVAL
+        } else {
+            now + RETRY_DELAY
+        };
+        WifiAction::Join(network)
+    }
+
+    pub fn handle(&mut self, event: WifiEvent, now: u64) {
+        match event {
+            WifiEvent::Joined(ssid) => {
+                self.connected = Some(ssid);
+                self.next = 0;
+            }
+            WifiEvent::JoinFailed => {}
+            WifiEvent::Disconnected => {
+                if self.connected.take().is_some() {
+                    self.offline_since = now;
+                    self.retry_at = now;
+                }
+            }
+            WifiEvent::AccessPointOpened(ssid) => self.access_point = Some(ssid),
+            WifiEvent::AccessPointClosed => self.access_point = None,
+        }
+    }
+
+    /// The known networks changed, they are tried again right away.
+    pub fn networks_changed(&mut self, now: u64) {
+        self.next = 0;
+        self.retry_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::MemoryStore;
+
+    fn network(ssid: &str) -> WifiNetwork {
+        WifiNetwork {
+            ssid: ssid.to_string(),
+            password: "correct horse".to_string(),
+        }
+    }
+
+    #[test]
+    fn networks_survive_restart() {
+        let mut nvs = MemoryStore::new();
+        let mut networks = WifiNetworks::load(&nvs);
+        assert_eq!(networks.add(&mut nvs, network("home")), Ok(false));
+        assert_eq!(networks.add(&mut nvs, network("office")), Ok(false));
+        let replaced = WifiNetwork {
+            password: String::new(),
+            ..network("home")
+        };
+        assert_eq!(networks.add(&mut nvs, replaced.clone()), Ok(true));
+        networks.remove(&mut nvs, "office").unwrap();
+
+        let networks = WifiNetworks::load(&nvs);
+        assert_eq!(networks.networks(), [replaced]);
+        assert_eq!(
+            networks.known(),
+            [KnownNetwork {
+                ssid: "home".to_string(),
+                secured: false
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_networks_are_rejected() {
+        let mut nvs = MemoryStore::new();
+        let mut networks = WifiNetworks::load(&nvs);
+        let add =
+            |networks: &mut WifiNetworks, nvs: &mut MemoryStore, ssid: &str, password: &str| {
+                let network = WifiNetwork {
+                    ssid: ssid.to_string(),
+                    password: password.to_string(),
+                };
+                networks.add(nvs, network)
+            };
+        assert_eq!(
+            add(&mut networks, &mut nvs, "", "password"),
+            Err(ErrStatus::InvalidSsid)
+        );
+        assert_eq!(
+            add(&mut networks, &mut nvs, &"x".repeat(33), "password"),
+            Err(ErrStatus::InvalidSsid)
+        );
+        assert_eq!(
+            add(&mut networks, &mut nvs, "home", "short"),
+            Err(ErrStatus::InvalidPassword)
+        );
+        assert_eq!(
+            add(&mut networks, &mut nvs, "home", &"g".repeat(64)),
+            Err(ErrStatus::InvalidPassword)
+        );
+        assert_eq!(
+            add(&mut networks, &mut nvs, "home", &"a".repeat(64)),
+            Ok(false)
+        );
+        for i in 1..MAX_WIFI_NETWORKS {
+            networks.add(&mut nvs, network(&i.to_string())).unwrap();
+        }
+        assert_eq!(
+            networks.add(&mut nvs, network("one too many")),
+            Err(ErrStatus::TooManyNetworks)
+        );
+        assert_eq!(
+            networks.remove(&mut nvs, "unknown"),
+            Err(ErrStatus::NetworkNotFound("unknown".to_string()))
+        );
+    }
+
+    #[test]
+    fn full_storage_keeps_the_networks() {
+        let mut nvs = MemoryStore::with_capacity(0);
+        let mut networks = WifiNetworks::default();
+        assert_eq!(
+            networks.add(&mut nvs, network("home")),
+            Err(ErrStatus::StorageFull)
+        );
+        assert!(networks.networks().is_empty());
+    }
+
+    #[test]
+    fn access_point_ssid_fits() {
+        assert_eq!(access_point_ssid("Kitchen"), "Plant Kitchen");
+        let ssid = access_point_ssid(&"ü".repeat(20));
+        assert!(ssid.len() <= MAX_SSID_LENGTH);
+        assert!(ssid.ends_with('ü'));
+    }
+
+    #[test]
+    fn without_networks_the_access_point_opens() {
+        let mut supervisor = WifiSupervisor::default();
+        assert_eq!(supervisor.mode(), WifiMode::Unmanaged);
+        assert_eq!(supervisor.next_action(&[], "ap", 0), WifiAction::Wait);
+        supervisor.start(0);
+        assert_eq!(supervisor.mode(), WifiMode::Joining);
+        assert_eq!(
+            supervisor.next_action(&[], "ap", 0),
+            WifiAction::OpenAccessPoint("ap".to_string())
+        );
+        supervisor.handle(WifiEvent::AccessPointOpened("ap".to_string()), 0);
+        assert_eq!(
+            supervisor.mode(),
+            WifiMode::AccessPoint {
+                ssid: "ap".to_string()
+            }
+        );
+        assert_eq!(supervisor.next_action(&[], "ap", 1000), WifiAction::Wait);
+
+        // a network is provisioned
+        let networks = [network("home")];
+        supervisor.networks_changed(1000);
+        assert_eq!(
+            supervisor.next_action(&networks, "ap", 1000),
+            WifiAction::Join(network("home"))
+        );
+        supervisor.handle(WifiEvent::Joined("home".to_string()), 1005);
+        assert_eq!(
+            supervisor.next_action(&networks, "ap", 1005),
+            WifiAction::CloseAccessPoint
+        );
+        supervisor.handle(WifiEvent::AccessPointClosed, 1005);
+        assert_eq!(
+            supervisor.next_action(&networks, "ap", 1005),
+            WifiAction::Wait
+        );
+        assert_eq!(
+            supervisor.mode(),
+            WifiMode::Connected {
+                ssid: "home".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn networks_are_tried_in_turn_until_the_timeout() {
+        let networks = [network("home"), network("office")];
+        let mut supervisor = WifiSupervisor::default();
+        supervisor.start(0);
+        let mut now = 0;
+        let mut tried = vec![];
+        while now < JOIN_TIMEOUT {
+            if let WifiAction::Join(network) = supervisor.next_action(&networks, "ap", now) {
+                tried.push(network.ssid);
+                supervisor.handle(WifiEvent::JoinFailed, now);
+            }
+            now += 1;
+        }
+        assert!(tried.len() > 2);
+        assert!(tried.iter().step_by(2).all(|ssid| ssid == "home"));
+        assert!(tried.iter().skip(1).step_by(2).all(|ssid| ssid == "office"));
+        assert_eq!(
+            supervisor.next_action(&networks, "ap", now),
+            WifiAction::OpenAccessPoint("ap".to_string())
+        );
+        supervisor.handle(WifiEvent::AccessPointOpened("ap".to_string()), now);
+
+        // one round through the networks, then a break
+        for ssid in ["home", "office"] {
+            now += RETRY_DELAY;
+            assert_eq!(
+                supervisor.next_action(&networks, "ap", now),
+                WifiAction::Join(network(ssid))
+            );
+            supervisor.handle(WifiEvent::JoinFailed, now);
+        }
+        now += RETRY_DELAY;
+        assert_eq!(
+            supervisor.next_action(&networks, "ap", now),
+            WifiAction::Wait
+        );
+        now += RETRY_INTERVAL;
+        assert_eq!(
+            supervisor.next_action(&networks, "ap", now),
+            WifiAction::Join(network("home"))
+        );
+    }
+
+    #[test]
+    fn lost_connection_is_rejoined() {
+        let networks = [network("home")];
+        let mut supervisor = WifiSupervisor::default();
+        supervisor.start(0);
+        assert_eq!(
+            supervisor.next_action(&networks, "ap", 0),
+            WifiAction::Join(network("home"))
+        );
+        supervisor.handle(WifiEvent::Joined("home".to_string()), 0);
+        assert_eq!(
+            supervisor.next_action(&networks, "ap", 500),
+            WifiAction::Wait
+        );
+
+        supervisor.handle(WifiEvent::Disconnected, 1000);
+        assert_eq!(supervisor.mode(), WifiMode::Joining);
+        assert_eq!(
+            supervisor.next_action(&networks, "ap", 1000),
+            WifiAction::Join(network("home"))
+        );
+        supervisor.handle(WifiEvent::JoinFailed, 1000);
+        // the access point only opens after the timeout of the new outage
+        assert_eq!(
+            supervisor.next_action(&networks, "ap", 1001),
+            WifiAction::Wait
+        );
+        assert_eq!(
+            supervisor.next_action(&networks, "ap", 1000 + JOIN_TIMEOUT),
+            WifiAction::OpenAccessPoint("ap".to_string())
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use log::{info, warn};
+use plant_common::Connector;
+use plant_firmware::hal::{KeyValueStore, StorageError};
+use rand::rngs::StdRng;
+use rand::Rng;
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct FaultConfig {
+    /// Share of sensor readings that fail.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_rate)]
+    pub sensor_failure_rate: f64,
+    /// GPIO pins whose probe is unplugged, reading them always fails.
+    #[arg(long, value_delimiter = ',')]
+    pub disconnected: Vec<u8>,
+    /// Share of HTTP requests that are only answered after `timeout_secs`.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_rate)]
+    pub timeout_rate: f64,
+    #[arg(long, default_value_t = 30)]
+    pub timeout_secs: u64,
+    /// Share of storage writes that fail.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_rate)]
+    pub storage_failure_rate: f64,
+    /// Overwrites a stored plant with erased flash before the board starts.
+    #[arg(long)]
+    pub corrupt_storage: bool,
+    /// The board never reaches its SNTP server, so times are relative to its start.
+    #[arg(long)]
+    pub unsynced_clock: bool,
+}
+
+/// Accepts shares from 0 to 1, others would make every draw panic.
+fn parse_rate(value: &str) -> Result<f64, String> {
+    let rate: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if (0.0..=1.0).contains(&rate) {
+        Ok(rate)
+    } else {
+        Err(format!("{} is not between 0 and 1", rate))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SensorFaults {
+    pub failure_rate: f64,
+    pub disconnected: Vec<u8>,
+}
+
+impl SensorFaults {
+    pub fn check(&self, connector: &Connector, rng: &mut StdRng) -> anyhow::Result<()> {
+        match connector {
+            Connector::GPIO(pin) if self.disconnected.contains(pin) => {
+                anyhow::bail!("The probe at {} is disconnected", connector)
+            }
+            _ if rng.gen_bool(self.failure_rate) => {
+                anyhow::bail!("Reading the probe at {} failed", connector)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Wraps a store to let writes fail randomly.
+pub struct FaultyStore<K: KeyValueStore> {
+    inner: K,
+    failure_rate: f64,
+    rng: StdRng,
+}
+
+impl<K: KeyValueStore> FaultyStore<K> {
+    pub fn new(inner: K, failure_rate: f64, rng: StdRng) -> FaultyStore<K> {
+        FaultyStore {
+            inner,
+            failure_rate,
+            rng,
+        }
+    }
+
+    fn fail(&mut self, key: &str) -> Result<(), StorageError> {
+        if self.rng.gen_bool(self.failure_rate) {
+            warn!("Failing the write of '{}'", key);
+            Err(StorageError::Failed("Injected write failure".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<K: KeyValueStore> KeyValueStore for FaultyStore<K> {
+    fn get_u16(&self, key: &str) -> Result<Option<u16>, StorageError> {
+        self.inner.get_u16(key)
+    }
+
+    fn set_u16(&mut self, key: &str, value: u16) -> Result<(), StorageError> {
+        self.fail(key)?;
+        self.inner.set_u16(key, value)
+    }
+
+    fn get_raw<'a>(&self, key: &str, buf: &'a mut [u8]) -> Result<Option<&'a [u8]>, StorageError> {
+        self.inner.get_raw(key, buf)
+    }
+
+    fn set_raw(&mut self, key: &str, value: &[u8]) -> Result<(), StorageError> {
+        self.fail(key)?;
+        self.inner.set_raw(key, value)
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), StorageError> {
+        self.fail(key)?;
+        self.inner.remove(key)
+    }
+}
+
+/// Overwrites a random stored plant with erased flash.
+pub fn corrupt_storage<K: KeyValueStore>(store: &mut K, rng: &mut StdRng) -> anyhow::Result<()> {
+    let count = store.get_u16("plant_count")?.unwrap_or(0);
+    if count == 0 {
+        warn!("There is no stored plant to corrupt");
+        return Ok(());
+    }
+    let index = rng.gen_range(0..count);
+    info!("Corrupting stored plant {}", index);
+    store.set_raw(&format!("plant_{}", index), &[0xFF; 16])?;
+    Ok(())
+}
+
+/// Delays the response of a share of the requests, so clients run into their timeouts.
+pub async fn delay_requests(
+    timeout_rate: f64,
+    timeout: Duration,
+    request: Request,
+    next: Next,
+) -> Response {
+    if rand::thread_rng().gen_bool(timeout_rate) {
+        warn!(
+            "Delaying {} {} by {:?}",
+            request.method(),
+            request.uri(),
+            timeout
+        );
+        tokio::time::sleep(timeout).await;
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use plant_firmware::host::MemoryStore;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn rates_are_limited() {
+        assert_eq!(parse_rate("0.25"), Ok(0.25));
+        assert_eq!(parse_rate("1"), Ok(1.0));
+        assert!(parse_rate("5").is_err());
+        assert!(parse_rate("-0.1").is_err());
+        assert!(parse_rate("NaN").is_err());
+        assert!(parse_rate("often").is_err());
+    }
+
+    #[test]
+    fn sensor_faults() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let faults = SensorFaults {
+            failure_rate: 0.0,
+            disconnected: vec![33],
+        };
+        assert!(faults.check(&Connector::GPIO(32), &mut rng).is_ok());
+        assert!(faults.check(&Connector::GPIO(33), &mut rng).is_err());
+        let faults = SensorFaults {
+            failure_rate: 1.0,
+            disconnected: Vec::new(),
+        };
+        assert!(faults.check(&Connector::GPIO(32), &mut rng).is_err());
+    }
+
+    #[test]
+    fn faulty_store() {
+        let rng = StdRng::seed_from_u64(0);
+        let mut store = FaultyStore::new(MemoryStore::new(), 0.0, rng.clone());
+        store.set_u16("count", 3).unwrap();
+        store.set_raw("name", b"Basil").unwrap();
+        assert_eq!(store.get_u16("count").unwrap(), Some(3));
+        store.remove("count").unwrap();
+        assert_eq!(store.get_u16("count").unwrap(), None);
+
+        let mut store = FaultyStore::new(store.inner, 1.0, rng);
+        assert!(store.set_u16("count", 4).is_err());
+        assert!(store.set_raw("name", b"Fern").is_err());
+        assert!(store.remove("name").is_err());
+        // reads still work and the failed writes changed nothing
+        let buf = &mut [0; 16];
+        assert_eq!(store.get_raw("name", buf).unwrap(), Some(&b"Basil"[..]));
+        assert_eq!(store.get_u16("count").unwrap(), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Simulates plant boards on a PC, serving the same HTTP API as the firmware.
+//!
+//! Every `--listen` address gets its own board. Add them to the GUI with their port, like
+//! `127.0.0.1:8081`, so several simulated boards can share one loopback address.
+//! The boards answer the discovery probes of the GUI like real ones.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::extract::Request;
+use axum::middleware::{self, Next};
+use clap::Parser;
+use log::{info, warn};
+use plant_common::{Connector, PlantSettings, SoilType, DISCOVERY_PORT};
+use plant_firmware::hal::KeyValueStore;
+use plant_firmware::host::{FileStore, MemoryOutputs, MemoryStore, SystemClock};
+use plant_firmware::plant_db::PlantDB;
+use plant_firmware::{actuator, discovery, pins, plant, server};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+
+use faults::{FaultConfig, FaultyStore, SensorFaults};
+use sensors::{PouredWater, SensorConfig, SimulatedSensors};
+
+mod faults;
+mod sensors;
+
+#[derive(Clone, Debug, Parser)]
+#[command(about)]
+struct Args {
+    /// Address to serve a board on, can be given multiple times.
+    #[arg(short, long, default_value = "127.0.0.1:8080")]
+    listen: Vec<SocketAddr>,
+    /// Keeps the storage of every board in a subdirectory, otherwise it is lost on exit.
+    #[arg(long)]
+    data_dir: Option<PathBuf>,
+    /// Number of plants created on boards without plants.
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u16).range(0..=14))]
+    plants: u16,
+    /// Seed of the simulation, boards get consecutive seeds.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Don't answer discovery probes of the GUI.
+    #[arg(long)]
+    no_discovery: bool,
+    #[command(flatten)]
+    sensors: SensorConfig,
+    #[command(flatten)]
+    faults: FaultConfig,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+
+    let mut boards = JoinSet::new();
+    for (index, addr) in args.listen.iter().enumerate() {
+        let rng = StdRng::seed_from_u64(args.seed + index as u64);
+        match &args.data_dir {
+            Some(dir) => {
+                let dir = dir.join(addr.to_string().replace([':', '.', '[', ']'], "_"));
+                let store = FileStore::new(&dir)
+                    .with_context(|| format!("Cannot create {}", dir.display()))?;
+                boards.spawn(run_board(store, *addr, args.clone(), rng))
+            }
+            None => boards.spawn(run_board(MemoryStore::new(), *addr, args.clone(), rng)),
+        };
+    }
+    while let Some(board) = boards.join_next().await {
+        board??;
+    }
+    Ok(())
+}
+
+async fn run_board<K: KeyValueStore + 'static>(
+    mut store: K,
+    addr: SocketAddr,
+    args: Args,
+    mut rng: StdRng,
+) -> anyhow::Result<()> {
+    seed_plants(&mut PlantDB::new(&mut store), args.plants);
+    if args.faults.corrupt_storage {
+        faults::corrupt_storage(&mut store, &mut rng)?;
+    }
+
+    let store = FaultyStore::new(
+        store,
+        args.faults.storage_failure_rate,
+        StdRng::from_rng(&mut rng)?,
+    );
+    let clock = if args.faults.unsynced_clock {
+        SystemClock::unsynced()
+    } else {
+        SystemClock::new()
+    };
+    let mut db = PlantDB::with_clock(store, Arc::new(clock));
+    // simulated boards are told apart by their port until they are renamed
+    db.set_default_name(&format!("Simulated {}", addr.port()));
+    let plants = Arc::new(Mutex::new(db));
+    let poured = PouredWater::default();
+    let sensors = SimulatedSensors::new(
+        args.sensors,
+        SensorFaults {
+            failure_rate: args.faults.sensor_failure_rate,
+            disconnected: args.faults.disconnected,
+        },
+        poured.clone(),
+        StdRng::from_rng(&mut rng)?,
+    );
+    tokio::spawn(plant::measure_plants(sensors, plants.clone()));
+    let outputs = MemoryOutputs::new();
+    tokio::spawn(actuator::drive_actuators(outputs.clone(), plants.clone()));
+    tokio::spawn(sensors::follow_actuators(plants.clone(), outputs, poured));
+    if !args.no_discovery {
+        match discovery_socket() {
+            Ok(socket) => {
+                tokio::spawn(discovery::answer_probes(socket, plants.clone(), addr));
+            }
+            Err(e) => warn!("Board on {} can't be discovered: {}", addr, e),
+        }
+    }
+
+    let timeout_rate = args.faults.timeout_rate;
+    let timeout = Duration::from_secs(args.faults.timeout_secs);
+    let app =
+        server::router(plants).layer(middleware::from_fn(move |request: Request, next: Next| {
+            faults::delay_requests(timeout_rate, timeout, request, next)
+        }));
+    info!("Serving a board on http://{}", addr);
+    axum_server::bind(addr)
+        .serve(app.into_make_service())
+        .await
+        .with_context(|| format!("Cannot serve on {}", addr))
+}
+
+/// Binds a socket for discovery probes, all simulated boards on the host receive broadcasts.
+fn discovery_socket() -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::from(([0, 0, 0, 0], DISCOVERY_PORT)).into())?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// Creates `count` plants on the ADC1 pins, then on a multiplexed I2C bus.
+fn seed_plants<K: KeyValueStore>(db: &mut PlantDB<K>, count: u16) {
+    if !db.get_plants().is_empty() {
+        return;
+    }
+    let gpios = (0..pins::GPIO_COUNT)
+        .map(Connector::GPIO)
+        .filter(pins::is_readable);
+    let channels = (0..pins::MULTIPLEXER_CHANNELS).map(|channel| Connector::I2c {
+        bus: 0,
+        address: Connector::SEESAW_ADDRESS,
+        channel: Some(channel),
+    });
+    for (i, connection) in gpios.chain(channels).take(count as usize).enumerate() {
+        let soil = SoilType::ALL[i % SoilType::ALL.len()].clone();
+        let settings = PlantSettings {
+            name: format!("Plant {}", i + 1),
+            connection,
+            soil,
+            pot_volume: Some(1000.0),
+            ..Default::default()
+        };
+        db.create_plant(settings).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::info;
+use plant_common::Connector;
+use plant_firmware::hal::{KeyValueStore, SensorReading, SensorSource};
+use plant_firmware::host::MemoryOutputs;
+use plant_firmware::plant_db::PlantDB;
+use rand::rngs::StdRng;
+use rand::Rng;
+use tokio::sync::Mutex;
+
+use crate::faults::SensorFaults;
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum DryingCurve {
+    /// The pot loses the same amount of water every hour.
+    Linear,
+    /// The pot loses a fixed share of its remaining water every hour, wet soil dries faster.
+    Exponential,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct SensorConfig {
+    /// Hours until a freshly watered pot is dry, varies by ±20 % between plants.
+    #[arg(long, default_value_t = 72.0)]
+    pub dry_hours: f32,
+    #[arg(long, value_enum, default_value_t = DryingCurve::Exponential)]
+    pub curve: DryingCurve,
+    /// Amplitude of the noise added to every reading, in raw sensor units.
+    #[arg(long, default_value_t = 20.0)]
+    pub noise: f32,
+    /// Speeds up the simulated time, 3600 makes a second pass like an hour.
+    #[arg(long, default_value_t = 1.0)]
+    pub time_scale: f32,
+    /// Waters a pot once its water level falls below this share, never waters if not set.
+    #[arg(long)]
+    pub water_below: Option<f32>,
+}
+
+/// Water that fills a dry pot, the seeded plants have 1 l pots.
+const POT_CAPACITY_ML: f32 = 400.0;
+/// Flow of actuators without a measured flow rate, in ml/s.
+const DEFAULT_FLOW_RATE: f32 = 20.0;
+
+/// A simulated pot with a probe in it.
+struct Probe {
+    /// Water level between 0 (dry) and 1 (freshly watered).
+    water: f32,
+    dry_hours: f32,
+    last_update: Instant,
+}
+
+/// Soil sensors of simulated pots, a pot is created on the first read of a connector.
+pub struct SimulatedSensors {
+    config: SensorConfig,
+    faults: SensorFaults,
+    probes: HashMap<Connector, Probe>,
+    poured: PouredWater,
+    rng: StdRng,
+}
+
+/// Water in ml poured into the pots since their last reading, by the connector of their probe.
+#[derive(Clone, Debug, Default)]
+pub struct PouredWater(Arc<std::sync::Mutex<HashMap<Connector, f32>>>);
+
+impl PouredWater {
+    pub fn pour(&self, connector: &Connector, amount_ml: f32) {
+        *self.0.lock().unwrap().entry(connector.clone()).or_default() += amount_ml;
+    }
+
+    fn take(&self, connector: &Connector) -> f32 {
+        self.0.lock().unwrap().remove(connector).unwrap_or_default()
+    }
+}
+
+impl SimulatedSensors {
+    pub fn new(
+        config: SensorConfig,
+        faults: SensorFaults,
+        poured: PouredWater,
+        rng: StdRng,
+    ) -> SimulatedSensors {
+        SimulatedSensors {
+            config,
+            faults,
+            probes: HashMap::new(),
+            poured,
+            rng,
+        }
+    }
+
+    fn probe(&mut self, connector: &Connector) -> &mut Probe {
+        let config = &self.config;
+        let rng = &mut self.rng;
+        self.probes
+            .entry(connector.clone())
+            .or_insert_with(|| Probe {
+                water: rng.gen_range(0.3..1.0),
+                dry_hours: config.dry_hours * rng.gen_range(0.8..1.2),
+                last_update: Instant::now(),
+            })
+    }
+
+    /// Dries the pot for the time passed since the last reading and adds the water poured into
+    /// it, returns its water level.
+    fn update(&mut self, connector: &Connector) -> f32 {
+        let curve = self.config.curve;
+        let time_scale = self.config.time_scale;
+        let water_below = self.config.water_below;
+        let poured = self.poured.take(connector);
+        let probe = self.probe(connector);
+        let hours = probe.last_update.elapsed().as_secs_f32() / 3600.0 * time_scale;
+        probe.last_update = Instant::now();
+        probe.water = match curve {
+            DryingCurve::Linear => probe.water - hours / probe.dry_hours,
+            // reaches 5 % after `dry_hours`
+            DryingCurve::Exponential => probe.water * (-hours * 20f32.ln() / probe.dry_hours).exp(),
+        };
+        probe.water = (probe.water + poured / POT_CAPACITY_ML).clamp(0.0, 1.0);
+        if water_below.is_some_and(|threshold| probe.water < threshold) {
+            info!("Watering the pot at {}", connector);
+            probe.water = 1.0;
+        }
+        probe.water
+    }
+}
+
+impl SensorSource for SimulatedSensors {
+    async fn read(&mut self, connector: &Connector) -> anyhow::Result<SensorReading> {
+        self.faults.check(connector, &mut self.rng)?;
+        let water = self.update(connector);
+        let calibration = connector.default_calibration();
+        let value = calibration.dry_voltage
+            + water * (calibration.wet_voltage - calibration.dry_voltage)
+            + self.rng.gen_range(-1.0..=1.0) * self.config.noise;
+        let temperature = match connector {
+            Connector::GPIO(_) => None,
+            Connector::I2c { .. } => Some(21.0 + self.rng.gen_range(-0.5..0.5)),
+        };
+        Ok(SensorReading {
+            value: value.max(0.0),
+            temperature,
+        })
+    }
+}
+
+/// Pours the water of the running actuators into the pots and sends the pulses of their flow
+/// sensors, so the watering controllers see the water arrive.
+pub async fn follow_actuators<K: KeyValueStore>(
+    plants: Arc<Mutex<PlantDB<K>>>,
+    outputs: MemoryOutputs,
+    poured: PouredWater,
+) {
+    const TICK: Duration = Duration::from_secs(1);
+    let mut interval = tokio::time::interval(TICK);
+    loop {
+        interval.tick().await;
+        let db = plants.lock().await;
+        for plant in db.get_plants() {
+            let info = db.get_plant_info(plant);
+            for actuator in info.actuators.iter().filter(|a| a.running) {
+                let actuator = &actuator.actuator;
+                let amount_ml =
+                    actuator.flow_rate.unwrap_or(DEFAULT_FLOW_RATE) * TICK.as_secs_f32();
+                poured.pour(&info.connection, amount_ml);
+                if let Some(flow_sensor) = &actuator.flow_sensor {
+                    let pulses = amount_ml * flow_sensor.pulses_per_litre / 1000.0;
+                    outputs.add_pulses(flow_sensor.pin, pulses.round() as u32);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn poured_water_wets_the_pot() {
+        let config = SensorConfig {
+            dry_hours: 72.0,
+            curve: DryingCurve::Linear,
+            noise: 0.0,
+            time_scale: 1.0,
+            water_below: None,
+        };
+        let faults = SensorFaults {
+            failure_rate: 0.0,
+            disconnected: Vec::new(),
+        };
+        let poured = PouredWater::default();
+        let mut sensors =
+            SimulatedSensors::new(config, faults, poured.clone(), StdRng::seed_from_u64(0));
+        let connector = Connector::GPIO(32);
+        sensors.probe(&connector).water = 0.0;
+        let dry = sensors.read(&connector).await.unwrap().value;
+
+        poured.pour(&connector, POT_CAPACITY_ML / 2.0);
+        poured.pour(&Connector::GPIO(33), POT_CAPACITY_ML);
+        let watered = sensors.read(&connector).await.unwrap().value;
+        // analog sensors read lower voltages when wet
+        assert!((dry - watered - 1500.0).abs() < 1.0, "{} {}", dry, watered);
+        // the water only arrives once
+        let later = sensors.read(&connector).await.unwrap().value;
+        assert!((later - watered).abs() < 1.0);
+    }
+}